@@ -1,2 +1,2 @@
-pub mod database;
+pub use tauritest_db as database;
 pub mod database_tauri;
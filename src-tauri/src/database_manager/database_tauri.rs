@@ -1,38 +1,119 @@
 use anyhow::{Result};
-use tauri::State;
-use tokio::sync::Mutex;
+use chrono::{Local, NaiveDate, NaiveTime};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, Window};
+use tokio::net::TcpListener;
+use tokio::sync::{Notify, OnceCell, RwLock};
 use crate::database_manager::database::{
-    Database, BlocJson, PageJson, PropsJson
+    Database, BlocJson, PageJson, PropsJson, ContentQuery, ContentMatch, BlocPosition,
+    CommentJson, PageCommentCount, ThreadJson, SyncOptions, SyncReport, ChangeFeed,
+    UndoStatus, UndoStep, TemplateJson, JournalDay, JournalOptions, TagEntity, TagJson,
+    TaggedItem, VisitJson, PageVisits, TabSession, SettingScope, SettingValue, SettingsExport,
+    SettingsImport, BlocChunk, BlocCursor, PageChunk, PageCursor, PagePreview, PageWithPreview,
+    OutlineNode, HeadingHit, PaletteCommand, PaletteHit, PaletteIndex, DuplicateReport,
+    MoveAnchor, BlocReference, PageBlocs, PropsMatch, Flashcard, CardReview, ReminderJson, ReminderScheduler,
+    SystemClock
 };
+use crate::database_manager::database::reminders::REMINDER_PROPS;
+use crate::database_manager::database::settings;
+use tauritest_crdt::CrdtStore;
 
+// Le verrou ne protège que l'initialisation : chaque commande clone le handle
+// du pool et les requêtes s'exécutent ensuite en parallèle.
 pub struct AppState {
-    db: Mutex<Option<Database>>,
+    db: RwLock<Option<Database>>,
+    // réglages globaux, communs à tous les espaces de travail
+    settings: OnceCell<Database>,
+    // index de la palette, reconstruit quand la base a changé
+    palette: RwLock<Option<PaletteIndex>>,
+    palette_commands: RwLock<Vec<PaletteCommand>>,
+    // planificateur des rappels de l'espace ouvert
+    reminders: RwLock<Option<RunningReminders>>,
+    // création des notes récurrentes au changement de jour
+    daily_notes: RwLock<Option<tauri::async_runtime::JoinHandle<()>>>,
+    // serveur de synchronisation de l'espace ouvert
+    sync_server: RwLock<Option<RunningSyncServer>>,
+}
+
+struct RunningSyncServer {
+    task: tauri::async_runtime::JoinHandle<()>,
+    port: u16,
+}
+
+struct RunningReminders {
+    task: tauri::async_runtime::JoinHandle<()>,
+    wake: Arc<Notify>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
-            db: Mutex::new(None),
+            db: RwLock::new(None),
+            settings: OnceCell::new(),
+            palette: RwLock::new(None),
+            palette_commands: RwLock::new(Vec::new()),
+            reminders: RwLock::new(None),
+            daily_notes: RwLock::new(None),
+            sync_server: RwLock::new(None),
         }
     }
 }
 
+impl AppState {
+    // retourne une copie du handle (le pool est partagé, la copie est peu coûteuse)
+    pub async fn database(&self, not_initialized: &str) -> Result<Database, String> {
+        self.db
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| not_initialized.to_string())
+    }
+
+    // base des réglages globaux, ouverte au premier usage dans le dossier de configuration
+    pub async fn settings_database(&self, app: &AppHandle) -> Result<Database, String> {
+        self.settings
+            .get_or_try_init(|| async {
+                let dir = app
+                    .path_resolver()
+                    .app_config_dir()
+                    .ok_or_else(|| "Config directory not found".to_string())?;
+                Database::new(&dir.join("settings.db").to_string_lossy())
+                    .await
+                    .map_err(|e| e.to_string())
+            })
+            .await
+            .cloned()
+    }
+}
+
 const SUCCESS:i8 = 1;
 const ERROR:i8 = -1;
 const NO_CHANGE:i8 = 0;
 
 #[tauri::command]
-pub async fn init_db(state: State<'_, AppState>, db_path: String) -> Result<(), String> {
+pub async fn init_db(app: AppHandle, state: State<'_, AppState>, db_path: String) -> Result<(), String> {
     let db = Database::new(&db_path).await.map_err(|e| e.to_string())?;
+    // note du jour des modèles récurrents
+    db.run_recurring_templates(Local::now().date_naive())
+        .await
+        .map_err(|e| e.to_string())?;
 
-    *state.db.lock().await = Some(db);
-    Ok(())
+    // le serveur servait la base précédente
+    stop_sync(&state).await;
+    *state.db.write().await = Some(db.clone());
+    *state.palette.write().await = None;
+    start_daily_notes(&app, &state, db.clone()).await;
+    start_reminders(&app, &state, db).await?;
+    // les réglages de l'espace ouvert remplacent ceux du précédent
+    emit_settings_changed(&app, &state, None).await
 }
 
 #[tauri::command]
 pub async fn new_bloc(state: State<'_, AppState>, bloc: BlocJson) -> Result<String, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
     db.new_bloc(&bloc)
         .await
@@ -41,8 +122,7 @@ pub async fn new_bloc(state: State<'_, AppState>, bloc: BlocJson) -> Result<Stri
 
 #[tauri::command]
 pub async fn update_bloc(state: State<'_, AppState>, bloc: BlocJson) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
     db.update_bloc(&bloc)
         .await
@@ -55,8 +135,7 @@ pub async fn update_bloc_content(state: State<'_, AppState>,
     new_content: String,
     updated_at: i64
 ) -> Result<i8, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
 db.update_bloc_content(id, new_content, updated_at)
     .await
@@ -74,13 +153,20 @@ db.update_bloc_content(id, new_content, updated_at)
 pub async fn update_bloc_position(state: State<'_, AppState>,
     id: String,
     new_position: String,
+    prev_id: Option<String>,
+    next_id: Option<String>,
     updated_at: i64
 ) -> Result<i8, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
-    db.update_bloc_position(id, new_position, updated_at)
-        .await
+    // avec les voisins, la clé est aussi vérifiée par rapport à eux
+    let result = if prev_id.is_some() || next_id.is_some() {
+        db.update_bloc_position_between(id, new_position, prev_id, next_id, updated_at).await
+    } else {
+        db.update_bloc_position(id, new_position, updated_at).await
+    };
+
+    result
         .map_err(|e| e.to_string())
         .and_then(|status| {
             match status {
@@ -91,10 +177,15 @@ pub async fn update_bloc_position(state: State<'_, AppState>,
         })
 }
 
+#[tauri::command]
+pub async fn rebalance_page_positions(state: State<'_, AppState>, page_id: String) -> Result<Vec<BlocPosition>, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+    db.rebalance_page_positions(page_id).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn update_bloc_page_id(state: State<'_, AppState>, id: String, new_page_id: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
     db.update_bloc_page_id(id, new_page_id)
         .await
@@ -103,8 +194,7 @@ pub async fn update_bloc_page_id(state: State<'_, AppState>, id: String, new_pag
 
 #[tauri::command]
 pub async fn delete_bloc(state: State<'_, AppState>, id: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
     db.delete_bloc(id)
         .await
@@ -113,8 +203,7 @@ pub async fn delete_bloc(state: State<'_, AppState>, id: String) -> Result<bool,
 
 #[tauri::command]
 pub async fn delete_bloc_by_page_id(state: State<'_, AppState>, page_id: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
     db.delete_bloc_by_page_id(page_id)
         .await
@@ -123,8 +212,7 @@ pub async fn delete_bloc_by_page_id(state: State<'_, AppState>, page_id: String)
 
 #[tauri::command]
 pub async fn get_checksum(state: State<'_, AppState>, id: String) -> Result<String, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
     db.get_checksum(id)
         .await
@@ -133,20 +221,29 @@ pub async fn get_checksum(state: State<'_, AppState>, id: String) -> Result<Stri
 
 #[tauri::command]
 pub async fn get_bloc_by_id(state: State<'_, AppState>, id: String) -> Result<BlocJson, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+    let db = state.database("Bloc structure not initialized").await?;
 
     db.get_bloc_by_id(id)
         .await
         .map_err(|e| e.to_string())
 }
 
+// les sources des blocs références viennent avec les blocs
+#[tauri::command]
+pub async fn get_blocs_by_page_id(state: State<'_, AppState>, page_id: String) -> Result<PageBlocs, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+
+    db.get_page_blocs(page_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// recherche de noeuds dans le contenu des blocs, ex. cases non cochées ou code rust
 #[tauri::command]
-pub async fn get_blocs_by_page_id(state: State<'_, AppState>, page_id: String) -> Result<Vec<BlocJson>, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Bloc structure not initialized".to_string())?;
+pub async fn query_bloc_nodes(state: State<'_, AppState>, query: ContentQuery) -> Result<Vec<ContentMatch>, String> {
+    let db = state.database("Bloc structure not initialized").await?;
 
-    db.get_blocs_by_page_id(page_id)
+    db.query_nodes(&query)
         .await
         .map_err(|e| e.to_string())
 }
@@ -155,8 +252,7 @@ pub async fn get_blocs_by_page_id(state: State<'_, AppState>, page_id: String) -
 // remember to call `.manage(MyState::default())`
 #[tauri::command]
 pub async fn new_page(state: tauri::State<'_, AppState>, page: PageJson) -> Result<String, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
     db.new_page(&page)
         .await
@@ -165,8 +261,7 @@ pub async fn new_page(state: tauri::State<'_, AppState>, page: PageJson) -> Resu
 
 #[tauri::command]
 pub async fn update_page(state: tauri::State<'_, AppState>, page: PageJson) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
     db.update_page(&page)
         .await
@@ -175,8 +270,7 @@ pub async fn update_page(state: tauri::State<'_, AppState>, page: PageJson) -> R
 
 #[tauri::command]
 pub async fn update_page_path(state: tauri::State<'_, AppState>, id: String, path: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
     db.update_page_path(id, path)
         .await
@@ -185,8 +279,7 @@ pub async fn update_page_path(state: tauri::State<'_, AppState>, id: String, pat
 
 #[tauri::command]
 pub async fn update_page_title(state: tauri::State<'_, AppState>, id: String, title: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
     db.update_page_title(id, title)
         .await
@@ -195,8 +288,7 @@ pub async fn update_page_title(state: tauri::State<'_, AppState>, id: String, ti
 
 #[tauri::command]
 pub async fn update_page_cache(state: tauri::State<'_, AppState>, id: String, cache: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
     db.update_page_cache(id, cache)
         .await
@@ -205,8 +297,7 @@ pub async fn update_page_cache(state: tauri::State<'_, AppState>, id: String, ca
 
 #[tauri::command]
 pub async fn get_page_cache(state: tauri::State<'_, AppState>, id: String) -> Result<String, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
     db.get_page_cache(id)
         .await
@@ -215,18 +306,21 @@ pub async fn get_page_cache(state: tauri::State<'_, AppState>, id: String) -> Re
 
 #[tauri::command]
 pub async fn update_page_updated_at(state: tauri::State<'_, AppState>, id: String, updated_at: i64) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
-    db.update_page_updated_at(id, updated_at)
+    let updated = db.update_page_updated_at(id.clone(), updated_at)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    // la page vient d'être enregistrée : aperçu à jour pour les listes
+    if updated {
+        db.refresh_page_preview(id).await.map_err(|e| e.to_string())?;
+    }
+    Ok(updated)
 }
 
 #[tauri::command]
 pub async fn delete_page(state: tauri::State<'_, AppState>, id: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
     db.delete_page(id)
         .await
@@ -235,8 +329,7 @@ pub async fn delete_page(state: tauri::State<'_, AppState>, id: String) -> Resul
 
 #[tauri::command]
 pub async fn get_pages_by_path(state: tauri::State<'_, AppState>, path: String) -> Result<Vec<PageJson>, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Page structure not initialized".to_string())?;
+    let db = state.database("Page structure not initialized").await?;
 
     db.get_pages_by_path(path)
         .await
@@ -246,8 +339,7 @@ pub async fn get_pages_by_path(state: tauri::State<'_, AppState>, path: String)
 
 #[tauri::command]
 pub async fn new_prop(state: tauri::State<'_, AppState>, prop: PropsJson) -> Result<String, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Prop structure not initialized".to_string())?;
+    let db = state.database("Prop structure not initialized").await?;
 
     db.new_prop(&prop)
         .await
@@ -256,8 +348,7 @@ pub async fn new_prop(state: tauri::State<'_, AppState>, prop: PropsJson) -> Res
 
 #[tauri::command]
 pub async fn update_prop_value(state: tauri::State<'_, AppState>, bloc_id: String, key: String, value: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Prop structure not initialized".to_string())?;
+    let db = state.database("Prop structure not initialized").await?;
 
     db.update_prop_value(bloc_id, key, value)
         .await
@@ -266,8 +357,7 @@ pub async fn update_prop_value(state: tauri::State<'_, AppState>, bloc_id: Strin
 
 #[tauri::command]
 pub async fn delete_prop(state: tauri::State<'_, AppState>, bloc_id: String, key: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Prop structure not initialized".to_string())?;
+    let db = state.database("Prop structure not initialized").await?;
 
     db.delete_prop(bloc_id, key)
         .await
@@ -276,8 +366,7 @@ pub async fn delete_prop(state: tauri::State<'_, AppState>, bloc_id: String, key
 
 #[tauri::command]
 pub async fn delete_prop_by_bloc_id(state: tauri::State<'_, AppState>, bloc_id: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Prop structure not initialized".to_string())?;
+    let db = state.database("Prop structure not initialized").await?;
 
     db.delete_prop_by_bloc_id(bloc_id)
         .await
@@ -286,8 +375,7 @@ pub async fn delete_prop_by_bloc_id(state: tauri::State<'_, AppState>, bloc_id:
 
 #[tauri::command]
 pub async fn get_props_by_bloc_id(state: tauri::State<'_, AppState>, bloc_id: String) -> Result<Vec<PropsJson>, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Prop structure not initialized".to_string())?;
+    let db = state.database("Prop structure not initialized").await?;
 
     db.get_props_by_bloc_id(bloc_id)
         .await
@@ -296,8 +384,7 @@ pub async fn get_props_by_bloc_id(state: tauri::State<'_, AppState>, bloc_id: St
 
 #[tauri::command]
 pub async fn get_props_by_key(state: tauri::State<'_, AppState>, key: String) -> Result<Vec<PropsJson>, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Prop structure not initialized".to_string())?;
+    let db = state.database("Prop structure not initialized").await?;
 
     db.get_props_by_key(key)
         .await
@@ -306,10 +393,888 @@ pub async fn get_props_by_key(state: tauri::State<'_, AppState>, key: String) ->
 
 #[tauri::command]
 pub async fn change_prop_key_name(state: tauri::State<'_, AppState>, key: String, new_key: String) -> Result<bool, String> {
-    let db = state.db.lock().await;
-    let db = db.as_ref().ok_or_else(|| "Prop structure not initialized".to_string())?;
+    let db = state.database("Prop structure not initialized").await?;
 
     db.change_prop_key_name(key, new_key)
         .await
         .map_err(|e| e.to_string())
 }
+
+#[tauri::command]
+pub async fn new_thread(state: State<'_, AppState>, thread: ThreadJson) -> Result<String, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.new_thread(&thread).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn new_comment(state: State<'_, AppState>, comment: CommentJson) -> Result<String, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.new_comment(&comment).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_comment_content(state: State<'_, AppState>, id: String, content: String, updated_at: i64) -> Result<bool, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.update_comment_content(id, content, updated_at).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_comment(state: State<'_, AppState>, id: String, updated_at: i64) -> Result<bool, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.delete_comment(id, updated_at).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn resolve_thread(state: State<'_, AppState>, id: String, updated_at: i64) -> Result<bool, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.set_thread_resolved(id, true, updated_at).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn reopen_thread(state: State<'_, AppState>, id: String, updated_at: i64) -> Result<bool, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.set_thread_resolved(id, false, updated_at).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_thread(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.delete_thread(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_threads_by_page_id(state: State<'_, AppState>, page_id: String, include_resolved: bool) -> Result<Vec<ThreadJson>, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.get_threads_by_page_id(page_id, include_resolved).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_threads_by_bloc_id(state: State<'_, AppState>, bloc_id: String) -> Result<Vec<ThreadJson>, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.get_threads_by_bloc_id(bloc_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_unresolved_comment_counts(state: State<'_, AppState>) -> Result<Vec<PageCommentCount>, String> {
+    let db = state.database("Comment structure not initialized").await?;
+    db.get_unresolved_comment_counts().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn sync_with_peer(state: State<'_, AppState>,
+    address: String,
+    secret: String,
+    attachments_dir: Option<String>
+) -> Result<SyncReport, String> {
+    let db = state.database("Sync not initialized").await?;
+    let options = SyncOptions { attachments_dir: attachments_dir.map(PathBuf::from), secret };
+
+    db.sync_with_peer(address.as_str(), &options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// attente après un échec de accept(), doublée jusqu'au maximum
+const SYNC_ACCEPT_RETRY: Duration = Duration::from_secs(1);
+const SYNC_ACCEPT_RETRY_MAX: Duration = Duration::from_secs(60);
+
+// écoute les pairs qui connaissent `secret` ; chaque synchronisation terminée
+// est signalée par l'événement "sync-completed". L'adresse d'écoute est
+// 127.0.0.1 sauf si une interface est choisie. Remplace le serveur précédent ;
+// renvoie le port (0 = au hasard).
+#[tauri::command]
+pub async fn start_sync_server(app: AppHandle, state: State<'_, AppState>,
+    port: u16,
+    address: Option<String>,
+    secret: String,
+    attachments_dir: Option<String>
+) -> Result<u16, String> {
+    let db = state.database("Sync not initialized").await?;
+    if secret.is_empty() {
+        return Err("a pairing secret is required".to_string());
+    }
+    let options = SyncOptions { attachments_dir: attachments_dir.map(PathBuf::from), secret };
+
+    stop_sync(&state).await;
+    let address = address.unwrap_or_else(|| "127.0.0.1".to_string());
+    let listener = TcpListener::bind((address.as_str(), port))
+        .await
+        .map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+    let task = tauri::async_runtime::spawn(async move {
+        let mut retry = SYNC_ACCEPT_RETRY;
+        loop {
+            // un échec de accept() (descripteurs épuisés…) se répète : on
+            // attend avant de réessayer. L'échec d'un pair ne concerne que lui.
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => {
+                    retry = SYNC_ACCEPT_RETRY;
+                    stream
+                }
+                Err(e) => {
+                    let _ = app.emit_all("sync-failed", e.to_string());
+                    tokio::time::sleep(retry).await;
+                    retry = (retry * 2).min(SYNC_ACCEPT_RETRY_MAX);
+                    continue;
+                }
+            };
+            let _ = stream.set_nodelay(true);
+            match db.sync_over(stream, &options).await {
+                Ok(report) => {
+                    let _ = app.emit_all("sync-completed", &report);
+                }
+                Err(e) => {
+                    let _ = app.emit_all("sync-failed", e.to_string());
+                }
+            }
+        }
+    });
+    *state.sync_server.write().await = Some(RunningSyncServer { task, port });
+
+    Ok(port)
+}
+
+// port du serveur arrêté, None s'il n'y en avait pas
+async fn stop_sync(state: &AppState) -> Option<u16> {
+    let running = state.sync_server.write().await.take()?;
+    running.task.abort();
+    Some(running.port)
+}
+
+#[tauri::command]
+pub async fn stop_sync_server(state: State<'_, AppState>) -> Result<Option<u16>, String> {
+    Ok(stop_sync(&state).await)
+}
+
+// Fusion CRDT des pages : les mises à jour sont les octets Yjs (v1) échangés
+// avec les autres appareils.
+#[tauri::command]
+pub async fn crdt_state_vector(state: State<'_, AppState>, page_id: String) -> Result<Vec<u8>, String> {
+    let store = CrdtStore::new(state.database("CRDT not initialized").await?);
+    store.state_vector(&page_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn crdt_diff(state: State<'_, AppState>, page_id: String, state_vector: Vec<u8>) -> Result<Vec<u8>, String> {
+    let store = CrdtStore::new(state.database("CRDT not initialized").await?);
+    store.diff(&page_id, &state_vector).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn apply_crdt_update(state: State<'_, AppState>, page_id: String, update: Vec<u8>) -> Result<Vec<BlocJson>, String> {
+    let store = CrdtStore::new(state.database("CRDT not initialized").await?);
+    store.apply_remote_update(&page_id, &update).await.map_err(|e| e.to_string())
+}
+
+// renvoie la mise à jour à diffuser aux autres appareils
+#[tauri::command]
+pub async fn record_crdt_changes(state: State<'_, AppState>,
+    page_id: String,
+    blocs: Vec<BlocJson>,
+    removed: Vec<String>
+) -> Result<Vec<u8>, String> {
+    let store = CrdtStore::new(state.database("CRDT not initialized").await?);
+    store.record_local_changes(&page_id, &blocs, &removed).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn materialise_page(state: State<'_, AppState>, page_id: String) -> Result<Vec<BlocJson>, String> {
+    let store = CrdtStore::new(state.database("CRDT not initialized").await?);
+    store.materialise(&page_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn changes_since(state: State<'_, AppState>, cursor: i64, limit: i64) -> Result<ChangeFeed, String> {
+    let db = state.database("Operation log not initialized").await?;
+    db.changes_since(cursor, limit).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn undo(state: State<'_, AppState>) -> Result<Option<UndoStep>, String> {
+    let db = state.database("Undo journal not initialized").await?;
+    db.undo().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn redo(state: State<'_, AppState>) -> Result<Option<UndoStep>, String> {
+    let db = state.database("Undo journal not initialized").await?;
+    db.redo().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn undo_status(state: State<'_, AppState>) -> Result<UndoStatus, String> {
+    let db = state.database("Undo journal not initialized").await?;
+    db.undo_status().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn undo_history(state: State<'_, AppState>, limit: i64) -> Result<Vec<UndoStep>, String> {
+    let db = state.database("Undo journal not initialized").await?;
+    db.undo_history(limit).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_template(state: State<'_, AppState>, template: TemplateJson) -> Result<bool, String> {
+    let db = state.database("Templates not initialized").await?;
+    db.set_template(&template).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn unset_template(state: State<'_, AppState>, page_id: String) -> Result<bool, String> {
+    let db = state.database("Templates not initialized").await?;
+    db.unset_template(page_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_templates(state: State<'_, AppState>) -> Result<Vec<TemplateJson>, String> {
+    let db = state.database("Templates not initialized").await?;
+    db.get_templates().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn template_variables(state: State<'_, AppState>, template_id: String) -> Result<Vec<String>, String> {
+    let db = state.database("Templates not initialized").await?;
+    db.template_variables(template_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn instantiate_template(state: State<'_, AppState>,
+    template_id: String,
+    path: String,
+    title: String,
+    variables: HashMap<String, String>
+) -> Result<String, String> {
+    let db = state.database("Templates not initialized").await?;
+    db.instantiate_template(template_id, path, title, &variables)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// fait aussi, sans appel, par start_daily_notes
+#[tauri::command]
+pub async fn run_recurring_templates(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let db = state.database("Templates not initialized").await?;
+    db.run_recurring_templates(Local::now().date_naive())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// revérifié au moins aussi souvent : veille ou changement d'heure décalent le
+// réveil prévu
+const DAILY_NOTES_CHECK: Duration = Duration::from_secs(3600);
+
+fn until_next_day() -> Duration {
+    let now = Local::now();
+    now.date_naive()
+        .succ_opt()
+        .and_then(|day| day.and_hms_opt(0, 0, 1))
+        .and_then(|next| next.and_local_timezone(Local).earliest())
+        .and_then(|next| (next - now).to_std().ok())
+        .unwrap_or(DAILY_NOTES_CHECK)
+}
+
+// crée les notes des modèles récurrents au changement de jour tant que
+// l'application reste ouverte ; "daily-notes-created" porte les ids des pages
+async fn start_daily_notes(app: &AppHandle, state: &AppState, db: Database) {
+    if let Some(previous) = state.daily_notes.write().await.take() {
+        previous.abort();
+    }
+
+    let app = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(until_next_day().min(DAILY_NOTES_CHECK)).await;
+            match db.run_recurring_templates(Local::now().date_naive()).await {
+                Ok(created) if !created.is_empty() => {
+                    let _ = app.emit_all("daily-notes-created", &created);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    let _ = app.emit_all("daily-notes-failed", e.to_string());
+                }
+            }
+        }
+    });
+    *state.daily_notes.write().await = Some(task);
+}
+
+// dates échangées avec le front au format AAAA-MM-JJ
+fn parse_day(day: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|e| format!("invalid date {}: {}", day, e))
+}
+
+#[tauri::command]
+pub async fn get_or_create_daily_page(state: State<'_, AppState>, day: String, options: JournalOptions) -> Result<PageJson, String> {
+    let db = state.database("Journal not initialized").await?;
+    db.get_or_create_daily_page(parse_day(&day)?, &options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_previous_daily_page(state: State<'_, AppState>, day: String) -> Result<Option<JournalDay>, String> {
+    let db = state.database("Journal not initialized").await?;
+    db.get_previous_daily_page(parse_day(&day)?)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_next_daily_page(state: State<'_, AppState>, day: String) -> Result<Option<JournalDay>, String> {
+    let db = state.database("Journal not initialized").await?;
+    db.get_next_daily_page(parse_day(&day)?)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_journal_calendar(state: State<'_, AppState>, from: String, to: String) -> Result<Vec<JournalDay>, String> {
+    let db = state.database("Journal not initialized").await?;
+    db.get_journal_calendar(parse_day(&from)?, parse_day(&to)?)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn new_tag(state: State<'_, AppState>, name: String, color: Option<String>) -> Result<TagJson, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.new_tag(name, color).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_tag_color(state: State<'_, AppState>, id: String, color: Option<String>) -> Result<bool, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.update_tag_color(id, color).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_tag(state: State<'_, AppState>, id: String) -> Result<u64, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.delete_tag(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tags(state: State<'_, AppState>) -> Result<Vec<TagJson>, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.get_tags().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn tag_entity(state: State<'_, AppState>, entity: TagEntity, entity_id: String, name: String) -> Result<bool, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.tag_entity(entity, entity_id, name).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn untag_entity(state: State<'_, AppState>, entity: TagEntity, entity_id: String, name: String) -> Result<bool, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.untag_entity(entity, entity_id, name).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_entity_tags(state: State<'_, AppState>, entity: TagEntity, entity_id: String, names: Vec<String>) -> Result<Vec<TagJson>, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.set_entity_tags(entity, entity_id, &names)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_entity_tags(state: State<'_, AppState>, entity: TagEntity, entity_id: String) -> Result<Vec<TagJson>, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.get_entity_tags(entity, entity_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tagged(state: State<'_, AppState>, name: String, include_descendants: bool) -> Result<Vec<TaggedItem>, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.get_tagged(name, include_descendants).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rename_tag(state: State<'_, AppState>, id: String, new_name: String) -> Result<TagJson, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.rename_tag(id, new_name).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn merge_tags(state: State<'_, AppState>, source_id: String, target_id: String) -> Result<TagJson, String> {
+    let db = state.database("Tags not initialized").await?;
+    db.merge_tags(source_id, target_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn record_visit(state: State<'_, AppState>, page_id: String, anchor: Option<String>) -> Result<i64, String> {
+    let db = state.database("History not initialized").await?;
+    db.record_visit(page_id, anchor).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_visit_anchor(state: State<'_, AppState>, id: i64, anchor: Option<String>) -> Result<bool, String> {
+    let db = state.database("History not initialized").await?;
+    db.update_visit_anchor(id, anchor).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_visits(state: State<'_, AppState>, limit: i64, before: Option<i64>) -> Result<Vec<VisitJson>, String> {
+    let db = state.database("History not initialized").await?;
+    db.get_visits(limit, before).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_recent_pages(state: State<'_, AppState>, limit: i64) -> Result<Vec<PageVisits>, String> {
+    let db = state.database("History not initialized").await?;
+    db.get_recent_pages(limit).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_frequent_pages(state: State<'_, AppState>, limit: i64, since: Option<i64>) -> Result<Vec<PageVisits>, String> {
+    let db = state.database("History not initialized").await?;
+    db.get_frequent_pages(limit, since).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_page_visits(state: State<'_, AppState>, page_id: String) -> Result<u64, String> {
+    let db = state.database("History not initialized").await?;
+    db.delete_page_visits(page_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn clear_visits(state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.database("History not initialized").await?;
+    db.clear_visits().await.map_err(|e| e.to_string())
+}
+
+// les onglets sont rangés par fenêtre (libellé Tauri)
+#[tauri::command]
+pub async fn save_tab_session(window: Window, state: State<'_, AppState>, session: TabSession) -> Result<(), String> {
+    let db = state.database("Tabs not initialized").await?;
+    db.save_tab_session(window.label().to_string(), &session)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tab_session(window: Window, state: State<'_, AppState>) -> Result<TabSession, String> {
+    let db = state.database("Tabs not initialized").await?;
+    db.get_tab_session(window.label().to_string())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn effective_settings(app: &AppHandle, state: &AppState) -> Result<Vec<SettingValue>, String> {
+    let global = state.settings_database(app).await?;
+    let workspace = state.db.read().await.clone();
+    settings::get_settings(&global, workspace.as_ref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// "settings-changed" porte les valeurs effectives des réglages modifiés (tous
+// si `keys` est None), pour que chaque fenêtre les applique aussitôt
+async fn emit_settings_changed(app: &AppHandle, state: &AppState, keys: Option<&[String]>) -> Result<(), String> {
+    let changed: Vec<SettingValue> = effective_settings(app, state)
+        .await?
+        .into_iter()
+        .filter(|setting| match keys {
+            Some(keys) => keys.contains(&setting.key),
+            None => true,
+        })
+        .collect();
+    if !changed.is_empty() {
+        app.emit_all("settings-changed", &changed).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+async fn scope_database(app: &AppHandle, state: &AppState, scope: SettingScope) -> Result<Database, String> {
+    match scope {
+        SettingScope::Global => state.settings_database(app).await,
+        SettingScope::Workspace => state.database("Workspace not initialized").await,
+    }
+}
+
+#[tauri::command]
+pub async fn get_settings(app: AppHandle, state: State<'_, AppState>) -> Result<Vec<SettingValue>, String> {
+    effective_settings(&app, &state).await
+}
+
+#[tauri::command]
+pub async fn set_setting(app: AppHandle, state: State<'_, AppState>,
+    scope: SettingScope,
+    key: String,
+    value: serde_json::Value
+) -> Result<(), String> {
+    let db = scope_database(&app, &state, scope).await?;
+    db.set_setting(scope, key.clone(), value)
+        .await
+        .map_err(|e| e.to_string())?;
+    emit_settings_changed(&app, &state, Some(std::slice::from_ref(&key))).await
+}
+
+// retire la valeur de la portée (toutes si `key` est absent)
+#[tauri::command]
+pub async fn reset_setting(app: AppHandle, state: State<'_, AppState>, scope: SettingScope, key: Option<String>) -> Result<(), String> {
+    let db = scope_database(&app, &state, scope).await?;
+    let keys = db.reset_setting(key).await.map_err(|e| e.to_string())?;
+    emit_settings_changed(&app, &state, Some(&keys)).await
+}
+
+#[tauri::command]
+pub async fn export_settings(app: AppHandle, state: State<'_, AppState>) -> Result<SettingsExport, String> {
+    let global = state.settings_database(&app).await?;
+    let workspace = state.db.read().await.clone();
+    settings::export_settings(&global, workspace.as_ref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// accepte un export, ou les réglages du localStorage dans `global`
+#[tauri::command]
+pub async fn import_settings(app: AppHandle, state: State<'_, AppState>, export: SettingsExport) -> Result<SettingsImport, String> {
+    let mut report = SettingsImport::default();
+    for (scope, values) in [(SettingScope::Global, export.global), (SettingScope::Workspace, export.workspace)] {
+        if values.is_empty() {
+            continue;
+        }
+        let db = scope_database(&app, &state, scope).await?;
+        let imported = db
+            .import_settings(scope, &values.into_iter().collect(), export.version)
+            .await
+            .map_err(|e| e.to_string())?;
+        report.applied.extend(imported.applied);
+        report.skipped.extend(imported.skipped);
+    }
+
+    emit_settings_changed(&app, &state, Some(&report.applied)).await?;
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_blocs_chunk(state: State<'_, AppState>, page_id: String, after: Option<BlocCursor>, limit: i64) -> Result<BlocChunk, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+    db.get_blocs_chunk(page_id, after, limit).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_pages_chunk(state: State<'_, AppState>, path: String, after: Option<PageCursor>, limit: i64) -> Result<PageChunk, String> {
+    let db = state.database("Page structure not initialized").await?;
+    db.get_pages_chunk(path, after, limit).await.map_err(|e| e.to_string())
+}
+
+#[derive(Clone, serde::Serialize)]
+struct BlocChunkEvent {
+    request_id: String,
+    page_id: String,
+    index: usize,
+    blocs: Vec<BlocJson>,
+    // sources des blocs références de la tranche
+    references: HashMap<String, serde_json::Value>,
+}
+
+// envoie les blocs à la fenêtre appelante par événements "bloc-chunk" ; la
+// commande se termine après la dernière tranche et renvoie le nombre de blocs
+#[tauri::command]
+pub async fn stream_blocs_by_page_id(window: Window, state: State<'_, AppState>,
+    page_id: String,
+    chunk_size: i64,
+    request_id: String
+) -> Result<usize, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+
+    let mut index = 0;
+    db.stream_blocs_by_page_id(page_id.clone(), chunk_size, |blocs, references| {
+        let event = BlocChunkEvent {
+            request_id: request_id.clone(),
+            page_id: page_id.clone(),
+            index,
+            blocs,
+            references,
+        };
+        index += 1;
+        window.emit("bloc-chunk", event)?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_page_preview(state: State<'_, AppState>, page_id: String) -> Result<PagePreview, String> {
+    let db = state.database("Page structure not initialized").await?;
+    db.refresh_page_preview(page_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_page_previews_by_path(state: State<'_, AppState>, path: String) -> Result<Vec<PageWithPreview>, String> {
+    let db = state.database("Page structure not initialized").await?;
+    db.get_page_previews_by_path(path).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_page_outline(state: State<'_, AppState>, page_id: String) -> Result<Vec<OutlineNode>, String> {
+    let db = state.database("Page structure not initialized").await?;
+    db.get_page_outline(page_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rebuild_heading_index(state: State<'_, AppState>) -> Result<usize, String> {
+    let db = state.database("Page structure not initialized").await?;
+    db.rebuild_heading_index().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn search_headings(state: State<'_, AppState>, query: String, limit: i64) -> Result<Vec<HeadingHit>, String> {
+    let db = state.database("Page structure not initialized").await?;
+    db.search_headings(query, limit).await.map_err(|e| e.to_string())
+}
+
+// au-delà, étiquettes et visites sont relues même sans changement dans le
+// journal (elles ne passent pas par le journal des opérations)
+const PALETTE_TTL_MS: i64 = 60_000;
+
+#[tauri::command]
+pub async fn register_palette_commands(state: State<'_, AppState>, commands: Vec<PaletteCommand>) -> Result<(), String> {
+    *state.palette_commands.write().await = commands;
+    *state.palette.write().await = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn search_palette(state: State<'_, AppState>, query: String, limit: usize) -> Result<Vec<PaletteHit>, String> {
+    let db = state.database("Palette not initialized").await?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let cursor = db.latest_cursor().await.map_err(|e| e.to_string())?;
+
+    {
+        let palette = state.palette.read().await;
+        if let Some(index) = palette.as_ref() {
+            if index.cursor == cursor && now - index.built_at < PALETTE_TTL_MS {
+                return Ok(index.search(&query, limit, now));
+            }
+        }
+    }
+
+    // seules les pages touchées depuis le curseur sont relues ; l'index
+    // complet n'est construit qu'une fois
+    let mut palette = state.palette.write().await;
+    match palette.as_mut() {
+        Some(index) => {
+            let stale = now - index.built_at >= PALETTE_TTL_MS;
+            db.update_palette_index(index, stale.then_some(now)).await.map_err(|e| e.to_string())?;
+        }
+        None => {
+            let commands = state.palette_commands.read().await.clone();
+            *palette = Some(db.build_palette_index(&commands, now).await.map_err(|e| e.to_string())?);
+        }
+    }
+    Ok(palette.as_ref().map(|index| index.search(&query, limit, now)).unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn duplicate_page(state: State<'_, AppState>, page_id: String, path: String, rewrite_links: bool) -> Result<String, String> {
+    let db = state.database("Page structure not initialized").await?;
+    db.duplicate_page(page_id, path, rewrite_links).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn duplicate_subtree(
+    state: State<'_, AppState>,
+    path: String,
+    target_path: String,
+    rewrite_links: bool,
+) -> Result<DuplicateReport, String> {
+    let db = state.database("Page structure not initialized").await?;
+    db.duplicate_subtree(path, target_path, rewrite_links).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn move_blocs(
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+    target_page: String,
+    anchor: Option<MoveAnchor>,
+) -> Result<Vec<BlocPosition>, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+    db.move_blocs(ids, target_page, anchor).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn new_bloc_reference(
+    state: State<'_, AppState>,
+    page_id: String,
+    source_id: String,
+    position: String,
+) -> Result<String, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+    db.new_bloc_reference(page_id, source_id, position).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_reference_source(state: State<'_, AppState>, id: String, source_id: String) -> Result<bool, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+    db.set_reference_source(id, source_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_bloc_references(state: State<'_, AppState>, source_id: String) -> Result<Vec<BlocReference>, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+    db.get_bloc_references(source_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn detach_bloc_reference(state: State<'_, AppState>, id: String) -> Result<BlocJson, String> {
+    let db = state.database("Bloc structure not initialized").await?;
+    db.detach_bloc_reference(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn query_props(state: State<'_, AppState>, query: String) -> Result<Vec<PropsMatch>, String> {
+    let db = state.database("Prop structure not initialized").await?;
+    db.query_props(&query, Local::now().date_naive()).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn mark_flashcard(state: State<'_, AppState>, bloc_id: String) -> Result<bool, String> {
+    let db = state.database("Flashcards not initialized").await?;
+    db.mark_flashcard(bloc_id, Local::now().date_naive()).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn unmark_flashcard(state: State<'_, AppState>, bloc_id: String) -> Result<bool, String> {
+    let db = state.database("Flashcards not initialized").await?;
+    db.unmark_flashcard(bloc_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_due_cards(state: State<'_, AppState>, limit: i64) -> Result<Vec<Flashcard>, String> {
+    let db = state.database("Flashcards not initialized").await?;
+    db.get_due_cards(Local::now().date_naive(), limit).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn record_review(state: State<'_, AppState>, bloc_id: String, grade: u8) -> Result<CardReview, String> {
+    let db = state.database("Flashcards not initialized").await?;
+    db.record_review(bloc_id, grade, Local::now().date_naive()).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_card_reviews(state: State<'_, AppState>, bloc_id: String) -> Result<Vec<CardReview>, String> {
+    let db = state.database("Flashcards not initialized").await?;
+    db.get_card_reviews(bloc_id).await.map_err(|e| e.to_string())
+}
+
+// heure des rappels tirés d'une date sans heure
+const REMINDER_TIME: (u32, u32) = (9, 0);
+
+fn reminder_props() -> Vec<String> {
+    REMINDER_PROPS.iter().map(|key| key.to_string()).collect()
+}
+
+async fn sync_reminders_from_props(db: &Database) -> Result<usize, String> {
+    let time = NaiveTime::from_hms_opt(REMINDER_TIME.0, REMINDER_TIME.1, 0).unwrap_or_default();
+    db.sync_prop_reminders(&reminder_props(), time, *Local::now().offset())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// remplace le planificateur de l'espace précédent ; chaque rappel déclenché
+// est signalé par "reminder-fired" et une notification du système
+async fn start_reminders(app: &AppHandle, state: &AppState, db: Database) -> Result<(), String> {
+    if let Some(previous) = state.reminders.write().await.take() {
+        previous.task.abort();
+    }
+    sync_reminders_from_props(&db).await?;
+
+    let scheduler = ReminderScheduler::new(db, Arc::new(SystemClock));
+    let wake = scheduler.waker();
+    let app = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let identifier = app.config().tauri.bundle.identifier.clone();
+        // le planificateur retente seul après une erreur : on la signale
+        // seulement
+        scheduler
+            .run(
+                |fired| {
+                    let _ = tauri::api::notification::Notification::new(&identifier)
+                        .title(&fired.reminder.title)
+                        .show();
+                    let _ = app.emit_all("reminder-fired", &fired);
+                },
+                |e| {
+                    let _ = app.emit_all("reminders-failed", e.to_string());
+                },
+            )
+            .await;
+    });
+    *state.reminders.write().await = Some(RunningReminders { task, wake });
+    Ok(())
+}
+
+// le planificateur recalcule son prochain réveil
+async fn wake_reminders(state: &AppState) {
+    if let Some(running) = state.reminders.read().await.as_ref() {
+        running.wake.notify_one();
+    }
+}
+
+#[tauri::command]
+pub async fn new_reminder(
+    state: State<'_, AppState>,
+    bloc_id: Option<String>,
+    page_id: Option<String>,
+    title: String,
+    fire_at: i64,
+) -> Result<ReminderJson, String> {
+    let db = state.database("Reminders not initialized").await?;
+    let reminder = db.new_reminder(bloc_id, page_id, title, fire_at).await.map_err(|e| e.to_string())?;
+    wake_reminders(&state).await;
+    Ok(reminder)
+}
+
+#[tauri::command]
+pub async fn get_reminders(state: State<'_, AppState>, include_done: bool) -> Result<Vec<ReminderJson>, String> {
+    let db = state.database("Reminders not initialized").await?;
+    db.get_reminders(include_done).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn snooze_reminder(state: State<'_, AppState>, id: String, minutes: i64) -> Result<bool, String> {
+    let db = state.database("Reminders not initialized").await?;
+    if minutes <= 0 {
+        return Err("snooze minutes must be positive".to_string());
+    }
+    let until = minutes
+        .checked_mul(60_000)
+        .and_then(|delay| chrono::Utc::now().timestamp_millis().checked_add(delay))
+        .ok_or_else(|| "snooze delay out of range".to_string())?;
+    let snoozed = db.snooze_reminder(id, until).await.map_err(|e| e.to_string())?;
+    wake_reminders(&state).await;
+    Ok(snoozed)
+}
+
+#[tauri::command]
+pub async fn dismiss_reminder(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let db = state.database("Reminders not initialized").await?;
+    let dismissed = db.dismiss_reminder(id).await.map_err(|e| e.to_string())?;
+    wake_reminders(&state).await;
+    Ok(dismissed)
+}
+
+#[tauri::command]
+pub async fn delete_reminder(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+    let db = state.database("Reminders not initialized").await?;
+    let deleted = db.delete_reminder(id).await.map_err(|e| e.to_string())?;
+    wake_reminders(&state).await;
+    Ok(deleted)
+}
+
+// à appeler après l'enregistrement des props de date ("due", "remind")
+#[tauri::command]
+pub async fn sync_prop_reminders(state: State<'_, AppState>) -> Result<usize, String> {
+    let db = state.database("Reminders not initialized").await?;
+    let changed = sync_reminders_from_props(&db).await?;
+    wake_reminders(&state).await;
+    Ok(changed)
+}
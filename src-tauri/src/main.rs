@@ -13,12 +13,14 @@ use database_manager::database_tauri::{
     update_bloc,
     update_bloc_content,
     update_bloc_position,
+    rebalance_page_positions,
     update_bloc_page_id,
     delete_bloc,
     delete_bloc_by_page_id,
     get_checksum,
     get_bloc_by_id,
     get_blocs_by_page_id,
+    query_bloc_nodes,
 
     new_page,
     update_page,
@@ -36,7 +38,115 @@ use database_manager::database_tauri::{
     delete_prop_by_bloc_id,
     get_props_by_bloc_id,
     get_props_by_key,
-    change_prop_key_name
+    change_prop_key_name,
+
+    new_thread,
+    new_comment,
+    update_comment_content,
+    delete_comment,
+    resolve_thread,
+    reopen_thread,
+    delete_thread,
+    get_threads_by_page_id,
+    get_threads_by_bloc_id,
+    get_unresolved_comment_counts,
+
+    sync_with_peer,
+    start_sync_server,
+
+    changes_since,
+
+    undo,
+    redo,
+    undo_status,
+    undo_history,
+
+    set_template,
+    unset_template,
+    get_templates,
+    template_variables,
+    instantiate_template,
+    run_recurring_templates,
+
+    get_or_create_daily_page,
+    get_previous_daily_page,
+    get_next_daily_page,
+    get_journal_calendar,
+
+    new_tag,
+    update_tag_color,
+    delete_tag,
+    get_tags,
+    tag_entity,
+    untag_entity,
+    set_entity_tags,
+    get_entity_tags,
+    get_tagged,
+    rename_tag,
+    merge_tags,
+
+    record_visit,
+    update_visit_anchor,
+    get_visits,
+    get_recent_pages,
+    get_frequent_pages,
+    delete_page_visits,
+    clear_visits,
+    save_tab_session,
+    get_tab_session,
+
+    get_settings,
+    set_setting,
+    reset_setting,
+    export_settings,
+    import_settings,
+
+    get_blocs_chunk,
+    get_pages_chunk,
+    stream_blocs_by_page_id,
+
+    get_page_preview,
+    get_page_previews_by_path,
+
+    get_page_outline,
+    rebuild_heading_index,
+    search_headings,
+
+    register_palette_commands,
+    search_palette,
+
+    duplicate_page,
+    duplicate_subtree,
+
+    move_blocs,
+
+    new_bloc_reference,
+    set_reference_source,
+    get_bloc_references,
+    detach_bloc_reference,
+
+    query_props,
+
+    mark_flashcard,
+    unmark_flashcard,
+    get_due_cards,
+    record_review,
+    get_card_reviews,
+
+    new_reminder,
+    get_reminders,
+    snooze_reminder,
+    dismiss_reminder,
+    delete_reminder,
+    sync_prop_reminders,
+
+    crdt_state_vector,
+    crdt_diff,
+    apply_crdt_update,
+    record_crdt_changes,
+    materialise_page,
+
+    stop_sync_server
 };
 
 #[tauri::command]
@@ -93,12 +203,14 @@ fn main() {
             update_bloc,
             update_bloc_content,
             update_bloc_position,
+            rebalance_page_positions,
             update_bloc_page_id,
             delete_bloc,
             delete_bloc_by_page_id,
             get_checksum,
             get_bloc_by_id,
             get_blocs_by_page_id,
+            query_bloc_nodes,
 
             new_page,
             update_page,
@@ -116,7 +228,115 @@ fn main() {
             delete_prop_by_bloc_id,
             get_props_by_bloc_id,
             get_props_by_key,
-            change_prop_key_name
+            change_prop_key_name,
+
+            new_thread,
+            new_comment,
+            update_comment_content,
+            delete_comment,
+            resolve_thread,
+            reopen_thread,
+            delete_thread,
+            get_threads_by_page_id,
+            get_threads_by_bloc_id,
+            get_unresolved_comment_counts,
+
+            sync_with_peer,
+            start_sync_server,
+
+            changes_since,
+
+            undo,
+            redo,
+            undo_status,
+            undo_history,
+
+            set_template,
+            unset_template,
+            get_templates,
+            template_variables,
+            instantiate_template,
+            run_recurring_templates,
+
+            get_or_create_daily_page,
+            get_previous_daily_page,
+            get_next_daily_page,
+            get_journal_calendar,
+
+            new_tag,
+            update_tag_color,
+            delete_tag,
+            get_tags,
+            tag_entity,
+            untag_entity,
+            set_entity_tags,
+            get_entity_tags,
+            get_tagged,
+            rename_tag,
+            merge_tags,
+
+            record_visit,
+            update_visit_anchor,
+            get_visits,
+            get_recent_pages,
+            get_frequent_pages,
+            delete_page_visits,
+            clear_visits,
+            save_tab_session,
+            get_tab_session,
+
+            get_settings,
+            set_setting,
+            reset_setting,
+            export_settings,
+            import_settings,
+
+            get_blocs_chunk,
+            get_pages_chunk,
+            stream_blocs_by_page_id,
+
+            get_page_preview,
+            get_page_previews_by_path,
+
+            get_page_outline,
+            rebuild_heading_index,
+            search_headings,
+
+            register_palette_commands,
+            search_palette,
+
+            duplicate_page,
+            duplicate_subtree,
+
+            move_blocs,
+
+            new_bloc_reference,
+            set_reference_source,
+            get_bloc_references,
+            detach_bloc_reference,
+
+            query_props,
+
+            mark_flashcard,
+            unmark_flashcard,
+            get_due_cards,
+            record_review,
+            get_card_reviews,
+
+            new_reminder,
+            get_reminders,
+            snooze_reminder,
+            dismiss_reminder,
+            delete_reminder,
+            sync_prop_reminders,
+
+            crdt_state_vector,
+            crdt_diff,
+            apply_crdt_update,
+            record_crdt_changes,
+            materialise_page,
+
+            stop_sync_server
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
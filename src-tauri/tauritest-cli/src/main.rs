@@ -0,0 +1,183 @@
+// Accès à la base de notes hors de l'interface, pour les scripts (cron, hooks git).
+// Toutes les commandes écrivent du JSON sur stdout, sauf `markdown` qui écrit la page.
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tauritest_db::{Database, WorkspaceExport};
+
+#[derive(Parser)]
+#[command(name = "tauritest-cli", version, about = "Scripts against the tauritest notes database")]
+struct Cli {
+    /// Path to the SQLite database file
+    #[arg(long, env = "TAURITEST_DB")]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the pages stored under a path
+    Pages { path: String },
+
+    /// Print a page as Markdown
+    Markdown {
+        page_id: String,
+        /// Wrap the Markdown in a JSON object
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Create a page from Markdown read on stdin
+    Create {
+        #[arg(long)]
+        path: String,
+        #[arg(long)]
+        title: String,
+    },
+
+    /// Search page titles and bloc text
+    Search {
+        text: String,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+
+    /// Print the operation log after a cursor
+    Changes {
+        #[arg(long, default_value_t = 0)]
+        since: i64,
+        #[arg(long, default_value_t = 1000)]
+        limit: i64,
+    },
+
+    /// Export the whole workspace as JSON
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import a workspace export ("-" reads stdin)
+    Import { file: PathBuf },
+
+    /// Check the database; exits with status 1 when problems are found
+    Check,
+
+    /// Apply pending schema migrations
+    Migrate {
+        /// Only report the current and latest schema versions
+        #[arg(long)]
+        status: bool,
+    },
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+fn read_input(file: &PathBuf) -> Result<String> {
+    if file.as_os_str() == "-" {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    } else {
+        std::fs::read_to_string(file).with_context(|| format!("cannot read {}", file.display()))
+    }
+}
+
+async fn run(cli: Cli) -> Result<ExitCode> {
+    let db_path = cli.db.to_string_lossy().to_string();
+
+    // migrate doit voir la version d'avant : on ouvre sans migrer
+    if let Command::Migrate { status } = &cli.command {
+        let db = Database::connect(&db_path).await?;
+        if *status {
+            print_json(&json!({
+                "current_version": db.schema_version().await?,
+                "latest_version": Database::latest_schema_version(),
+            }))?;
+        } else {
+            print_json(&db.migrate().await?)?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let db = Database::new(&db_path).await?;
+
+    match cli.command {
+        Command::Pages { path } => {
+            print_json(&db.get_pages_by_path(path).await?)?;
+        }
+        Command::Markdown { page_id, json } => {
+            let markdown = db.page_to_markdown(page_id.clone()).await?;
+            if json {
+                print_json(&json!({ "page_id": page_id, "markdown": markdown }))?;
+            } else {
+                print!("{}", markdown);
+            }
+        }
+        Command::Create { path, title } => {
+            let mut markdown = String::new();
+            std::io::stdin().read_to_string(&mut markdown)?;
+            let page_id = db.new_page_from_markdown(path, title, &markdown).await?;
+            print_json(&json!({ "page_id": page_id }))?;
+        }
+        Command::Search { text, limit } => {
+            print_json(&db.search(text, limit).await?)?;
+        }
+        Command::Changes { since, limit } => {
+            print_json(&db.changes_since(since, limit).await?)?;
+        }
+        Command::Export { output } => {
+            let export = db.export_workspace().await?;
+            match output {
+                Some(file) => {
+                    let writer = std::fs::File::create(&file)
+                        .with_context(|| format!("cannot create {}", file.display()))?;
+                    serde_json::to_writer_pretty(writer, &export)?;
+                    print_json(&json!({
+                        "file": file,
+                        "pages": export.pages.len(),
+                        "blocs": export.blocs.len(),
+                        "props": export.props.len(),
+                    }))?;
+                }
+                None => print_json(&export)?,
+            }
+        }
+        Command::Import { file } => {
+            let export: WorkspaceExport = serde_json::from_str(&read_input(&file)?)?;
+            print_json(&db.import_workspace(&export).await?)?;
+        }
+        Command::Check => {
+            let report = db.check_integrity().await?;
+            print_json(&report)?;
+            if !report.is_ok() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Migrate { .. } => unreachable!(),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", json!({ "error": format!("{:#}", e) }));
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,251 @@
+// Stockage CRDT des pages : chaque page est un document Yjs dont la map racine
+// "blocs" associe l'id d'un bloc à une map de ses champs. Deux appareils qui
+// modifient des champs différents d'un même bloc (contenu / position) ne
+// s'écrasent plus ; pour un même champ, Yjs départage de façon déterministe.
+// Les mises à jour sont journalisées par tauritest-db (page_updates) et l'état
+// fusionné est réécrit dans la table blocs.
+//
+// La table blocs reste la matérialisation du journal : chaque opération compare
+// d'abord les deux et reporte dans le document ce que l'éditeur a écrit sans
+// passer par le CRDT (update_bloc, update_bloc_content, delete_bloc…). Lecture,
+// rattrapage et réécriture se font dans une seule transaction (merge_page).
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use tauritest_db::{BlocJson, Database, PageMerge, PageUpdates};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{Any, Doc, Map, MapPrelim, MapRef, Out, ReadTxn, StateVector, Transact, Update};
+
+pub const BLOCS_MAP: &str = "blocs";
+
+// au-delà, le journal de la page est remplacé par un seul état fusionné
+pub const COMPACT_AFTER: usize = 64;
+
+#[derive(Clone)]
+pub struct CrdtStore {
+    db: Database,
+}
+
+fn field_str<T: ReadTxn>(map: &MapRef, txn: &T, key: &str) -> Option<String> {
+    match map.get(txn, key) {
+        Some(Out::Any(Any::String(s))) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+fn field_i64<T: ReadTxn>(map: &MapRef, txn: &T, key: &str) -> i64 {
+    match map.get(txn, key) {
+        Some(Out::Any(Any::BigInt(n))) => n,
+        Some(Out::Any(Any::Number(n))) => n as i64,
+        _ => 0,
+    }
+}
+
+// blocs décrits par le document, triés par position puis id
+fn blocs_of(doc: &Doc, page_id: &str) -> Vec<BlocJson> {
+    let root = doc.get_or_insert_map(BLOCS_MAP);
+    let txn = doc.transact();
+
+    let mut blocs: Vec<BlocJson> = root
+        .iter(&txn)
+        .filter_map(|(id, value)| match value {
+            Out::YMap(fields) => Some(BlocJson {
+                id: Some(id.to_string()),
+                position: field_str(&fields, &txn, "position")?,
+                content: field_str(&fields, &txn, "content")?,
+                page_id: page_id.to_string(),
+                bloc_type: field_str(&fields, &txn, "bloc_type")?,
+                created_at: field_i64(&fields, &txn, "created_at"),
+                updated_at: field_i64(&fields, &txn, "updated_at"),
+            }),
+            _ => None,
+        })
+        .collect();
+
+    blocs.sort_by(|a, b| a.position.cmp(&b.position).then_with(|| a.id.cmp(&b.id)));
+    blocs
+}
+
+// écrit les blocs dans le document ; renvoie la mise à jour produite
+fn write_blocs(doc: &Doc, blocs: &[BlocJson], removed: &[String]) -> Result<Vec<u8>> {
+    let root = doc.get_or_insert_map(BLOCS_MAP);
+    let mut txn = doc.transact_mut();
+    for bloc in blocs {
+        let id = bloc.id.clone().ok_or_else(|| anyhow!("bloc without id"))?;
+        let fields = match root.get(&txn, &id) {
+            Some(Out::YMap(fields)) => fields,
+            _ => root.insert(&mut txn, id.as_str(), MapPrelim::default()),
+        };
+        // seuls les champs modifiés produisent une opération
+        if field_str(&fields, &txn, "position").as_deref() != Some(bloc.position.as_str()) {
+            fields.insert(&mut txn, "position", bloc.position.clone());
+        }
+        if field_str(&fields, &txn, "content").as_deref() != Some(bloc.content.as_str()) {
+            fields.insert(&mut txn, "content", bloc.content.clone());
+        }
+        if field_str(&fields, &txn, "bloc_type").as_deref() != Some(bloc.bloc_type.as_str()) {
+            fields.insert(&mut txn, "bloc_type", bloc.bloc_type.clone());
+        }
+        if field_i64(&fields, &txn, "created_at") != bloc.created_at {
+            fields.insert(&mut txn, "created_at", bloc.created_at);
+        }
+        fields.insert(&mut txn, "updated_at", bloc.updated_at);
+    }
+    for id in removed {
+        root.remove(&mut txn, id);
+    }
+    Ok(txn.encode_update_v1())
+}
+
+// mêmes champs que la table blocs
+fn same_bloc(a: &BlocJson, b: &BlocJson) -> bool {
+    a.position == b.position
+        && a.content == b.content
+        && a.bloc_type == b.bloc_type
+        && a.created_at == b.created_at
+        && a.updated_at == b.updated_at
+}
+
+// rejoue le journal puis y reporte les écritures locales faites hors du CRDT ;
+// une page sans journal est ainsi amorcée depuis ses blocs actuels. Renvoie
+// aussi le vecteur d'état du journal seul et si le document a été rattrapé.
+// `client_id` est celui de la base (Database::crdt_client_id).
+fn load_doc(client_id: u64, page_id: &str, log: &PageUpdates, blocs: &[BlocJson]) -> Result<(Doc, StateVector, bool)> {
+    let doc = Doc::with_client_id(client_id);
+    {
+        let mut txn = doc.transact_mut();
+        for update in &log.updates {
+            txn.apply_update(Update::decode_v1(update)?)?;
+        }
+    }
+    let logged = doc.transact().state_vector();
+
+    let mut known: HashMap<String, BlocJson> = blocs_of(&doc, page_id)
+        .into_iter()
+        .filter_map(|bloc| Some((bloc.id.clone()?, bloc)))
+        .collect();
+    let changed: Vec<BlocJson> = blocs
+        .iter()
+        .filter(|bloc| match bloc.id.as_ref().and_then(|id| known.remove(id)) {
+            Some(merged) => !same_bloc(&merged, bloc),
+            None => true,
+        })
+        .cloned()
+        .collect();
+    // connus du journal mais plus dans la table : supprimés localement
+    let removed: Vec<String> = known.into_keys().collect();
+    let dirty = !changed.is_empty() || !removed.is_empty();
+    if dirty {
+        write_blocs(&doc, &changed, &removed)?;
+    }
+    Ok((doc, logged, dirty))
+}
+
+// ce que le document a de plus que le journal, à y ajouter. Le diff contient
+// tout l'ensemble des suppressions : une suppression seule n'avance pas le
+// vecteur d'état, d'où le drapeau `dirty`.
+fn unlogged(doc: &Doc, logged: &StateVector, dirty: bool) -> Vec<Vec<u8>> {
+    if dirty {
+        vec![doc.transact().encode_diff_v1(logged)]
+    } else {
+        Vec::new()
+    }
+}
+
+// au-delà de COMPACT_AFTER, le journal de la page devient un seul état fusionné
+fn compacted(doc: &Doc, log: &PageUpdates, updates: &[Vec<u8>]) -> Option<Vec<u8>> {
+    if log.updates.len() + updates.len() > COMPACT_AFTER {
+        Some(doc.transact().encode_state_as_update_v1(&StateVector::default()))
+    } else {
+        None
+    }
+}
+
+impl CrdtStore {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub async fn state_vector(&self, page_id: &str) -> Result<Vec<u8>> {
+        let client_id = self.db.crdt_client_id().await?;
+        self.db
+            .merge_page(page_id.to_string(), |log, blocs| {
+                let (doc, logged, dirty) = load_doc(client_id, page_id, log, blocs)?;
+                let sv = doc.transact().state_vector().encode_v1();
+                let updates = unlogged(&doc, &logged, dirty);
+                Ok((PageMerge { updates, ..PageMerge::default() }, sv))
+            })
+            .await
+    }
+
+    // ce qui manque à un pair dont on a reçu le vecteur d'état
+    pub async fn diff(&self, page_id: &str, remote_state_vector: &[u8]) -> Result<Vec<u8>> {
+        let remote = StateVector::decode_v1(remote_state_vector)?;
+        let client_id = self.db.crdt_client_id().await?;
+        self.db
+            .merge_page(page_id.to_string(), |log, blocs| {
+                let (doc, logged, dirty) = load_doc(client_id, page_id, log, blocs)?;
+                let update = doc.transact().encode_diff_v1(&remote);
+                let updates = unlogged(&doc, &logged, dirty);
+                Ok((PageMerge { updates, ..PageMerge::default() }, update))
+            })
+            .await
+    }
+
+    // mise à jour venue d'un autre appareil : journalisée puis matérialisée
+    pub async fn apply_remote_update(&self, page_id: &str, update: &[u8]) -> Result<Vec<BlocJson>> {
+        // refuse les octets illisibles avant de les écrire dans le journal
+        Update::decode_v1(update).map_err(|e| anyhow!("invalid update: {}", e))?;
+        let client_id = self.db.crdt_client_id().await?;
+        self.db
+            .merge_page(page_id.to_string(), |log, blocs| {
+                let (doc, logged, dirty) = load_doc(client_id, page_id, log, blocs)?;
+                // le rattrapage local passe avant la mise à jour distante
+                let mut updates = unlogged(&doc, &logged, dirty);
+                doc.transact_mut().apply_update(Update::decode_v1(update)?)?;
+                updates.push(update.to_vec());
+                let merged = blocs_of(&doc, page_id);
+                let compacted = compacted(&doc, log, &updates);
+                Ok((PageMerge { updates, blocs: Some(merged.clone()), compacted }, merged))
+            })
+            .await
+    }
+
+    // modifications locales (blocs écrits ou supprimés par l'éditeur) ;
+    // renvoie la mise à jour à diffuser aux autres appareils
+    pub async fn record_local_changes(
+        &self,
+        page_id: &str,
+        blocs: &[BlocJson],
+        removed: &[String],
+    ) -> Result<Vec<u8>> {
+        let client_id = self.db.crdt_client_id().await?;
+        self.db
+            .merge_page(page_id.to_string(), |log, current| {
+                let (doc, logged, _) = load_doc(client_id, page_id, log, current)?;
+                write_blocs(&doc, blocs, removed)?;
+                let update = doc.transact().encode_diff_v1(&logged);
+                let updates = vec![update.clone()];
+                // la table suit le document, sinon le prochain rattrapage
+                // annulerait ces changements
+                let merged = blocs_of(&doc, page_id);
+                let compacted = compacted(&doc, log, &updates);
+                Ok((PageMerge { updates, blocs: Some(merged), compacted }, update))
+            })
+            .await
+    }
+
+    // réécrit les blocs de la page depuis l'état fusionné
+    pub async fn materialise(&self, page_id: &str) -> Result<Vec<BlocJson>> {
+        let client_id = self.db.crdt_client_id().await?;
+        self.db
+            .merge_page(page_id.to_string(), |log, blocs| {
+                let (doc, logged, dirty) = load_doc(client_id, page_id, log, blocs)?;
+                let updates = unlogged(&doc, &logged, dirty);
+                let merged = blocs_of(&doc, page_id);
+                let compacted = compacted(&doc, log, &updates);
+                Ok((PageMerge { updates, blocs: Some(merged.clone()), compacted }, merged))
+            })
+            .await
+    }
+}
@@ -0,0 +1,124 @@
+use tauritest_crdt::CrdtStore;
+use tauritest_db::{BlocJson, Database};
+use yrs::updates::decoder::Decode;
+use yrs::StateVector;
+
+fn bloc(id: &str, position: &str, content: &str) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: content.to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 1,
+        updated_at: 1,
+    }
+}
+
+// deux appareils partent du même état puis échangent leurs différences
+async fn sync(a: &CrdtStore, b: &CrdtStore) {
+    let missing_on_b = a.diff("p1", &b.state_vector("p1").await.unwrap()).await.unwrap();
+    let missing_on_a = b.diff("p1", &a.state_vector("p1").await.unwrap()).await.unwrap();
+    b.apply_remote_update("p1", &missing_on_b).await.unwrap();
+    a.apply_remote_update("p1", &missing_on_a).await.unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_edits_to_different_fields_both_survive() {
+    let laptop = CrdtStore::new(Database::new_in_memory().await.unwrap());
+    let desktop = CrdtStore::new(Database::new_in_memory().await.unwrap());
+
+    laptop.record_local_changes("p1", &[bloc("b1", "a0", "hello")], &[]).await.unwrap();
+    sync(&laptop, &desktop).await;
+
+    // l'un modifie le contenu, l'autre déplace le bloc et en ajoute un
+    laptop.record_local_changes("p1", &[bloc("b1", "a0", "hello world")], &[]).await.unwrap();
+    desktop
+        .record_local_changes("p1", &[bloc("b1", "a2", "hello"), bloc("b2", "a1", "new")], &[])
+        .await
+        .unwrap();
+    sync(&laptop, &desktop).await;
+
+    let on_laptop = laptop.materialise("p1").await.unwrap();
+    let on_desktop = desktop.materialise("p1").await.unwrap();
+    let view = |blocs: &[BlocJson]| -> Vec<(String, String, String)> {
+        blocs
+            .iter()
+            .map(|b| (b.id.clone().unwrap(), b.position.clone(), b.content.clone()))
+            .collect()
+    };
+    assert_eq!(view(&on_laptop), view(&on_desktop));
+    assert_eq!(
+        view(&on_laptop),
+        vec![
+            ("b2".to_string(), "a1".to_string(), "new".to_string()),
+            ("b1".to_string(), "a2".to_string(), "hello world".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn removals_propagate_and_garbage_is_rejected() {
+    let a = CrdtStore::new(Database::new_in_memory().await.unwrap());
+    let b = CrdtStore::new(Database::new_in_memory().await.unwrap());
+
+    a.record_local_changes("p1", &[bloc("b1", "a0", "x"), bloc("b2", "a1", "y")], &[]).await.unwrap();
+    sync(&a, &b).await;
+    a.record_local_changes("p1", &[], &["b1".to_string()]).await.unwrap();
+    sync(&a, &b).await;
+
+    let blocs = b.materialise("p1").await.unwrap();
+    assert_eq!(blocs.len(), 1);
+    assert_eq!(blocs[0].id.as_deref(), Some("b2"));
+
+    assert!(b.apply_remote_update("p1", &[0xff, 0xff, 0xff]).await.is_err());
+}
+
+#[tokio::test]
+async fn first_materialise_keeps_blocs_written_before_the_crdt_log() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_bloc(&bloc("b1", "a0", "already here")).await.unwrap();
+    let store = CrdtStore::new(db.clone());
+
+    store.record_local_changes("p1", &[bloc("b2", "a1", "new")], &[]).await.unwrap();
+    let blocs = store.materialise("p1").await.unwrap();
+
+    let ids: Vec<_> = blocs.iter().map(|b| b.id.clone().unwrap()).collect();
+    assert_eq!(ids, vec!["b1", "b2"]);
+    assert_eq!(db.get_blocs_by_page_id("p1".to_string()).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn edits_saved_outside_the_crdt_survive_a_remote_update() {
+    let db = Database::new_in_memory().await.unwrap();
+    let laptop = CrdtStore::new(db.clone());
+    let desktop = CrdtStore::new(Database::new_in_memory().await.unwrap());
+
+    laptop.record_local_changes("p1", &[bloc("b1", "a0", "hello")], &[]).await.unwrap();
+    sync(&laptop, &desktop).await;
+
+    // sauvegarde ordinaire de l'éditeur, sans record_local_changes
+    db.update_bloc_content("b1".to_string(), "edited".to_string(), 2).await.unwrap();
+    desktop.record_local_changes("p1", &[bloc("b2", "a1", "new")], &[]).await.unwrap();
+    let update = desktop.diff("p1", &laptop.state_vector("p1").await.unwrap()).await.unwrap();
+    let blocs = laptop.apply_remote_update("p1", &update).await.unwrap();
+
+    let view: Vec<(String, String)> = blocs.iter().map(|b| (b.id.clone().unwrap(), b.content.clone())).collect();
+    assert_eq!(view, vec![("b1".to_string(), "edited".to_string()), ("b2".to_string(), "new".to_string())]);
+    assert_eq!(db.get_bloc_by_id("b1".to_string()).await.unwrap().content, "edited");
+
+    // l'édition part aussi vers l'autre appareil
+    sync(&laptop, &desktop).await;
+    let on_desktop = desktop.materialise("p1").await.unwrap();
+    assert_eq!(on_desktop[0].content, "edited");
+}
+
+#[tokio::test]
+async fn local_changes_reuse_the_database_client_id() {
+    let store = CrdtStore::new(Database::new_in_memory().await.unwrap());
+    for content in ["one", "two", "three"] {
+        store.record_local_changes("p1", &[bloc("b1", "a0", content)], &[]).await.unwrap();
+    }
+    let sv = StateVector::decode_v1(&store.state_vector("p1").await.unwrap()).unwrap();
+    assert_eq!(sv.len(), 1);
+}
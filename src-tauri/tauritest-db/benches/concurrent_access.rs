@@ -0,0 +1,99 @@
+// Mesure le débit de lectures (get_blocs_by_page_id) et d'écritures
+// (update_bloc_content) lancées en parallèle sur le même Database.
+//
+// cargo bench -p tauritest-db --bench concurrent_access
+
+use tauritest_db::{BlocJson, Database, PageJson};
+use std::time::{Duration, Instant};
+
+const BLOCS_PER_PAGE: usize = 200;
+const READERS: usize = 4;
+const WRITERS: usize = 1;
+const RUN_FOR: Duration = Duration::from_secs(3);
+
+async fn seed(db: &Database) -> Vec<String> {
+    let page = PageJson {
+        id: Some("bench-page".to_string()),
+        path: "/bench".to_string(),
+        title: "bench".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    };
+    db.new_page(&page).await.unwrap();
+
+    let mut ids = Vec::with_capacity(BLOCS_PER_PAGE);
+    for i in 0..BLOCS_PER_PAGE {
+        let bloc = BlocJson {
+            id: Some(format!("bench-bloc-{}", i)),
+            position: format!("a{:04}", i),
+            content: format!("{{\"text\":\"bloc {}\"}}", i),
+            page_id: "bench-page".to_string(),
+            bloc_type: "paragraph".to_string(),
+            created_at: 0,
+            updated_at: 0,
+        };
+        ids.push(db.new_bloc(&bloc).await.unwrap());
+    }
+    ids
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() {
+    let dir = std::env::temp_dir().join(format!("tauritest-bench-{}", std::process::id()));
+    let db_path = dir.join("bench.db");
+    let db = Database::new(db_path.to_str().unwrap()).await.unwrap();
+    let ids = seed(&db).await;
+
+    let deadline = Instant::now() + RUN_FOR;
+    let mut readers = Vec::new();
+    for _ in 0..READERS {
+        let db = db.clone();
+        readers.push(tokio::spawn(async move {
+            let mut count = 0u64;
+            while Instant::now() < deadline {
+                db.get_blocs_by_page_id("bench-page".to_string()).await.unwrap();
+                count += 1;
+            }
+            count
+        }));
+    }
+
+    let mut writers = Vec::new();
+    for w in 0..WRITERS {
+        let db = db.clone();
+        let ids = ids.clone();
+        writers.push(tokio::spawn(async move {
+            let mut count = 0u64;
+            while Instant::now() < deadline {
+                let id = ids[count as usize % ids.len()].clone();
+                let content = format!("{{\"text\":\"writer {} edit {}\"}}", w, count);
+                db.update_bloc_content(id, content, count as i64).await.unwrap();
+                count += 1;
+            }
+            count
+        }));
+    }
+
+    let mut reads = 0;
+    for handle in readers {
+        reads += handle.await.unwrap();
+    }
+    let mut writes = 0;
+    for handle in writers {
+        writes += handle.await.unwrap();
+    }
+
+    let secs = RUN_FOR.as_secs_f64();
+    println!(
+        "{} readers / {} writers over {:.1}s: {:.0} get_blocs_by_page_id/s, {:.0} update_bloc_content/s",
+        READERS,
+        WRITERS,
+        secs,
+        reads as f64 / secs,
+        writes as f64 / secs
+    );
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(dir);
+}
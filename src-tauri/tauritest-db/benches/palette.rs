@@ -0,0 +1,65 @@
+// Vérifie qu'une frappe dans la palette répond en moins de 10 ms sur 50 000
+// pages. À lancer avec optimisations, comme l'application :
+//
+// cargo bench -p tauritest-db --bench palette
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauritest_db::palette::VisitStats;
+use tauritest_db::{PaletteEntry, PaletteIndex, PaletteKind};
+
+const PAGES: usize = 50_000;
+const BOUND: Duration = Duration::from_millis(10);
+const ROUNDS: usize = 20;
+
+fn index() -> PaletteIndex {
+    let words = ["project", "alpha", "meeting", "notes", "budget", "review", "design", "roadmap", "journal", "ideas"];
+    let mut entries = Vec::new();
+    let mut visits = HashMap::new();
+    for i in 0..PAGES {
+        let id = format!("p{}", i);
+        let title = format!("{} {} {}", words[i % 10], words[(i / 10) % 10], i);
+        entries.push(PaletteEntry {
+            kind: PaletteKind::Page,
+            id: id.clone(),
+            label: title.clone(),
+            detail: format!("/{}/{}", words[(i / 100) % 10], words[(i / 1000) % 10]),
+            page_id: Some(id.clone()),
+            anchor: None,
+        });
+        if i % 50 == 0 {
+            visits.insert(id, VisitStats { count: (i % 7) as i64, last_visited_at: i as i64 });
+        }
+    }
+    PaletteIndex::new(entries, visits, 0, 0)
+}
+
+fn main() {
+    if cfg!(debug_assertions) {
+        eprintln!("palette: build without debug assertions (cargo bench) to check the bound");
+    }
+    let index = index();
+
+    // meilleur temps de chaque frappe sur plusieurs tours, pour écarter le
+    // bruit de la machine ; la plus lente des frappes est comparée au seuil
+    let mut slowest = Duration::ZERO;
+    for query in ["p", "pr", "pro", "proj", "proj al", "proj alp 12", "zzz", "rvw", "jrnl ideas"] {
+        let mut best = Duration::MAX;
+        for _ in 0..ROUNDS {
+            let start = Instant::now();
+            let hits = index.search(query, 20, PAGES as i64);
+            best = best.min(start.elapsed());
+            assert!(hits.len() <= 20);
+        }
+        println!("palette: {:>12} {:?}", format!("{:?}", query), best);
+        slowest = slowest.max(best);
+    }
+
+    println!("palette: slowest keystroke on {} pages: {:?}", PAGES, slowest);
+    assert!(
+        cfg!(debug_assertions) || slowest < BOUND,
+        "slowest keystroke took {:?}, over {:?}",
+        slowest,
+        BOUND
+    );
+}
@@ -0,0 +1,290 @@
+// Fils de commentaires (texteditor/commenting) : un fil est ancré à un bloc,
+// la page se déduit du bloc pour suivre ses déplacements.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, Sqlite, Transaction};
+use std::collections::HashMap;
+
+use crate::database::Database;
+
+// même texte que markDeleted côté éditeur
+pub const DELETED_COMMENT: &str = "[Deleted Comment]";
+
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct CommentJson {
+    pub id: Option<String>,
+    pub thread_id: String,
+    pub author: String,
+    pub content: String,
+    #[serde(default)]
+    pub deleted: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize)]
+pub struct ThreadJson {
+    pub id: Option<String>,
+    pub bloc_id: String,
+    // renseigné à la lecture, None si le bloc n'existe plus
+    #[serde(default)]
+    pub page_id: Option<String>,
+    pub quote: String,
+    #[serde(default)]
+    pub resolved: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub comments: Vec<CommentJson>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCommentCount {
+    pub page_id: String,
+    pub threads: i64,
+    pub comments: i64,
+}
+
+const THREAD_COLUMNS: &str = "t.id, t.bloc_id, b.page_id, t.quote, t.resolved, t.created_at, t.updated_at
+    FROM comment_threads t
+    LEFT JOIN blocs b ON b.id = t.bloc_id";
+
+async fn insert_comment(tx: &mut Transaction<'_, Sqlite>, comment: &CommentJson) -> Result<String> {
+    let id = comment
+        .id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    sqlx::query(
+        "INSERT INTO comments (id, thread_id, author, content, deleted, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(&comment.thread_id)
+    .bind(&comment.author)
+    .bind(&comment.content)
+    .bind(comment.deleted)
+    .bind(comment.created_at)
+    .bind(comment.updated_at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(id)
+}
+
+impl Database {
+    // crée le fil et ses premiers commentaires ; l'id est généré s'il manque
+    pub async fn new_thread(&self, thread: &ThreadJson) -> Result<String> {
+        let id = thread
+            .id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO comment_threads (id, bloc_id, quote, resolved, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&thread.bloc_id)
+        .bind(&thread.quote)
+        .bind(thread.resolved)
+        .bind(thread.created_at)
+        .bind(thread.updated_at)
+        .execute(&mut *tx)
+        .await?;
+
+        for comment in &thread.comments {
+            let comment = CommentJson {
+                thread_id: id.clone(),
+                ..comment.clone()
+            };
+            insert_comment(&mut tx, &comment).await?;
+        }
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    // réponse dans un fil existant, qui est rouvert s'il était résolu
+    pub async fn new_comment(&self, comment: &CommentJson) -> Result<String> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows_affected = sqlx::query(
+            "UPDATE comment_threads SET resolved = 0, updated_at = ? WHERE id = ?",
+        )
+        .bind(comment.created_at)
+        .bind(&comment.thread_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if rows_affected == 0 {
+            anyhow::bail!("thread not found: {}", comment.thread_id);
+        }
+
+        let id = insert_comment(&mut tx, comment).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    pub async fn update_comment_content(&self, id: String, content: String, updated_at: i64) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            "UPDATE comments SET content = ?, updated_at = ? WHERE id = ? AND deleted = 0",
+        )
+        .bind(content)
+        .bind(updated_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    // suppression douce : le commentaire reste à sa place dans le fil
+    pub async fn delete_comment(&self, id: String, updated_at: i64) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            "UPDATE comments SET deleted = 1, content = ?, updated_at = ? WHERE id = ? AND deleted = 0",
+        )
+        .bind(DELETED_COMMENT)
+        .bind(updated_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn set_thread_resolved(&self, id: String, resolved: bool, updated_at: i64) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            "UPDATE comment_threads SET resolved = ?, updated_at = ? WHERE id = ? AND resolved <> ?",
+        )
+        .bind(resolved)
+        .bind(updated_at)
+        .bind(id)
+        .bind(resolved)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    // le fil et tous ses commentaires, comme deleteCommentOrThread sur un fil
+    pub async fn delete_thread(&self, id: String) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM comments WHERE thread_id = ?")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+        let rows_affected = sqlx::query("DELETE FROM comment_threads WHERE id = ?")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn get_thread_by_id(&self, id: String) -> Result<ThreadJson> {
+        let mut thread: ThreadJson = sqlx::query_as(&format!("SELECT {} WHERE t.id = ?", THREAD_COLUMNS))
+            .bind(&id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        thread.comments = sqlx::query_as(
+            "SELECT * FROM comments WHERE thread_id = ? ORDER BY created_at, id",
+        )
+        .bind(&id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(thread)
+    }
+
+    pub async fn get_threads_by_bloc_id(&self, bloc_id: String) -> Result<Vec<ThreadJson>> {
+        let threads = sqlx::query_as(&format!(
+            "SELECT {} WHERE t.bloc_id = ? ORDER BY t.created_at, t.id",
+            THREAD_COLUMNS
+        ))
+        .bind(bloc_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.with_comments(threads).await
+    }
+
+    // fils de la page dans l'ordre des blocs, les résolus seulement sur demande
+    pub async fn get_threads_by_page_id(&self, page_id: String, include_resolved: bool) -> Result<Vec<ThreadJson>> {
+        let threads = sqlx::query_as(&format!(
+            "SELECT {} WHERE b.page_id = ? AND (? OR t.resolved = 0)
+            ORDER BY b.position, t.created_at, t.id",
+            THREAD_COLUMNS
+        ))
+        .bind(page_id)
+        .bind(include_resolved)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.with_comments(threads).await
+    }
+
+    // pour les pastilles de la barre latérale : pages ayant des fils ouverts,
+    // les commentaires supprimés ne comptent pas
+    pub async fn get_unresolved_comment_counts(&self) -> Result<Vec<PageCommentCount>> {
+        let rows = sqlx::query(
+            "SELECT b.page_id,
+                COUNT(DISTINCT t.id) as threads,
+                COUNT(c.id) as comments
+            FROM comment_threads t
+            JOIN blocs b ON b.id = t.bloc_id
+            LEFT JOIN comments c ON c.thread_id = t.id AND c.deleted = 0
+            WHERE t.resolved = 0
+            GROUP BY b.page_id
+            ORDER BY b.page_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PageCommentCount {
+                page_id: row.get("page_id"),
+                threads: row.get("threads"),
+                comments: row.get("comments"),
+            })
+            .collect())
+    }
+
+    async fn with_comments(&self, mut threads: Vec<ThreadJson>) -> Result<Vec<ThreadJson>> {
+        if threads.is_empty() {
+            return Ok(threads);
+        }
+
+        let placeholders = vec!["?"; threads.len()].join(", ");
+        let sql = format!(
+            "SELECT * FROM comments WHERE thread_id IN ({}) ORDER BY created_at, id",
+            placeholders
+        );
+        let mut query = sqlx::query_as::<_, CommentJson>(&sql);
+        for thread in &threads {
+            query = query.bind(thread.id.clone());
+        }
+
+        let mut by_thread: HashMap<String, Vec<CommentJson>> = HashMap::new();
+        for comment in query.fetch_all(&self.pool).await? {
+            by_thread.entry(comment.thread_id.clone()).or_default().push(comment);
+        }
+        for thread in &mut threads {
+            if let Some(id) = &thread.id {
+                thread.comments = by_thread.remove(id).unwrap_or_default();
+            }
+        }
+
+        Ok(threads)
+    }
+}
@@ -1,11 +1,18 @@
 use anyhow::{Ok, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Row, Sqlite};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::{Pool, Row, Sqlite, Transaction};
 use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::hash::{Hash, Hasher};
 use std::collections::hash_map::DefaultHasher;
 
+use crate::fractional_index;
+use crate::tags::index_hashtags;
+use crate::undo::{begin_undo_group, end_undo_group};
+
 // Structure pour représenter un document JSON dans la base de données
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JsonDocument {
@@ -14,7 +21,7 @@ pub struct JsonDocument {
     pub data: JsonValue,
 }
 
-#[derive(sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Debug, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
 pub struct PageJson {
     pub id: Option<String>,
     pub path: String,
@@ -24,7 +31,7 @@ pub struct PageJson {
     pub updated_at: i64,
 }
 
-#[derive(sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Debug, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
 pub struct BlocJson {
     pub id: Option<String>,
     pub position: String,
@@ -35,12 +42,12 @@ pub struct BlocJson {
     pub updated_at: i64,
 }
 
-#[derive(sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Debug, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
 pub struct PropsJson {
     pub id: Option<String>,
     pub key: String,
     pub value: String,
-    pub bloc_id: i64,
+    pub bloc_id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,8 +57,89 @@ pub struct SearchResult {
     pub data: JsonValue,
     pub score: Option<f64>,
 }
+
+// millisecondes depuis l'epoch, comme Date.now() côté éditeur
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// empreinte du contenu d'un bloc, stockée dans blocs.checksum
+pub fn checksum(content: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish().to_string()
+}
+
+// contenu actuel du bloc, None s'il n'existe pas
+pub(crate) async fn bloc_content(tx: &mut Transaction<'_, Sqlite>, id: Option<&str>) -> Result<Option<String>> {
+    let content = sqlx::query("SELECT content FROM blocs WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .map(|row| row.get(0));
+    Ok(content)
+}
+
+// les props voyagent avec leur bloc, que la synchronisation choisit par
+// updated_at : une prop modifiée date donc son bloc
+async fn touch_bloc(tx: &mut Transaction<'_, Sqlite>, bloc_id: &str) -> Result<()> {
+    sqlx::query("UPDATE blocs SET updated_at = ? WHERE id = ?")
+        .bind(now_millis())
+        .bind(bloc_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// déplace le bloc dans la transaction de l'appelant ; voir update_bloc_position
+pub(crate) async fn update_position_in(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: &str,
+    new_position: &str,
+    updated_at: i64,
+) -> Result<i8> {
+    let current_position: String = sqlx::query("SELECT position FROM blocs WHERE id = ?")
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await?
+        .get(0);
+    if current_position == new_position {
+        return Ok(Database::NO_CHANGE);
+    }
+    if fractional_index::validate_order_key(new_position).is_err() {
+        return Ok(Database::ERROR);
+    }
+
+    // le test de doublon et l'écriture dans la même requête
+    let rows_affected = sqlx::query(
+        "UPDATE blocs SET position = ?, updated_at = ?
+        WHERE id = ? AND NOT EXISTS (
+            SELECT 1 FROM blocs other
+            WHERE other.page_id = blocs.page_id AND other.position = ? AND other.id <> blocs.id
+        )",
+    )
+    .bind(new_position)
+    .bind(updated_at)
+    .bind(id)
+    .bind(new_position)
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    if rows_affected > 0 {
+        Ok(Database::SUCCESS)
+    } else {
+        Ok(Database::ERROR)
+    }
+}
+
+// Pool<Sqlite> est un handle partagé : cloner Database ne rouvre pas de connexion
+#[derive(Clone)]
 pub struct Database {
-    pool: Pool<Sqlite>,
+    pub(crate) pool: Pool<Sqlite>,
 }
 
 impl Database {
@@ -59,8 +147,20 @@ impl Database {
     pub const ERROR:i8 = -1;
     pub const NO_CHANGE:i8 = 0;
 
+    pub const MAX_CONNECTIONS: u32 = 5;
+    // temps d'attente quand une autre connexion tient le verrou d'écriture
+    pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
     // Initialise une nouvelle connexion à la base de données SQLite
+    // et met le schéma à jour
     pub async fn new(db_path: &str) -> Result<Self> {
+        let db = Self::connect(db_path).await?;
+        db.migrate().await?;
+        Ok(db)
+    }
+
+    // Ouvre la base sans appliquer les migrations (outil en ligne de commande)
+    pub async fn connect(db_path: &str) -> Result<Self> {
         let db_path = Path::new(db_path);
 
         // Crée le répertoire parent si nécessaire
@@ -75,84 +175,54 @@ impl Database {
             std::fs::File::create(db_path)?;
         }
 
-        let database_url = format!("sqlite:{}", db_path.display());
+        // WAL : les lectures ne bloquent plus pendant une écriture
+        let options = SqliteConnectOptions::new()
+            .filename(db_path)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(Self::BUSY_TIMEOUT);
+
         let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+            .max_connections(Self::MAX_CONNECTIONS)
+            .connect_with(options)
             .await?;
 
-        // Crée la table si elle n'existe pas
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS pages (
-                id TEXT NOT NULL,
-                path TEXT NOT NULL,
-                title TEXT NOT NULL,
-                cache TEXT,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )"#,
-        )
-        .execute(&pool)
-        .await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS blocs (
-                id TEXT NOT NULL,
-                position TEXT NOT NULL,
-                content TEXT NOT NULL,
-                checksum TEXT NOT NULL,
-                page_id TEXT NOT NULL,
-                bloc_type TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )"#,
-        )
-        .execute(&pool)
-        .await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS props (
-                id TEXT NOT NULL,
-                key TEXT NOT NULL,
-                value TEXT NOT NULL,
-                bloc_id TEXT NOT NULL
-            )"#,
-        )
-        .execute(&pool)
-        .await?;
+        Ok(Database { pool })
+    }
 
-        // Création de la table si elle n'existe pas
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS bloc (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+    // Base en mémoire, perdue à la fermeture : utilisée par les tests
+    pub async fn new_in_memory() -> Result<Self> {
+        // sqlx donne un nom unique à chaque base ":memory:" et active le cache partagé,
+        // toutes les connexions du pool voient donc les mêmes tables
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")?
+            .busy_timeout(Self::BUSY_TIMEOUT);
 
-        // Crée un index sur la colonne collection pour de meilleures performances
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_pages_title ON pages(title)")
-            .execute(&pool)
+        // la base disparaît avec sa dernière connexion : on en garde une ouverte
+        let pool = SqlitePoolOptions::new()
+            .max_connections(Self::MAX_CONNECTIONS)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
             .await?;
 
-        Ok(Database { pool })
+        let db = Database { pool };
+        db.migrate().await?;
+        Ok(db)
     }
 
     // new bloc
+    // la clé de position est vérifiée comme dans update_bloc_position : une
+    // clé mal formée ou déjà prise sur la page est une erreur
     pub async fn new_bloc(&self, bloc_json: &BlocJson) -> Result<String> {
-        let mut hasher = DefaultHasher::new();
-        bloc_json.content.hash(&mut hasher);
-        let checksum = hasher.finish().to_string();
+        let checksum = checksum(&bloc_json.content);
+        fractional_index::validate_order_key(&bloc_json.position)?;
         
-        let id = sqlx::query(
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let id: Option<String> = sqlx::query(
             "INSERT INTO blocs (id, position, content, checksum, page_id, bloc_type, created_at, updated_at) 
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?) 
+            SELECT ?, ?, ?, ?, ?, ?, ?, ? 
+            WHERE NOT EXISTS (SELECT 1 FROM blocs WHERE page_id = ? AND position = ?) 
             RETURNING id")
             .bind(&bloc_json.id)
             .bind(&bloc_json.position)
@@ -160,33 +230,48 @@ impl Database {
             .bind(checksum)
             .bind(&bloc_json.page_id)
             .bind(&bloc_json.bloc_type)
-            .bind(&bloc_json.created_at)
-            .bind(&bloc_json.updated_at)
-            .fetch_one(&self.pool)
+            .bind(bloc_json.created_at)
+            .bind(bloc_json.updated_at)
+            .bind(&bloc_json.page_id)
+            .bind(&bloc_json.position)
+            .fetch_optional(&mut *tx)
             .await?
-            .get(0);
+            .map(|row| row.get(0));
+        let Some(id) = id else {
+            anyhow::bail!("position {} already taken on page {}", bloc_json.position, bloc_json.page_id);
+        };
+        index_hashtags(&mut tx, &id, None, &bloc_json.content).await?;
+        tx.commit().await?;
         Ok(id)
     }
 
     // use when a content was edited, call on bloc lose focus
+    // la position passe par update_position_in : une clé invalide ou prise
+    // annule toute la sauvegarde
     pub async fn update_bloc(&self, bloc_json: &BlocJson) -> Result<bool> {
-        let mut hasher = DefaultHasher::new();
-        bloc_json.content.hash(&mut hasher);
-        let checksum = hasher.finish().to_string();
+        let checksum = checksum(&bloc_json.content);
         
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let (Some(id), Some(before)) = (bloc_json.id.as_deref(), bloc_content(&mut tx, bloc_json.id.as_deref()).await?) else {
+            return Ok(false);
+        };
+        if update_position_in(&mut tx, id, &bloc_json.position, bloc_json.updated_at).await? == Self::ERROR {
+            anyhow::bail!("invalid or duplicate position {} for bloc {}", bloc_json.position, id);
+        }
         let rows_affected = sqlx::query(
-            "UPDATE blocs SET position = ?, content = ?, checksum = ?, bloc_type = ?, updated_at = ? 
+            "UPDATE blocs SET content = ?, checksum = ?, bloc_type = ?, updated_at = ? 
             WHERE id = ?",
         )
-        .bind(&bloc_json.position)
         .bind(&bloc_json.content)
         .bind(&checksum)
         .bind(&bloc_json.bloc_type)
-        .bind(&bloc_json.updated_at)
-        .bind(&bloc_json.id)
-        .execute(&self.pool)
+        .bind(bloc_json.updated_at)
+        .bind(id)
+        .execute(&mut *tx)
         .await?
         .rows_affected();
+        index_hashtags(&mut tx, id, Some(&before), &bloc_json.content).await?;
+        tx.commit().await?;
 
         Ok(rows_affected > 0)
     }
@@ -199,14 +284,14 @@ impl Database {
     ) -> Result<i8> {
         let current_checksum = self.get_checksum(id.clone()).await?;
         
-        let mut hasher = DefaultHasher::new();
-        new_content.hash(&mut hasher);
-        let new_checksum = hasher.finish().to_string();
+        let new_checksum = checksum(&new_content);
         
         if current_checksum == new_checksum {
             return Ok(Self::NO_CHANGE);
         }
 
+        let mut tx = self.pool.begin().await?;
+        let before = bloc_content(&mut tx, Some(&id)).await?;
         let rows_affected = sqlx::query(
             "UPDATE blocs SET content = ?, checksum = ?, updated_at = ? 
             WHERE id = ?",
@@ -215,9 +300,13 @@ impl Database {
         .bind(&new_checksum)
         .bind(updated_at)
         .bind(&id)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?
         .rows_affected();
+        if let Some(before) = before {
+            index_hashtags(&mut tx, &id, Some(&before), &new_content).await?;
+        }
+        tx.commit().await?;
 
         if rows_affected > 0 {
             Ok(Self::SUCCESS)
@@ -227,43 +316,31 @@ impl Database {
     }
 
     // use when bloc was dragged and change position
+    // NO_CHANGE si le bloc est déjà à cette place. ERROR (nouveau depuis le
+    // contrôle des clés) si la clé est mal formée ou déjà prise par un autre
+    // bloc de la page : rien n'est écrit, l'éditeur doit recalculer la clé.
     pub async fn update_bloc_position(
         &self,
         id: String,
         new_position: String,
         updated_at: i64,
     ) -> Result<i8> {
-
-        let current_position = self.get_position(id.clone()).await?;
-        if current_position == new_position {
-            return Ok(Self::NO_CHANGE);
-        }
-            
-        let rows_affected = sqlx::query(
-            "UPDATE blocs SET position = ?, updated_at = ? 
-            WHERE id = ?",
-        )
-        .bind(&new_position)
-        .bind(&updated_at)
-        .bind(&id)
-        .execute(&self.pool)
-        .await?
-        .rows_affected();
-
-        if rows_affected > 0 {
-            Ok(Self::SUCCESS)
-        } else {
-            Ok(Self::NO_CHANGE)
-        }
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let status = update_position_in(&mut tx, &id, &new_position, updated_at).await?;
+        tx.commit().await?;
+        Ok(status)
     }
 
     // use when a bloc was moved on an another page
+    // le déplacement date le bloc : la synchronisation ne voit que updated_at
     pub async fn update_bloc_page_id(&self, id: String, new_page_id: String) -> Result<bool> {
         let rows_affected = sqlx::query(
-            "UPDATE blocs SET page_id = ? 
+            "UPDATE blocs SET page_id = ?, updated_at = CASE WHEN page_id = ? THEN updated_at ELSE ? END 
             WHERE id = ?",
         )
         .bind(&new_page_id)
+        .bind(&new_page_id)
+        .bind(now_millis())
         .bind(&id)
         .execute(&self.pool)
         .await?
@@ -284,12 +361,17 @@ impl Database {
 
     // use when a page was deleted
     pub async fn delete_bloc_by_page_id(&self, page_id: String) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "delete page blocs").await?;
+
         let rows_affected = sqlx::query("DELETE FROM blocs WHERE page_id = ?")
             .bind(page_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?
             .rows_affected();
 
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
         Ok(rows_affected > 0)
     }
 
@@ -325,6 +407,7 @@ impl Database {
     }
 
     // get all blocs in a specific page
+    // les sources des références viennent avec get_page_blocs
     pub async fn get_blocs_by_page_id(&self, page_id: String) -> Result<Vec<BlocJson>> {
         let blocs = sqlx::query_as::<_, BlocJson>(
             "SELECT id, position, content, checksum, page_id, bloc_type, created_at, updated_at 
@@ -349,8 +432,8 @@ impl Database {
         .bind(&page.path)
         .bind(&page.title)
         .bind(&page.cache)
-        .bind(&page.created_at)
-        .bind(&page.updated_at)
+        .bind(page.created_at)
+        .bind(page.updated_at)
         .fetch_one(&self.pool)
         .await?
         .get(0);
@@ -366,7 +449,7 @@ impl Database {
         .bind(&page.path)
         .bind(&page.title)
         .bind(&page.cache)
-        .bind(&page.updated_at)
+        .bind(page.updated_at)
         .bind(&page.id)
         .execute(&self.pool)
         .await?
@@ -435,7 +518,7 @@ impl Database {
             "UPDATE pages SET updated_at = ? 
             WHERE id = ?",
         )
-        .bind(&updated_at)
+        .bind(updated_at)
         .bind(&id)
         .execute(&self.pool)
         .await?
@@ -444,16 +527,43 @@ impl Database {
         Ok(rows_affected > 0)
     }
 
+    // supprime la page avec ses blocs et leurs props : une seule étape à annuler
     pub async fn delete_page(&self, id: String) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "delete page").await?;
+
+        sqlx::query("DELETE FROM props WHERE bloc_id IN (SELECT id FROM blocs WHERE page_id = ?)")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM blocs WHERE page_id = ?")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
         let rows_affected = sqlx::query("DELETE FROM pages WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
+            .bind(&id)
+            .execute(&mut *tx)
             .await?
             .rows_affected();
 
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
         Ok(rows_affected > 0)
     }
 
+    pub async fn get_page_by_id(&self, id: String) -> Result<PageJson> {
+        let page = sqlx::query_as::<_, PageJson>(
+            "SELECT id, path, title, COALESCE(cache, '') as cache, created_at, updated_at 
+            FROM pages 
+            WHERE id = ?",
+        )
+        .bind(&id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(page)
+    }
+
     // use to get all pages in a specific path
     pub async fn get_pages_by_path(&self, path: String) -> Result<Vec<PageJson>> {
         let pages = sqlx::query_as::<_, PageJson>(
@@ -469,6 +579,7 @@ impl Database {
     }
 
     pub async fn new_prop(&self, prop: &PropsJson) -> Result<String> {
+        let mut tx = self.pool.begin().await?;
         let id = sqlx::query(
             "INSERT INTO props (id, key, value, bloc_id) 
             VALUES (?, ?, ?, ?) 
@@ -478,9 +589,11 @@ impl Database {
         .bind(&prop.key)
         .bind(&prop.value)
         .bind(&prop.bloc_id)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?
         .get(0);
+        touch_bloc(&mut tx, &prop.bloc_id).await?;
+        tx.commit().await?;
 
         Ok(id)
     }
@@ -491,44 +604,59 @@ impl Database {
         key: String,
         value: String,
     ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
         let rows_affected = sqlx::query(
             "UPDATE props SET value = ? 
             WHERE bloc_id = ? AND key = ?",
         )
         .bind(value)
-        .bind(bloc_id)
+        .bind(&bloc_id)
         .bind(key)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?
         .rows_affected();
+        if rows_affected > 0 {
+            touch_bloc(&mut tx, &bloc_id).await?;
+        }
+        tx.commit().await?;
 
         Ok(rows_affected > 0)
     }
 
     // one prop is idetifies by the bloc_id and the prop key
     pub async fn delete_prop(&self, bloc_id: String, key: String) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
         let rows_affected = sqlx::query(
             "
         DELETE FROM props 
         WHERE bloc_id = ? AND key = ?",
         )
-        .bind(bloc_id)
+        .bind(&bloc_id)
         .bind(key)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?
         .rows_affected();
+        if rows_affected > 0 {
+            touch_bloc(&mut tx, &bloc_id).await?;
+        }
+        tx.commit().await?;
 
         Ok(rows_affected > 0)
     }
 
     // use when a bloc was deleted
     pub async fn delete_prop_by_bloc_id(&self, bloc_id: String) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "delete bloc props").await?;
+
         let rows_affected = sqlx::query("DELETE FROM props WHERE bloc_id = ?")
             .bind(bloc_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?
             .rows_affected();
 
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
         Ok(rows_affected > 0)
     }
 
@@ -562,44 +690,21 @@ impl Database {
 
     // used to modify prop key name
     pub async fn change_prop_key_name(&self, key: String, new_key: String) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "rename prop key").await?;
+
         let rows_affected = sqlx::query(
             "UPDATE props SET key = ? 
             WHERE key = ?",
         )
         .bind(new_key)
         .bind(key)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?
         .rows_affected();
 
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
         Ok(rows_affected > 0)
     }
-
-    pub async fn query<T: serde::de::DeserializeOwned>(
-        &self,
-        collection: &str,
-        json_path: &str,
-        value: &str,
-    ) -> Result<Vec<T>> {
-        let query = format!(
-            "SELECT node_map FROM pages 
-             WHERE collection = ? AND json_extract(node_map, ?) = ?"
-        );
-
-        let rows = sqlx::query(&query)
-            .bind(collection)
-            .bind(json_path)
-            .bind(value)
-            .fetch_all(&self.pool)
-            .await?;
-
-        let mut results = Vec::new();
-        for row in rows {
-            let data: String = row.get("node_map");
-            let item: T = serde_json::from_str(&data)?;
-            results.push(item);
-        }
-
-        Ok(results)
-    }
 }
@@ -0,0 +1,243 @@
+// Copie de pages : blocs (nouveaux ids, mêmes positions), propriétés,
+// étiquettes et références aux pièces jointes (les fichiers ne sont pas
+// copiés, les blocs copiés pointent vers les mêmes). Les liens internes entre
+// pages copiées peuvent être réécrits pour viser les copies.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{Sqlite, Transaction};
+use std::collections::HashMap;
+
+use crate::database::{checksum, now_millis, BlocJson, Database, PageJson, PropsJson};
+use crate::lexical;
+use crate::previews::rebuild_preview_in;
+use crate::tags::TagEntity;
+use crate::undo::{begin_undo_group, end_undo_group};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    // id de la page d'origine -> id de la copie
+    pub pages: HashMap<String, String>,
+    pub blocs: usize,
+    pub props: usize,
+    pub taggings: usize,
+    pub links_rewritten: usize,
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+// remplace en une passe les ids entiers (pas "p1" dans "p10") : un id déjà
+// remplacé n'est pas relu
+fn replace_ids(url: &str, ids: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(url.len());
+    let mut rest = url;
+    while let Some(c) = rest.chars().next() {
+        if is_id_char(c) && !out.ends_with(is_id_char) {
+            let end = rest.find(|c: char| !is_id_char(c)).unwrap_or(rest.len());
+            let token = &rest[..end];
+            out.push_str(ids.get(token).map_or(token, String::as_str));
+            rest = &rest[end..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+// remplace dans l'url des liens les ids copiés par ceux des copies
+fn rewrite_links(node: &mut JsonValue, ids: &HashMap<String, String>) -> usize {
+    let mut count = 0;
+    if matches!(lexical::node_type(node), "link" | "autolink") {
+        if let Some(url) = node.get("url").and_then(JsonValue::as_str) {
+            let rewritten = replace_ids(url, ids);
+            if rewritten != url {
+                node["url"] = JsonValue::String(rewritten);
+                count += 1;
+            }
+        }
+    }
+    if let Some(children) = node.get_mut("children").and_then(JsonValue::as_array_mut) {
+        for child in children {
+            count += rewrite_links(child, ids);
+        }
+    }
+    count
+}
+
+async fn copy_taggings(tx: &mut Transaction<'_, Sqlite>, entity: TagEntity, from: &str, to: &str, now: i64) -> Result<usize> {
+    let copied = sqlx::query(
+        "INSERT OR IGNORE INTO taggings (tag_id, entity, entity_id, created_at)
+        SELECT tag_id, entity, ?3, ?4 FROM taggings WHERE entity = ?1 AND entity_id = ?2",
+    )
+    .bind(entity.as_str())
+    .bind(from)
+    .bind(to)
+    .bind(now)
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+    Ok(copied as usize)
+}
+
+// copie les pages `sources`, chacune vers le dossier associé
+async fn duplicate_in(
+    tx: &mut Transaction<'_, Sqlite>,
+    sources: &[(PageJson, String)],
+    rewrite: bool,
+) -> Result<DuplicateReport> {
+    let now = now_millis();
+    let mut report = DuplicateReport::default();
+
+    // ids des pages et des blocs copiés, attribués avant d'écrire les contenus
+    let mut ids: HashMap<String, String> = HashMap::new();
+    let mut page_blocs = Vec::with_capacity(sources.len());
+    for (page, _) in sources {
+        let page_id = page.id.clone().unwrap_or_default();
+        let blocs = sqlx::query_as::<_, BlocJson>(
+            "SELECT id, position, content, page_id, bloc_type, created_at, updated_at
+            FROM blocs WHERE page_id = ? ORDER BY position, id",
+        )
+        .bind(&page_id)
+        .fetch_all(&mut **tx)
+        .await?;
+        for bloc in &blocs {
+            ids.insert(bloc.id.clone().unwrap_or_default(), uuid::Uuid::new_v4().to_string());
+        }
+        let copy_id = uuid::Uuid::new_v4().to_string();
+        ids.insert(page_id.clone(), copy_id.clone());
+        report.pages.insert(page_id, copy_id);
+        page_blocs.push(blocs);
+    }
+
+    for ((page, path), blocs) in sources.iter().zip(page_blocs) {
+        let page_id = page.id.clone().unwrap_or_default();
+        let copy_id = &ids[&page_id];
+        sqlx::query(
+            "INSERT INTO pages (id, path, title, cache, created_at, updated_at)
+            VALUES (?, ?, ?, '', ?, ?)",
+        )
+        .bind(copy_id)
+        .bind(path)
+        .bind(&page.title)
+        .bind(now)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+        report.taggings += copy_taggings(tx, TagEntity::Page, &page_id, copy_id, now).await?;
+
+        for bloc in blocs {
+            let bloc_id = bloc.id.clone().unwrap_or_default();
+            let copy_bloc_id = &ids[&bloc_id];
+            let content = match serde_json::from_str::<JsonValue>(&bloc.content) {
+                Ok(mut node) => {
+                    if rewrite {
+                        report.links_rewritten += rewrite_links(&mut node, &ids);
+                    }
+                    if node.get("$").is_some() {
+                        lexical::set_bloc_state(&mut node, copy_bloc_id, &bloc.position);
+                    }
+                    serde_json::to_string(&node)?
+                }
+                // contenu illisible : recopié tel quel
+                Err(_) => bloc.content.clone(),
+            };
+
+            sqlx::query(
+                "INSERT INTO blocs (id, position, content, checksum, page_id, bloc_type, created_at, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(copy_bloc_id)
+            .bind(&bloc.position)
+            .bind(&content)
+            .bind(checksum(&content))
+            .bind(copy_id)
+            .bind(&bloc.bloc_type)
+            .bind(now)
+            .bind(now)
+            .execute(&mut **tx)
+            .await?;
+            report.blocs += 1;
+
+            let props = sqlx::query_as::<_, PropsJson>("SELECT * FROM props WHERE bloc_id = ? ORDER BY id")
+                .bind(&bloc_id)
+                .fetch_all(&mut **tx)
+                .await?;
+            for prop in props {
+                sqlx::query("INSERT INTO props (id, key, value, bloc_id) VALUES (?, ?, ?, ?)")
+                    .bind(uuid::Uuid::new_v4().to_string())
+                    .bind(&prop.key)
+                    .bind(&prop.value)
+                    .bind(copy_bloc_id)
+                    .execute(&mut **tx)
+                    .await?;
+                report.props += 1;
+            }
+            report.taggings += copy_taggings(tx, TagEntity::Bloc, &bloc_id, copy_bloc_id, now).await?;
+        }
+        // aperçu et titres de la copie, comme à l'enregistrement d'une page
+        rebuild_preview_in(tx, copy_id).await?;
+    }
+    Ok(report)
+}
+
+impl Database {
+    // copie la page dans le dossier `path` ; renvoie l'id de la copie
+    pub async fn duplicate_page(&self, page_id: String, path: String, rewrite_links: bool) -> Result<String> {
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "duplicate page").await?;
+
+        let Some(page) = sqlx::query_as::<_, PageJson>(
+            "SELECT id, path, title, '' AS cache, created_at, updated_at FROM pages WHERE id = ?",
+        )
+        .bind(&page_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            bail!("page {} not found", page_id);
+        };
+        let report = duplicate_in(&mut tx, &[(page, path)], rewrite_links).await?;
+
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
+        Ok(report.pages[&page_id].clone())
+    }
+
+    // copie les pages du dossier `path` et de ses sous-dossiers sous `target_path` :
+    // "/a/b" copié sous "/c" donne "/c/b"
+    pub async fn duplicate_subtree(&self, path: String, target_path: String, rewrite_links: bool) -> Result<DuplicateReport> {
+        let root = path.trim_end_matches('/');
+        let target = target_path.trim_end_matches('/');
+
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "duplicate subtree").await?;
+
+        let pages = sqlx::query_as::<_, PageJson>(
+            "SELECT id, path, title, '' AS cache, created_at, updated_at FROM pages
+            WHERE (?1 <> '' AND path = ?1) OR substr(path, 1, length(?1) + 1) = ?1 || '/'
+            ORDER BY path, id",
+        )
+        .bind(root)
+        .fetch_all(&mut *tx)
+        .await?;
+        if pages.is_empty() {
+            bail!("no page under {}", path);
+        }
+
+        let sources: Vec<(PageJson, String)> = pages
+            .into_iter()
+            .map(|page| {
+                let parent = root.rsplit_once('/').map_or("", |(parent, _)| parent);
+                let relocated = format!("{}/{}", target, page.path[parent.len()..].trim_start_matches('/'));
+                (page, relocated)
+            })
+            .collect();
+        let report = duplicate_in(&mut tx, &sources, rewrite_links).await?;
+
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
+        Ok(report)
+    }
+}
@@ -0,0 +1,253 @@
+// Cartes de révision tirées des blocs. La question est le texte du bloc et la
+// réponse la prop "answer" ; sans elle, le texte est coupé au marqueur "::"
+// ("Capitale de l'Italie :: Rome"). Les révisions suivent SM-2 : une note de
+// 0 à 5, un rappel (>= 3) allonge l'intervalle selon la facilité de la carte,
+// un oubli le ramène à un jour. L'intervalle est plafonné à cent ans.
+use anyhow::{bail, Result};
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::Row;
+
+use crate::database::{now_millis, Database};
+use crate::lexical;
+
+pub const ANSWER_MARKER: &str = "::";
+pub const ANSWER_PROP: &str = "answer";
+pub const MIN_EASE: f64 = 1.3;
+pub const DEFAULT_EASE: f64 = 2.5;
+pub const MAX_GRADE: u8 = 5;
+// sans plafond, une suite de bonnes notes déborde des dates représentables
+pub const MAX_INTERVAL_DAYS: i64 = 36_500;
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CardSchedule {
+    pub ease: f64,
+    pub interval_days: i64,
+    pub repetitions: i64,
+    pub lapses: i64,
+}
+
+impl Default for CardSchedule {
+    fn default() -> Self {
+        CardSchedule { ease: DEFAULT_EASE, interval_days: 0, repetitions: 0, lapses: 0 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Flashcard {
+    pub bloc_id: String,
+    pub page_id: String,
+    pub page_title: String,
+    pub question: String,
+    pub answer: String,
+    // AAAA-MM-JJ
+    pub due: String,
+    pub schedule: CardSchedule,
+}
+
+#[derive(Clone, Debug, PartialEq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct CardReview {
+    pub id: i64,
+    pub bloc_id: String,
+    pub grade: i64,
+    pub reviewed_on: String,
+    pub ease: f64,
+    pub interval_days: i64,
+    pub due: String,
+    pub created_at: i64,
+}
+
+// état de la carte après une révision notée `grade`
+pub fn sm2(schedule: CardSchedule, grade: u8) -> CardSchedule {
+    let q = grade.min(MAX_GRADE) as f64;
+    let ease = (schedule.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(MIN_EASE);
+    if grade >= 3 {
+        let interval_days = match schedule.repetitions {
+            0 => 1,
+            1 => 6,
+            _ => ((schedule.interval_days as f64 * schedule.ease).round() as i64).min(MAX_INTERVAL_DAYS),
+        };
+        CardSchedule { ease, interval_days, repetitions: schedule.repetitions + 1, lapses: schedule.lapses }
+    } else {
+        CardSchedule { ease, interval_days: 1, repetitions: 0, lapses: schedule.lapses + 1 }
+    }
+}
+
+// (question, réponse) d'un bloc
+pub fn split_card(text: &str, answer_prop: Option<&str>) -> (String, String) {
+    if let Some(answer) = answer_prop {
+        return (text.trim().to_string(), answer.trim().to_string());
+    }
+    match text.split_once(ANSWER_MARKER) {
+        Some((question, answer)) => (question.trim().to_string(), answer.trim().to_string()),
+        None => (text.trim().to_string(), String::new()),
+    }
+}
+
+fn day_key(date: NaiveDate) -> String {
+    date.format(DAY_FORMAT).to_string()
+}
+
+impl Database {
+    // fait du bloc une carte, à réviser dès `today` ; sans effet si c'en est déjà une
+    pub async fn mark_flashcard(&self, bloc_id: String, today: NaiveDate) -> Result<bool> {
+        let exists = sqlx::query("SELECT 1 FROM blocs WHERE id = ?")
+            .bind(&bloc_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .is_some();
+        if !exists {
+            bail!("bloc {} not found", bloc_id);
+        }
+
+        let now = now_millis();
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO cards (bloc_id, ease, due, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&bloc_id)
+        .bind(DEFAULT_EASE)
+        .bind(day_key(today))
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(inserted > 0)
+    }
+
+    // retire la carte et son historique
+    pub async fn unmark_flashcard(&self, bloc_id: String) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM card_reviews WHERE bloc_id = ?")
+            .bind(&bloc_id)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM cards WHERE bloc_id = ?")
+            .bind(&bloc_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    // cartes à réviser au plus tard `today`, les plus en retard d'abord
+    pub async fn get_due_cards(&self, today: NaiveDate, limit: i64) -> Result<Vec<Flashcard>> {
+        let rows = sqlx::query(
+            "SELECT c.bloc_id, c.ease, c.interval_days, c.repetitions, c.lapses, c.due,
+                b.content, p.id AS page_id, p.title,
+                (SELECT value FROM props WHERE bloc_id = c.bloc_id AND key = ?1 LIMIT 1) AS answer
+            FROM cards c
+            JOIN blocs b ON b.id = c.bloc_id
+            JOIN pages p ON p.id = b.page_id
+            WHERE c.due <= ?2
+            ORDER BY c.due, c.created_at, c.bloc_id
+            LIMIT ?3",
+        )
+        .bind(ANSWER_PROP)
+        .bind(day_key(today))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut cards = Vec::with_capacity(rows.len());
+        for row in rows {
+            let content: String = row.get("content");
+            let text = serde_json::from_str::<JsonValue>(&content)
+                .map(|node| lexical::plain_text(&node))
+                .unwrap_or_default();
+            let answer: Option<String> = row.get("answer");
+            let (question, answer) = split_card(&text, answer.as_deref());
+            cards.push(Flashcard {
+                bloc_id: row.get("bloc_id"),
+                page_id: row.get("page_id"),
+                page_title: row.get("title"),
+                question,
+                answer,
+                due: row.get("due"),
+                schedule: CardSchedule {
+                    ease: row.get("ease"),
+                    interval_days: row.get("interval_days"),
+                    repetitions: row.get("repetitions"),
+                    lapses: row.get("lapses"),
+                },
+            });
+        }
+        Ok(cards)
+    }
+
+    // note une révision faite `today` et planifie la suivante
+    pub async fn record_review(&self, bloc_id: String, grade: u8, today: NaiveDate) -> Result<CardReview> {
+        if grade > MAX_GRADE {
+            bail!("grade must be between 0 and {}", MAX_GRADE);
+        }
+        let mut tx = self.pool.begin().await?;
+        let Some(row) = sqlx::query("SELECT ease, interval_days, repetitions, lapses FROM cards WHERE bloc_id = ?")
+            .bind(&bloc_id)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            bail!("bloc {} is not a flashcard", bloc_id);
+        };
+        let schedule = sm2(
+            CardSchedule {
+                ease: row.get("ease"),
+                interval_days: row.get("interval_days"),
+                repetitions: row.get("repetitions"),
+                lapses: row.get("lapses"),
+            },
+            grade,
+        );
+        let Some(due) = u64::try_from(schedule.interval_days)
+            .ok()
+            .and_then(|days| today.checked_add_days(Days::new(days)))
+        else {
+            bail!("next review of {} is out of range", bloc_id);
+        };
+        let due = day_key(due);
+        let now = now_millis();
+
+        sqlx::query(
+            "UPDATE cards SET ease = ?, interval_days = ?, repetitions = ?, lapses = ?, due = ?, updated_at = ?
+            WHERE bloc_id = ?",
+        )
+        .bind(schedule.ease)
+        .bind(schedule.interval_days)
+        .bind(schedule.repetitions)
+        .bind(schedule.lapses)
+        .bind(&due)
+        .bind(now)
+        .bind(&bloc_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let review = sqlx::query_as::<_, CardReview>(
+            "INSERT INTO card_reviews (bloc_id, grade, reviewed_on, ease, interval_days, due, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING *",
+        )
+        .bind(&bloc_id)
+        .bind(grade as i64)
+        .bind(day_key(today))
+        .bind(schedule.ease)
+        .bind(schedule.interval_days)
+        .bind(&due)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(review)
+    }
+
+    // révisions de la carte, de la plus ancienne à la plus récente
+    pub async fn get_card_reviews(&self, bloc_id: String) -> Result<Vec<CardReview>> {
+        let reviews = sqlx::query_as::<_, CardReview>("SELECT * FROM card_reviews WHERE bloc_id = ? ORDER BY id")
+            .bind(bloc_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(reviews)
+    }
+}
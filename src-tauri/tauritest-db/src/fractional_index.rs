@@ -0,0 +1,349 @@
+// Portage de src/texteditor/algorithm/fractional_indexing.ts et
+// jittered_fractional_indexing.ts : clés de position base 62 des blocs.
+// Basé sur https://observablehq.com/@dgreensp/implementing-fractional-indexing
+use anyhow::{anyhow, bail, Result};
+
+pub const BASE_62_DIGITS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+const DEFAULT_JITTER_BITS: u32 = 30;
+
+fn digit_index(c: u8) -> Result<usize> {
+    BASE_62_DIGITS
+        .bytes()
+        .position(|d| d == c)
+        .ok_or_else(|| anyhow!("invalid digit: {}", c as char))
+}
+
+fn digit(i: usize) -> char {
+    BASE_62_DIGITS.as_bytes()[i] as char
+}
+
+// `a` peut être vide, `b` est None ou non vide ; a < b si b est présent.
+// Pas de zéro final.
+fn midpoint(a: &str, b: Option<&str>) -> Result<String> {
+    let zero = digit(0);
+    if let Some(b) = b {
+        if a >= b {
+            bail!("{} >= {}", a, b);
+        }
+    }
+    if a.ends_with(zero) || b.is_some_and(|b| b.ends_with(zero)) {
+        bail!("trailing zero");
+    }
+
+    if let Some(b) = b {
+        // retire le plus long préfixe commun, `a` étant complété par des zéros
+        let a_bytes = a.as_bytes();
+        let b_bytes = b.as_bytes();
+        let mut n = 0;
+        while n < b_bytes.len() && a_bytes.get(n).copied().unwrap_or(zero as u8) == b_bytes[n] {
+            n += 1;
+        }
+        if n > 0 {
+            let rest_a = if n < a.len() { &a[n..] } else { "" };
+            return Ok(format!("{}{}", &b[..n], midpoint(rest_a, Some(&b[n..]))?));
+        }
+    }
+
+    // les premiers chiffres (ou l'absence de chiffre) diffèrent
+    let digit_a = match a.as_bytes().first() {
+        Some(c) => digit_index(*c)?,
+        None => 0,
+    };
+    let digit_b = match b {
+        Some(b) => digit_index(b.as_bytes()[0])?,
+        None => BASE_62_DIGITS.len(),
+    };
+
+    if digit_b - digit_a > 1 {
+        // Math.round(0.5 * (a + b)) côté JS
+        let mid_digit = (digit_a + digit_b).div_ceil(2);
+        Ok(digit(mid_digit).to_string())
+    } else if let Some(b) = b.filter(|b| b.len() > 1) {
+        // premiers chiffres consécutifs
+        Ok(b[..1].to_string())
+    } else {
+        let rest_a = if a.is_empty() { "" } else { &a[1..] };
+        Ok(format!("{}{}", digit(digit_a), midpoint(rest_a, None)?))
+    }
+}
+
+fn integer_length(head: u8) -> Result<usize> {
+    match head {
+        b'a'..=b'z' => Ok((head - b'a') as usize + 2),
+        b'A'..=b'Z' => Ok((b'Z' - head) as usize + 2),
+        _ => bail!("invalid order key head: {}", head as char),
+    }
+}
+
+fn validate_integer(int: &str) -> Result<()> {
+    let head = *int
+        .as_bytes()
+        .first()
+        .ok_or_else(|| anyhow!("invalid integer part of order key: {}", int))?;
+    if int.len() != integer_length(head)? {
+        bail!("invalid integer part of order key: {}", int);
+    }
+    Ok(())
+}
+
+fn integer_part(key: &str) -> Result<&str> {
+    let head = *key
+        .as_bytes()
+        .first()
+        .ok_or_else(|| anyhow!("invalid order key: {}", key))?;
+    let length = integer_length(head)?;
+    if length > key.len() {
+        bail!("invalid order key: {}", key);
+    }
+    Ok(&key[..length])
+}
+
+fn smallest_integer() -> String {
+    format!("A{}", digit(0).to_string().repeat(26))
+}
+
+// vérifie qu'une clé a la forme produite par generate_key_between
+pub fn validate_order_key(key: &str) -> Result<()> {
+    if !key.is_ascii() {
+        bail!("invalid order key: {}", key);
+    }
+    if key == smallest_integer() {
+        bail!("invalid order key: {}", key);
+    }
+    let int = integer_part(key)?;
+    for c in key[1..].bytes() {
+        digit_index(c)?;
+    }
+    if key[int.len()..].ends_with(digit(0)) {
+        bail!("invalid order key: {}", key);
+    }
+    Ok(())
+}
+
+// None quand il n'existe pas d'entier plus grand
+fn increment_integer(x: &str) -> Result<Option<String>> {
+    validate_integer(x)?;
+    let head = x.as_bytes()[0];
+    let mut digs: Vec<char> = x[1..].chars().collect();
+
+    let mut carry = true;
+    for slot in digs.iter_mut().rev() {
+        let d = digit_index(*slot as u8)? + 1;
+        if d == BASE_62_DIGITS.len() {
+            *slot = digit(0);
+        } else {
+            *slot = digit(d);
+            carry = false;
+            break;
+        }
+    }
+
+    if !carry {
+        return Ok(Some(format!("{}{}", head as char, digs.iter().collect::<String>())));
+    }
+    match head {
+        b'Z' => Ok(Some(format!("a{}", digit(0)))),
+        b'z' => Ok(None),
+        _ => {
+            let h = head + 1;
+            if h > b'a' {
+                digs.push(digit(0));
+            } else {
+                digs.pop();
+            }
+            Ok(Some(format!("{}{}", h as char, digs.iter().collect::<String>())))
+        }
+    }
+}
+
+// None quand il n'existe pas d'entier plus petit
+fn decrement_integer(x: &str) -> Result<Option<String>> {
+    validate_integer(x)?;
+    let head = x.as_bytes()[0];
+    let last = digit(BASE_62_DIGITS.len() - 1);
+    let mut digs: Vec<char> = x[1..].chars().collect();
+
+    let mut borrow = true;
+    for slot in digs.iter_mut().rev() {
+        let d = digit_index(*slot as u8)?;
+        if d == 0 {
+            *slot = last;
+        } else {
+            *slot = digit(d - 1);
+            borrow = false;
+            break;
+        }
+    }
+
+    if !borrow {
+        return Ok(Some(format!("{}{}", head as char, digs.iter().collect::<String>())));
+    }
+    match head {
+        b'a' => Ok(Some(format!("Z{}", last))),
+        b'A' => Ok(None),
+        _ => {
+            let h = head - 1;
+            if h < b'Z' {
+                digs.push(last);
+            } else {
+                digs.pop();
+            }
+            Ok(Some(format!("{}{}", h as char, digs.iter().collect::<String>())))
+        }
+    }
+}
+
+// clé strictement entre `a` et `b` (None = début / fin de la page)
+pub fn generate_key_between(a: Option<&str>, b: Option<&str>) -> Result<String> {
+    if let Some(a) = a {
+        validate_order_key(a)?;
+    }
+    if let Some(b) = b {
+        validate_order_key(b)?;
+    }
+
+    match (a, b) {
+        (Some(a), Some(b)) if a >= b => bail!("{} >= {}", a, b),
+        (None, None) => Ok(format!("a{}", digit(0))),
+        (None, Some(b)) => {
+            let ib = integer_part(b)?;
+            let fb = &b[ib.len()..];
+            if ib == smallest_integer() {
+                return Ok(format!("{}{}", ib, midpoint("", Some(fb))?));
+            }
+            if ib < b {
+                return Ok(ib.to_string());
+            }
+            decrement_integer(ib)?.ok_or_else(|| anyhow!("cannot decrement any more"))
+        }
+        (Some(a), None) => {
+            let ia = integer_part(a)?;
+            let fa = &a[ia.len()..];
+            match increment_integer(ia)? {
+                Some(i) => Ok(i),
+                None => Ok(format!("{}{}", ia, midpoint(fa, None)?)),
+            }
+        }
+        (Some(a), Some(b)) => {
+            let ia = integer_part(a)?;
+            let fa = &a[ia.len()..];
+            let ib = integer_part(b)?;
+            let fb = &b[ib.len()..];
+            if ia == ib {
+                return Ok(format!("{}{}", ia, midpoint(fa, Some(fb))?));
+            }
+            let i = increment_integer(ia)?.ok_or_else(|| anyhow!("cannot increment any more"))?;
+            if i.as_str() < b {
+                return Ok(i);
+            }
+            Ok(format!("{}{}", ia, midpoint(fa, None)?))
+        }
+    }
+}
+
+// `n` clés distinctes et triées entre `a` et `b` ; sans bornes : a0, a1, a2...
+pub fn generate_n_keys_between(a: Option<&str>, b: Option<&str>, n: usize) -> Result<Vec<String>> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    if n == 1 {
+        return Ok(vec![generate_key_between(a, b)?]);
+    }
+    if b.is_none() {
+        let mut c = generate_key_between(a, b)?;
+        let mut result = vec![c.clone()];
+        for _ in 0..n - 1 {
+            c = generate_key_between(Some(&c), b)?;
+            result.push(c.clone());
+        }
+        return Ok(result);
+    }
+    if a.is_none() {
+        let mut c = generate_key_between(a, b)?;
+        let mut result = vec![c.clone()];
+        for _ in 0..n - 1 {
+            c = generate_key_between(a, Some(&c))?;
+            result.push(c.clone());
+        }
+        result.reverse();
+        return Ok(result);
+    }
+
+    let mid = n / 2;
+    let c = generate_key_between(a, b)?;
+    let mut result = generate_n_keys_between(a, Some(&c), mid)?;
+    result.push(c.clone());
+    result.extend(generate_n_keys_between(Some(&c), b, n - mid - 1)?);
+    Ok(result)
+}
+
+// bits aléatoires tirés d'un uuid v4, comme Math.random() < 0.5 côté éditeur
+fn random_bit_source() -> impl FnMut() -> bool {
+    let mut bits = 0u128;
+    let mut remaining = 0;
+    move || {
+        if remaining == 0 {
+            // les 62 bits de poids faible d'un uuid v4 sont aléatoires
+            // (les bits de variante et de version sont au-dessus)
+            bits = uuid::Uuid::new_v4().as_u128();
+            remaining = 62;
+        }
+        remaining -= 1;
+        let bit = bits & 1 == 1;
+        bits >>= 1;
+        bit
+    }
+}
+
+// version avec gigue : découpe l'intervalle `jitter_bits` fois au hasard pour
+// éviter les collisions entre deux appareils qui insèrent au même endroit
+pub fn generate_jittered_key_between_with(
+    a: Option<&str>,
+    b: Option<&str>,
+    jitter_bits: u32,
+    random_bit: &mut dyn FnMut() -> bool,
+) -> Result<String> {
+    let mut low = a.map(str::to_string);
+    let mut high = b.map(str::to_string);
+    let mut midpoint = generate_key_between(a, b)?;
+
+    for _ in 0..jitter_bits {
+        if random_bit() {
+            low = Some(midpoint);
+        } else {
+            high = Some(midpoint);
+        }
+        midpoint = generate_key_between(low.as_deref(), high.as_deref())?;
+    }
+
+    Ok(midpoint)
+}
+
+pub fn generate_jittered_key_between(a: Option<&str>, b: Option<&str>) -> Result<String> {
+    generate_jittered_key_between_with(a, b, DEFAULT_JITTER_BITS, &mut random_bit_source())
+}
+
+pub fn generate_jittered_n_keys_between(
+    a: Option<&str>,
+    b: Option<&str>,
+    n: usize,
+) -> Result<Vec<String>> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    // n + 1 clés entre a et b donnent n intervalles, une clé avec gigue dans chacun
+    let keys = generate_n_keys_between(a, b, n + 1)?;
+    let mut random_bit = random_bit_source();
+    keys.windows(2)
+        .map(|pair| {
+            generate_jittered_key_between_with(
+                Some(&pair[0]),
+                Some(&pair[1]),
+                DEFAULT_JITTER_BITS,
+                &mut random_bit,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::Row;
+
+use crate::database::{checksum, Database};
+
+#[derive(Debug, Default, Serialize)]
+pub struct IntegrityReport {
+    // sortie de PRAGMA integrity_check, ["ok"] si le fichier est sain
+    pub sqlite: Vec<String>,
+    pub duplicate_page_ids: Vec<String>,
+    pub duplicate_bloc_ids: Vec<String>,
+    // blocs dont la page n'existe plus
+    pub orphan_blocs: Vec<String>,
+    // props dont le bloc n'existe plus
+    pub orphan_props: Vec<String>,
+    // blocs dont la colonne checksum ne correspond plus au contenu
+    pub checksum_mismatches: Vec<String>,
+    // blocs dont le contenu n'est pas du JSON valide
+    pub invalid_contents: Vec<String>,
+    // "page_id position" partagés par plusieurs blocs
+    pub duplicate_positions: Vec<String>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.sqlite == ["ok"]
+            && self.duplicate_page_ids.is_empty()
+            && self.duplicate_bloc_ids.is_empty()
+            && self.orphan_blocs.is_empty()
+            && self.orphan_props.is_empty()
+            && self.checksum_mismatches.is_empty()
+            && self.invalid_contents.is_empty()
+            && self.duplicate_positions.is_empty()
+    }
+}
+
+impl Database {
+    async fn strings(&self, sql: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query(sql).fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    // lecture seule : signale les problèmes sans rien corriger
+    pub async fn check_integrity(&self) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport {
+            sqlite: self.strings("PRAGMA integrity_check").await?,
+            duplicate_page_ids: self
+                .strings("SELECT id FROM pages GROUP BY id HAVING COUNT(*) > 1")
+                .await?,
+            duplicate_bloc_ids: self
+                .strings("SELECT id FROM blocs GROUP BY id HAVING COUNT(*) > 1")
+                .await?,
+            orphan_blocs: self
+                .strings(
+                    "SELECT b.id FROM blocs b
+                    WHERE NOT EXISTS (SELECT 1 FROM pages p WHERE p.id = b.page_id)",
+                )
+                .await?,
+            orphan_props: self
+                .strings(
+                    "SELECT r.id FROM props r
+                    WHERE NOT EXISTS (SELECT 1 FROM blocs b WHERE b.id = r.bloc_id)",
+                )
+                .await?,
+            duplicate_positions: self
+                .strings(
+                    "SELECT page_id || ' ' || position FROM blocs
+                    GROUP BY page_id, position HAVING COUNT(*) > 1",
+                )
+                .await?,
+            ..Default::default()
+        };
+
+        let blocs = sqlx::query("SELECT id, content, checksum FROM blocs")
+            .fetch_all(&self.pool)
+            .await?;
+        for row in blocs {
+            let id: String = row.get("id");
+            let content: String = row.get("content");
+            let stored: String = row.get("checksum");
+
+            if stored != checksum(&content) {
+                report.checksum_mismatches.push(id.clone());
+            }
+            if serde_json::from_str::<serde_json::Value>(&content).is_err() {
+                report.invalid_contents.push(id);
+            }
+        }
+
+        Ok(report)
+    }
+}
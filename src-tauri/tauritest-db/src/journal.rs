@@ -0,0 +1,237 @@
+// Journal : une page par jour, retrouvée par sa date dans la table `journal`.
+// Les pages restent des pages ordinaires (chemin, titre, blocs) ; une entrée
+// dont la page a été supprimée est ignorée puis recréée à la demande.
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{Row, Sqlite, Transaction};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::database::{checksum, now_millis, Database, PageJson};
+use crate::fractional_index;
+use crate::lexical;
+use crate::tags::index_hashtags;
+use crate::templates::instantiate_in;
+use crate::undo::{begin_undo_group, end_undo_group};
+
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalOptions {
+    // dossier des pages du journal
+    pub path: String,
+    // format chrono du titre, ex. "%A %e %B %Y"
+    pub title_format: String,
+    // modèle appliqué aux nouvelles pages
+    pub template_id: Option<String>,
+    // recopie les cases non cochées de la page précédente dans la nouvelle
+    pub rollover: bool,
+}
+
+impl Default for JournalOptions {
+    fn default() -> Self {
+        Self {
+            path: "/journal".to_string(),
+            title_format: DAY_FORMAT.to_string(),
+            template_id: None,
+            rollover: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct JournalDay {
+    // AAAA-MM-JJ
+    pub day: String,
+    pub page_id: String,
+    pub bloc_count: i64,
+}
+
+fn day_key(date: NaiveDate) -> String {
+    date.format(DAY_FORMAT).to_string()
+}
+
+async fn page_for_day(tx: &mut Transaction<'_, Sqlite>, day: &str) -> Result<Option<String>> {
+    let page_id = sqlx::query(
+        "SELECT j.page_id FROM journal j JOIN pages p ON p.id = j.page_id WHERE j.day = ?",
+    )
+    .bind(day)
+    .fetch_optional(&mut **tx)
+    .await?
+    .map(|row| row.get(0));
+    Ok(page_id)
+}
+
+// ajoute en fin de `page_id` les cases non cochées de la page du jour précédent
+async fn rollover_unchecked(tx: &mut Transaction<'_, Sqlite>, day: &str, page_id: &str) -> Result<usize> {
+    let previous: Option<String> = sqlx::query(
+        "SELECT j.page_id FROM journal j JOIN pages p ON p.id = j.page_id
+        WHERE j.day < ? ORDER BY j.day DESC LIMIT 1",
+    )
+    .bind(day)
+    .fetch_optional(&mut **tx)
+    .await?
+    .map(|row| row.get(0));
+    let Some(previous) = previous else {
+        return Ok(0);
+    };
+
+    let contents = sqlx::query("SELECT content FROM blocs WHERE page_id = ? ORDER BY position, created_at, id")
+        .bind(&previous)
+        .fetch_all(&mut **tx)
+        .await?;
+    let lists: Vec<JsonValue> = contents
+        .iter()
+        .filter_map(|row| serde_json::from_str::<JsonValue>(row.get("content")).ok())
+        .filter_map(|node| lexical::unchecked_items(&node))
+        .collect();
+    if lists.is_empty() {
+        return Ok(0);
+    }
+
+    let last: Option<String> = sqlx::query("SELECT MAX(position) FROM blocs WHERE page_id = ?")
+        .bind(page_id)
+        .fetch_one(&mut **tx)
+        .await?
+        .get(0);
+    let positions = fractional_index::generate_n_keys_between(last.as_deref(), None, lists.len())?;
+
+    let count = lists.len();
+    let now = now_millis();
+    for (mut list, position) in lists.into_iter().zip(positions) {
+        let bloc_id = uuid::Uuid::new_v4().to_string();
+        lexical::set_bloc_state(&mut list, &bloc_id, &position);
+        let content = serde_json::to_string(&list)?;
+        sqlx::query(
+            "INSERT INTO blocs (id, position, content, checksum, page_id, bloc_type, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, 'list', ?, ?)",
+        )
+        .bind(&bloc_id)
+        .bind(&position)
+        .bind(&content)
+        .bind(checksum(&content))
+        .bind(page_id)
+        .bind(now)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+        index_hashtags(tx, &bloc_id, None, &content).await?;
+    }
+
+    Ok(count)
+}
+
+impl Database {
+    // page du jour `date`, créée (avec modèle et report des cases) si besoin
+    pub async fn get_or_create_daily_page(&self, date: NaiveDate, options: &JournalOptions) -> Result<PageJson> {
+        let day = day_key(date);
+        // verrou d'écriture dès la lecture : deux appels simultanés (tâche de
+        // fond et clic) ne créent pas chacun leur page
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+
+        let page_id = match page_for_day(&mut tx, &day).await? {
+            Some(page_id) => page_id,
+            None => {
+                begin_undo_group(&mut tx, "daily page").await?;
+
+                // un format invalide ferait paniquer to_string()
+                let mut title = String::new();
+                write!(title, "{}", date.format(&options.title_format))
+                    .map_err(|_| anyhow!("invalid journal title format: {}", options.title_format))?;
+                let page_id = match &options.template_id {
+                    Some(template_id) => {
+                        instantiate_in(&mut tx, template_id, &options.path, &title, &HashMap::new(), date).await?
+                    }
+                    None => {
+                        let page_id = uuid::Uuid::new_v4().to_string();
+                        let now = now_millis();
+                        sqlx::query(
+                            "INSERT INTO pages (id, path, title, cache, created_at, updated_at)
+                            VALUES (?, ?, ?, '', ?, ?)",
+                        )
+                        .bind(&page_id)
+                        .bind(&options.path)
+                        .bind(&title)
+                        .bind(now)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                        page_id
+                    }
+                };
+                if options.rollover {
+                    rollover_unchecked(&mut tx, &day, &page_id).await?;
+                }
+
+                sqlx::query("INSERT OR REPLACE INTO journal (day, page_id) VALUES (?, ?)")
+                    .bind(&day)
+                    .bind(&page_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                end_undo_group(&mut tx).await?;
+                page_id
+            }
+        };
+
+        let page = sqlx::query_as::<_, PageJson>("SELECT * FROM pages WHERE id = ?")
+            .bind(&page_id)
+            .fetch_one(&mut *tx)
+            .await
+            .with_context(|| format!("journal page {} not found", page_id))?;
+        tx.commit().await?;
+        Ok(page)
+    }
+
+    // jour du journal d'une page, None si ce n'est pas une page du journal
+    pub async fn get_daily_page_date(&self, page_id: String) -> Result<Option<NaiveDate>> {
+        let day: Option<String> = sqlx::query("SELECT day FROM journal WHERE page_id = ?")
+            .bind(page_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get(0));
+        Ok(day.and_then(|day| NaiveDate::parse_from_str(&day, DAY_FORMAT).ok()))
+    }
+
+    // entrée existante la plus proche avant `date`
+    pub async fn get_previous_daily_page(&self, date: NaiveDate) -> Result<Option<JournalDay>> {
+        self.adjacent_daily_page(date, false).await
+    }
+
+    // entrée existante la plus proche après `date`
+    pub async fn get_next_daily_page(&self, date: NaiveDate) -> Result<Option<JournalDay>> {
+        self.adjacent_daily_page(date, true).await
+    }
+
+    async fn adjacent_daily_page(&self, date: NaiveDate, forward: bool) -> Result<Option<JournalDay>> {
+        let day = sqlx::query_as::<_, JournalDay>(if forward {
+            "SELECT j.day, j.page_id, (SELECT COUNT(*) FROM blocs b WHERE b.page_id = j.page_id) AS bloc_count
+            FROM journal j JOIN pages p ON p.id = j.page_id
+            WHERE j.day > ? ORDER BY j.day LIMIT 1"
+        } else {
+            "SELECT j.day, j.page_id, (SELECT COUNT(*) FROM blocs b WHERE b.page_id = j.page_id) AS bloc_count
+            FROM journal j JOIN pages p ON p.id = j.page_id
+            WHERE j.day < ? ORDER BY j.day DESC LIMIT 1"
+        })
+        .bind(day_key(date))
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(day)
+    }
+
+    // jours ayant une page entre `from` et `to` inclus, pour le calendrier
+    pub async fn get_journal_calendar(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<JournalDay>> {
+        let days = sqlx::query_as::<_, JournalDay>(
+            "SELECT j.day, j.page_id, (SELECT COUNT(*) FROM blocs b WHERE b.page_id = j.page_id) AS bloc_count
+            FROM journal j JOIN pages p ON p.id = j.page_id
+            WHERE j.day BETWEEN ? AND ? ORDER BY j.day",
+        )
+        .bind(day_key(from))
+        .bind(day_key(to))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(days)
+    }
+}
@@ -0,0 +1,429 @@
+// Lecture du contenu des blocs : chaque bloc stocke en JSON un noeud racine
+// Lexical (exportJSON) avec son état "$" { id, position }.
+use serde_json::{json, Value as JsonValue};
+
+// bits de TextNode.format côté Lexical
+pub const FORMAT_BOLD: i64 = 1;
+pub const FORMAT_ITALIC: i64 = 1 << 1;
+pub const FORMAT_STRIKETHROUGH: i64 = 1 << 2;
+pub const FORMAT_CODE: i64 = 1 << 4;
+
+pub fn node_type(node: &JsonValue) -> &str {
+    node.get("type").and_then(JsonValue::as_str).unwrap_or("")
+}
+
+pub fn children(node: &JsonValue) -> &[JsonValue] {
+    node.get("children")
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or(&[])
+}
+
+// parcours en profondeur, le noeud lui-même compris
+pub fn walk<'a>(node: &'a JsonValue, visit: &mut dyn FnMut(&'a JsonValue)) {
+    visit(node);
+    for child in children(node) {
+        walk(child, visit);
+    }
+}
+
+// niveau d'un titre ("h2" -> 2), None si le noeud n'est pas un titre
+pub fn heading_level(node: &JsonValue) -> Option<usize> {
+    if node_type(node) != "heading" {
+        return None;
+    }
+    node.get("tag")
+        .and_then(JsonValue::as_str)
+        .and_then(|tag| tag.strip_prefix('h'))
+        .and_then(|level| level.parse().ok())
+}
+
+// texte brut d'un noeud, les blocs enfants séparés par un retour à la ligne
+pub fn plain_text(node: &JsonValue) -> String {
+    match node_type(node) {
+        "linebreak" => "\n".to_string(),
+        "tab" => "\t".to_string(),
+        "image" => node
+            .get("altText")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("")
+            .to_string(),
+        "equation" => node
+            .get("equation")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("")
+            .to_string(),
+        _ => {
+            if let Some(text) = node.get("text").and_then(JsonValue::as_str) {
+                return text.to_string();
+            }
+            let separator = if is_container(node) { "\n" } else { "" };
+            children(node)
+                .iter()
+                .map(plain_text)
+                .collect::<Vec<_>>()
+                .join(separator)
+        }
+    }
+}
+
+// noeuds dont les enfants sont des blocs et non du texte en ligne
+fn is_container(node: &JsonValue) -> bool {
+    matches!(
+        node_type(node),
+        "root" | "list" | "table" | "tablerow" | "layout-container" | "layout-item" | "collapsible-container"
+    )
+}
+
+fn inline_markdown(nodes: &[JsonValue]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node_type(node) {
+            "text" | "code-highlight" | "hashtag" | "keyword" | "autocomplete" | "mention" => {
+                let text = node.get("text").and_then(JsonValue::as_str).unwrap_or("");
+                let format = node.get("format").and_then(JsonValue::as_i64).unwrap_or(0);
+                out.push_str(&format_text(text, format));
+            }
+            "linebreak" => out.push('\n'),
+            "tab" => out.push('\t'),
+            "link" | "autolink" => {
+                let url = node.get("url").and_then(JsonValue::as_str).unwrap_or("");
+                out.push_str(&format!("[{}]({})", inline_markdown(children(node)), url));
+            }
+            "image" => {
+                let src = node.get("src").and_then(JsonValue::as_str).unwrap_or("");
+                let alt = node.get("altText").and_then(JsonValue::as_str).unwrap_or("");
+                out.push_str(&format!("![{}]({})", alt, src));
+            }
+            "equation" => {
+                let equation = node.get("equation").and_then(JsonValue::as_str).unwrap_or("");
+                out.push_str(&format!("${}$", equation));
+            }
+            _ => out.push_str(&inline_markdown(children(node))),
+        }
+    }
+    out
+}
+
+fn format_text(text: &str, format: i64) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    if format & FORMAT_CODE != 0 {
+        return format!("`{}`", text);
+    }
+    let mut out = text.to_string();
+    if format & FORMAT_STRIKETHROUGH != 0 {
+        out = format!("~~{}~~", out);
+    }
+    if format & FORMAT_ITALIC != 0 {
+        out = format!("*{}*", out);
+    }
+    if format & FORMAT_BOLD != 0 {
+        out = format!("**{}**", out);
+    }
+    out
+}
+
+fn list_markdown(list: &JsonValue, depth: usize, out: &mut Vec<String>) {
+    let list_type = list.get("listType").and_then(JsonValue::as_str).unwrap_or("bullet");
+    let start = list.get("start").and_then(JsonValue::as_i64).unwrap_or(1);
+    let indent = "  ".repeat(depth);
+
+    let mut number = start;
+    for item in children(list) {
+        // un listitem qui ne contient qu'une liste porte une sous-liste
+        let nested: Vec<&JsonValue> = children(item)
+            .iter()
+            .filter(|child| node_type(child) == "list")
+            .collect();
+        if !nested.is_empty() && nested.len() == children(item).len() {
+            for sub in nested {
+                list_markdown(sub, depth + 1, out);
+            }
+            continue;
+        }
+
+        let marker = match list_type {
+            "number" => format!("{}.", number),
+            "check" => {
+                let checked = item.get("checked").and_then(JsonValue::as_bool).unwrap_or(false);
+                if checked { "- [x]".to_string() } else { "- [ ]".to_string() }
+            }
+            _ => "-".to_string(),
+        };
+        number += 1;
+
+        let inline: Vec<JsonValue> = children(item)
+            .iter()
+            .filter(|child| node_type(child) != "list")
+            .cloned()
+            .collect();
+        out.push(format!("{}{} {}", indent, marker, inline_markdown(&inline)));
+
+        for sub in children(item).iter().filter(|child| node_type(child) == "list") {
+            list_markdown(sub, depth + 1, out);
+        }
+    }
+}
+
+fn table_markdown(table: &JsonValue) -> String {
+    let rows: Vec<Vec<String>> = children(table)
+        .iter()
+        .map(|row| {
+            children(row)
+                .iter()
+                .map(|cell| plain_text(cell).replace('\n', " ").replace('|', "\\|"))
+                .collect()
+        })
+        .collect();
+
+    let mut lines = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        lines.push(format!("| {} |", row.join(" | ")));
+        if i == 0 {
+            lines.push(format!("|{}", " --- |".repeat(row.len())));
+        }
+    }
+    lines.join("\n")
+}
+
+// rend un noeud de premier niveau (le contenu d'un bloc) en Markdown
+pub fn to_markdown(node: &JsonValue) -> String {
+    match node_type(node) {
+        "heading" => {
+            let level = heading_level(node).unwrap_or(1);
+            format!("{} {}", "#".repeat(level), inline_markdown(children(node)))
+        }
+        "quote" => inline_markdown(children(node))
+            .lines()
+            .map(|line| format!("> {}", line))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "code" => {
+            let language = node.get("language").and_then(JsonValue::as_str).unwrap_or("");
+            format!("```{}\n{}\n```", language, inline_markdown(children(node)))
+        }
+        "list" => {
+            let mut lines = Vec::new();
+            list_markdown(node, 0, &mut lines);
+            lines.join("\n")
+        }
+        "table" => table_markdown(node),
+        "horizontalrule" | "page-break" => "---".to_string(),
+        "image" | "equation" => inline_markdown(std::slice::from_ref(node)),
+        _ if is_container(node) => children(node)
+            .iter()
+            .map(to_markdown)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => inline_markdown(children(node)),
+    }
+}
+
+fn text_node(text: &str) -> JsonValue {
+    json!({
+        "detail": 0,
+        "format": 0,
+        "mode": "normal",
+        "style": "",
+        "text": text,
+        "type": "text",
+        "version": 1
+    })
+}
+
+// texte avec retours à la ligne -> noeuds text / linebreak
+fn inline_nodes(text: &str) -> Vec<JsonValue> {
+    let mut nodes = Vec::new();
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            nodes.push(json!({ "type": "linebreak", "version": 1 }));
+        }
+        if !line.is_empty() {
+            nodes.push(text_node(line));
+        }
+    }
+    nodes
+}
+
+fn element(node_type: &str, children: Vec<JsonValue>) -> JsonValue {
+    json!({
+        "children": children,
+        "direction": null,
+        "format": "",
+        "indent": 0,
+        "type": node_type,
+        "version": 1
+    })
+}
+
+pub fn paragraph(text: &str) -> JsonValue {
+    let mut node = element("paragraph", inline_nodes(text));
+    node["textFormat"] = json!(0);
+    node["textStyle"] = json!("");
+    node
+}
+
+fn list_item(text: &str, value: usize, checked: Option<bool>) -> JsonValue {
+    let mut item = element("listitem", inline_nodes(text));
+    item["value"] = json!(value);
+    if let Some(checked) = checked {
+        item["checked"] = json!(checked);
+    }
+    item
+}
+
+fn list_node(list_type: &str, items: Vec<JsonValue>) -> JsonValue {
+    let tag = if list_type == "number" { "ol" } else { "ul" };
+    let mut list = element("list", items);
+    list["listType"] = json!(list_type);
+    list["start"] = json!(1);
+    list["tag"] = json!(tag);
+    list
+}
+
+fn list_entry(line: &str) -> Option<(&'static str, Option<bool>, &str)> {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix("- [ ] ") {
+        return Some(("check", Some(false), rest));
+    }
+    if let Some(rest) = line.strip_prefix("- [x] ").or_else(|| line.strip_prefix("- [X] ")) {
+        return Some(("check", Some(true), rest));
+    }
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some(("bullet", None, rest));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        if let Some(rest) = line[digits..].strip_prefix(". ") {
+            return Some(("number", None, rest));
+        }
+    }
+    None
+}
+
+// Markdown simple -> noeuds Lexical de premier niveau (un par bloc).
+// Couvre titres, listes, cases à cocher, citations, blocs de code et paragraphes.
+pub fn from_markdown(markdown: &str) -> Vec<JsonValue> {
+    let mut nodes = Vec::new();
+    let mut lines = markdown.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(language) = line.strip_prefix("```") {
+            let mut code = Vec::new();
+            for inner in lines.by_ref() {
+                if inner.starts_with("```") {
+                    break;
+                }
+                code.push(inner);
+            }
+            let mut node = element("code", inline_nodes(&code.join("\n")));
+            node["language"] = json!(language.trim());
+            nodes.push(node);
+            continue;
+        }
+
+        let hashes = line.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+            let mut node = element("heading", inline_nodes(line[hashes..].trim()));
+            node["tag"] = json!(format!("h{}", hashes));
+            nodes.push(node);
+            continue;
+        }
+
+        if line == "---" {
+            nodes.push(json!({ "type": "horizontalrule", "version": 1 }));
+            continue;
+        }
+
+        if let Some((list_type, checked, text)) = list_entry(line) {
+            let mut items = vec![list_item(text, 1, checked)];
+            while let Some(next) = lines.peek() {
+                match list_entry(next) {
+                    Some((next_type, next_checked, next_text)) if next_type == list_type => {
+                        items.push(list_item(next_text, items.len() + 1, next_checked));
+                        lines.next();
+                    }
+                    _ => break,
+                }
+            }
+            nodes.push(list_node(list_type, items));
+            continue;
+        }
+
+        if line.starts_with('>') {
+            let mut quoted = vec![line.trim_start_matches('>').trim_start()];
+            while let Some(next) = lines.peek() {
+                if !next.starts_with('>') {
+                    break;
+                }
+                quoted.push(next.trim_start_matches('>').trim_start());
+                lines.next();
+            }
+            nodes.push(element("quote", inline_nodes(&quoted.join("\n"))));
+            continue;
+        }
+
+        // paragraphe : lignes consécutives jusqu'à une ligne vide ou un autre bloc
+        let mut paragraph_lines = vec![line];
+        while let Some(next) = lines.peek() {
+            if next.trim().is_empty()
+                || next.starts_with('#')
+                || next.starts_with('>')
+                || next.starts_with("```")
+                || list_entry(next).is_some()
+            {
+                break;
+            }
+            paragraph_lines.push(next);
+            lines.next();
+        }
+        nodes.push(paragraph(&paragraph_lines.join("\n")));
+    }
+
+    nodes
+}
+
+// écrit l'état "$" { id, position } attendu par l'éditeur
+pub fn set_bloc_state(node: &mut JsonValue, id: &str, position: &str) {
+    node["$"] = json!({ "id": id, "position": position });
+}
+
+// copie d'une liste de cases à cocher réduite aux cases non cochées (sous-listes
+// comprises) ; None si le noeud n'en contient aucune
+pub fn unchecked_items(list: &JsonValue) -> Option<JsonValue> {
+    if node_type(list) != "list" || list.get("listType").and_then(JsonValue::as_str) != Some("check") {
+        return None;
+    }
+
+    let mut items = Vec::new();
+    for item in children(list) {
+        let mut item = item.clone();
+        let is_wrapper = item.get("checked").is_none()
+            && !children(&item).is_empty()
+            && children(&item).iter().all(|child| node_type(child) == "list");
+        if is_wrapper {
+            let nested: Vec<JsonValue> = children(&item).iter().filter_map(unchecked_items).collect();
+            if nested.is_empty() {
+                continue;
+            }
+            item["children"] = json!(nested);
+        } else if item.get("checked").and_then(JsonValue::as_bool) == Some(true) {
+            continue;
+        }
+        item["value"] = json!(items.len() + 1);
+        items.push(item);
+    }
+
+    if items.is_empty() {
+        return None;
+    }
+    let mut list = list.clone();
+    list["children"] = json!(items);
+    Some(list)
+}
@@ -0,0 +1,56 @@
+// Couche de stockage SQLite (pages, blocs, props), utilisable sans Tauri
+pub mod comments;
+pub mod database;
+pub mod duplicate;
+pub mod flashcards;
+pub mod fractional_index;
+pub mod integrity;
+pub mod journal;
+pub mod lexical;
+pub mod migrations;
+pub mod navigation;
+pub mod oplog;
+pub mod outline;
+pub mod page_updates;
+pub mod pagination;
+pub mod palette;
+pub mod positions;
+pub mod previews;
+pub mod props_query;
+pub mod query;
+pub mod reminders;
+pub mod search;
+pub mod settings;
+pub mod sync;
+pub mod tags;
+pub mod templates;
+pub mod transclusion;
+pub mod undo;
+pub mod workspace;
+
+pub use comments::{CommentJson, PageCommentCount, ThreadJson};
+pub use database::{BlocJson, Database, JsonDocument, PageJson, PropsJson, SearchResult};
+pub use duplicate::DuplicateReport;
+pub use flashcards::{CardReview, CardSchedule, Flashcard};
+pub use integrity::IntegrityReport;
+pub use journal::{JournalDay, JournalOptions};
+pub use migrations::MigrationReport;
+pub use navigation::{PageVisits, TabJson, TabSession, VisitJson};
+pub use oplog::{ChangeFeed, Operation};
+pub use outline::{HeadingHit, OutlineNode};
+pub use page_updates::{PageMerge, PageUpdates};
+pub use palette::{PaletteCommand, PaletteEntry, PaletteHit, PaletteIndex, PaletteKind};
+pub use pagination::{BlocChunk, BlocCursor, PageChunk, PageCursor};
+pub use positions::{BlocPosition, MoveAnchor};
+pub use previews::{OutlineEntry, PagePreview, PageWithPreview};
+pub use props_query::{PropsMatch, PropsQuery};
+pub use query::{ContentMatch, ContentQuery, FilterOp, NodeFilter};
+pub use reminders::{Clock, FiredReminder, ManualClock, ReminderJson, ReminderScheduler, SystemClock};
+pub use search::SearchHit;
+pub use settings::{SettingScope, SettingSource, SettingValue, SettingsExport, SettingsImport};
+pub use sync::{SyncOptions, SyncReport};
+pub use tags::{TagEntity, TagJson, TaggedItem};
+pub use templates::{Recurrence, TemplateJson};
+pub use transclusion::{BlocReference, PageBlocs, Resolution};
+pub use undo::{UndoStatus, UndoStep};
+pub use workspace::{ImportReport, WorkspaceExport};
@@ -0,0 +1,687 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::Row;
+
+use crate::database::Database;
+
+// Une étape du schéma. La version appliquée est gardée dans PRAGMA user_version,
+// chaque migration tourne dans sa propre transaction.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+// Ne jamais modifier une migration déjà publiée : en ajouter une nouvelle à la fin
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "tables pages, blocs et props",
+        // IF NOT EXISTS : les bases créées avant le versionnement passent sans erreur
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS pages (
+                id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                title TEXT NOT NULL,
+                cache TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS blocs (
+                id TEXT NOT NULL,
+                position TEXT NOT NULL,
+                content TEXT NOT NULL,
+                checksum TEXT NOT NULL,
+                page_id TEXT NOT NULL,
+                bloc_type TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS props (
+                id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                bloc_id TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS bloc (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_pages_title ON pages(title);
+        "#,
+    },
+    Migration {
+        version: 2,
+        description: "index sur les colonnes de recherche",
+        sql: r#"
+            CREATE INDEX IF NOT EXISTS idx_pages_path ON pages(path);
+            CREATE INDEX IF NOT EXISTS idx_blocs_page_id ON blocs(page_id, position);
+            CREATE INDEX IF NOT EXISTS idx_props_bloc_id ON props(bloc_id);
+            CREATE INDEX IF NOT EXISTS idx_props_key ON props(key);
+        "#,
+    },
+    Migration {
+        version: 3,
+        description: "fils de commentaires ancrés aux blocs",
+        sql: r#"
+            CREATE TABLE comment_threads (
+                id TEXT PRIMARY KEY,
+                bloc_id TEXT NOT NULL,
+                quote TEXT NOT NULL,
+                resolved INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE comments (
+                id TEXT PRIMARY KEY,
+                thread_id TEXT NOT NULL,
+                author TEXT NOT NULL,
+                content TEXT NOT NULL,
+                deleted INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX idx_comment_threads_bloc_id ON comment_threads(bloc_id);
+            CREATE INDEX idx_comments_thread_id ON comments(thread_id, created_at);
+        "#,
+    },
+    Migration {
+        version: 4,
+        description: "journal des mises à jour Yjs par page",
+        sql: r#"
+            CREATE TABLE page_updates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                page_id TEXT NOT NULL,
+                update_data BLOB NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX idx_page_updates_page_id ON page_updates(page_id, id);
+        "#,
+    },
+    Migration {
+        version: 5,
+        description: "identité de l'instance et points de synchronisation",
+        sql: r#"
+            CREATE TABLE sync_state (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            CREATE TABLE sync_peers (
+                peer_id TEXT PRIMARY KEY,
+                last_sync_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_blocs_updated_at ON blocs(updated_at);
+            CREATE INDEX IF NOT EXISTS idx_pages_updated_at ON pages(updated_at);
+        "#,
+    },
+    Migration {
+        version: 6,
+        description: "journal des opérations, alimenté par triggers",
+        // les triggers s'exécutent dans la transaction de l'écriture elle-même ;
+        // checksum avant / après : blocs seulement, NULL pour les autres entités
+        sql: r#"
+            CREATE TABLE operations (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                op TEXT NOT NULL,
+                entity TEXT NOT NULL,
+                entity_id TEXT,
+                page_id TEXT,
+                before_checksum TEXT,
+                after_checksum TEXT,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX idx_operations_entity ON operations(entity, entity_id);
+
+            CREATE TRIGGER log_pages_insert AFTER INSERT ON pages
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('insert', 'page', NEW.id, NEW.id, NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_pages_update AFTER UPDATE ON pages
+            WHEN OLD.id IS NOT NEW.id OR OLD.path IS NOT NEW.path OR OLD.title IS NOT NEW.title OR OLD.cache IS NOT NEW.cache OR OLD.created_at IS NOT NEW.created_at OR OLD.updated_at IS NOT NEW.updated_at
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('update', 'page', NEW.id, NEW.id, NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_pages_delete AFTER DELETE ON pages
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('delete', 'page', OLD.id, OLD.id, NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_blocs_insert AFTER INSERT ON blocs
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('insert', 'bloc', NEW.id, NEW.page_id, NULL, NEW.checksum, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_blocs_update AFTER UPDATE ON blocs
+            WHEN OLD.id IS NOT NEW.id OR OLD.position IS NOT NEW.position OR OLD.checksum IS NOT NEW.checksum OR OLD.page_id IS NOT NEW.page_id OR OLD.bloc_type IS NOT NEW.bloc_type OR OLD.created_at IS NOT NEW.created_at OR OLD.updated_at IS NOT NEW.updated_at
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('update', 'bloc', NEW.id, NEW.page_id, OLD.checksum, NEW.checksum, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_blocs_delete AFTER DELETE ON blocs
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('delete', 'bloc', OLD.id, OLD.page_id, OLD.checksum, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_props_insert AFTER INSERT ON props
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('insert', 'prop', NEW.id, (SELECT page_id FROM blocs WHERE blocs.id = NEW.bloc_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_props_update AFTER UPDATE ON props
+            WHEN OLD.id IS NOT NEW.id OR OLD.key IS NOT NEW.key OR OLD.value IS NOT NEW.value OR OLD.bloc_id IS NOT NEW.bloc_id
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('update', 'prop', NEW.id, (SELECT page_id FROM blocs WHERE blocs.id = NEW.bloc_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_props_delete AFTER DELETE ON props
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('delete', 'prop', OLD.id, (SELECT page_id FROM blocs WHERE blocs.id = OLD.bloc_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_comment_threads_insert AFTER INSERT ON comment_threads
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('insert', 'thread', NEW.id, (SELECT page_id FROM blocs WHERE blocs.id = NEW.bloc_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_comment_threads_update AFTER UPDATE ON comment_threads
+            WHEN OLD.id IS NOT NEW.id OR OLD.bloc_id IS NOT NEW.bloc_id OR OLD.quote IS NOT NEW.quote OR OLD.resolved IS NOT NEW.resolved OR OLD.updated_at IS NOT NEW.updated_at
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('update', 'thread', NEW.id, (SELECT page_id FROM blocs WHERE blocs.id = NEW.bloc_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_comment_threads_delete AFTER DELETE ON comment_threads
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('delete', 'thread', OLD.id, (SELECT page_id FROM blocs WHERE blocs.id = OLD.bloc_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_comments_insert AFTER INSERT ON comments
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('insert', 'comment', NEW.id, (SELECT b.page_id FROM comment_threads t JOIN blocs b ON b.id = t.bloc_id WHERE t.id = NEW.thread_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_comments_update AFTER UPDATE ON comments
+            WHEN OLD.id IS NOT NEW.id OR OLD.content IS NOT NEW.content OR OLD.deleted IS NOT NEW.deleted OR OLD.updated_at IS NOT NEW.updated_at
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('update', 'comment', NEW.id, (SELECT b.page_id FROM comment_threads t JOIN blocs b ON b.id = t.bloc_id WHERE t.id = NEW.thread_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+
+            CREATE TRIGGER log_comments_delete AFTER DELETE ON comments
+            BEGIN
+                INSERT INTO operations (op, entity, entity_id, page_id, before_checksum, after_checksum, created_at)
+                VALUES ('delete', 'comment', OLD.id, (SELECT b.page_id FROM comment_threads t JOIN blocs b ON b.id = t.bloc_id WHERE t.id = OLD.thread_id), NULL, NULL, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+            END;
+        "#,
+    },
+    Migration {
+        version: 7,
+        description: "journal d'annulation des pages, blocs et props",
+        // une écriture hors groupe forme une étape à elle seule ; les 1000
+        // dernières étapes sont gardées (UNDO_LIMIT)
+        sql: r#"
+            CREATE TABLE undo_steps (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                label TEXT NOT NULL,
+                undone INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE undo_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                step_id INTEGER NOT NULL,
+                entity TEXT NOT NULL,
+                entity_id TEXT,
+                before_row TEXT,
+                after_row TEXT
+            );
+
+            CREATE INDEX idx_undo_entries_step_id ON undo_entries(step_id);
+
+            -- group : étape ouverte par Rust pour une écriture sur plusieurs lignes
+            -- replaying : 1 pendant un undo / redo, qui ne doit pas s'enregistrer
+            CREATE TABLE undo_state (
+                key TEXT PRIMARY KEY,
+                value INTEGER
+            );
+            INSERT INTO undo_state (key, value) VALUES ('group', NULL), ('replaying', 0);
+
+            CREATE TRIGGER undo_pages_insert AFTER INSERT ON pages
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'insert page', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'page', NEW.id, NULL, json_object('id', NEW.id, 'path', NEW.path, 'title', NEW.title, 'cache', NEW.cache, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                );
+            END;
+
+            CREATE TRIGGER undo_pages_update AFTER UPDATE ON pages
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+                AND (OLD.id IS NOT NEW.id OR OLD.path IS NOT NEW.path OR OLD.title IS NOT NEW.title)
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'update page', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'page', NEW.id, json_object('id', OLD.id, 'path', OLD.path, 'title', OLD.title, 'cache', OLD.cache, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at), json_object('id', NEW.id, 'path', NEW.path, 'title', NEW.title, 'cache', NEW.cache, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                );
+            END;
+
+            CREATE TRIGGER undo_pages_delete AFTER DELETE ON pages
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'delete page', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'page', OLD.id, json_object('id', OLD.id, 'path', OLD.path, 'title', OLD.title, 'cache', OLD.cache, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at), NULL
+                );
+            END;
+
+            CREATE TRIGGER undo_blocs_insert AFTER INSERT ON blocs
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'insert bloc', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'bloc', NEW.id, NULL, json_object('id', NEW.id, 'position', NEW.position, 'content', NEW.content, 'checksum', NEW.checksum, 'page_id', NEW.page_id, 'bloc_type', NEW.bloc_type, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                );
+            END;
+
+            CREATE TRIGGER undo_blocs_update AFTER UPDATE ON blocs
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+                AND (OLD.id IS NOT NEW.id OR OLD.position IS NOT NEW.position OR OLD.checksum IS NOT NEW.checksum OR OLD.page_id IS NOT NEW.page_id OR OLD.bloc_type IS NOT NEW.bloc_type)
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'update bloc', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'bloc', NEW.id, json_object('id', OLD.id, 'position', OLD.position, 'content', OLD.content, 'checksum', OLD.checksum, 'page_id', OLD.page_id, 'bloc_type', OLD.bloc_type, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at), json_object('id', NEW.id, 'position', NEW.position, 'content', NEW.content, 'checksum', NEW.checksum, 'page_id', NEW.page_id, 'bloc_type', NEW.bloc_type, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at)
+                );
+            END;
+
+            CREATE TRIGGER undo_blocs_delete AFTER DELETE ON blocs
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'delete bloc', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'bloc', OLD.id, json_object('id', OLD.id, 'position', OLD.position, 'content', OLD.content, 'checksum', OLD.checksum, 'page_id', OLD.page_id, 'bloc_type', OLD.bloc_type, 'created_at', OLD.created_at, 'updated_at', OLD.updated_at), NULL
+                );
+            END;
+
+            CREATE TRIGGER undo_props_insert AFTER INSERT ON props
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'insert prop', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'prop', NEW.id, NULL, json_object('id', NEW.id, 'key', NEW.key, 'value', NEW.value, 'bloc_id', NEW.bloc_id)
+                );
+            END;
+
+            CREATE TRIGGER undo_props_update AFTER UPDATE ON props
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+                AND (OLD.id IS NOT NEW.id OR OLD.key IS NOT NEW.key OR OLD.value IS NOT NEW.value OR OLD.bloc_id IS NOT NEW.bloc_id)
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'update prop', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'prop', NEW.id, json_object('id', OLD.id, 'key', OLD.key, 'value', OLD.value, 'bloc_id', OLD.bloc_id), json_object('id', NEW.id, 'key', NEW.key, 'value', NEW.value, 'bloc_id', NEW.bloc_id)
+                );
+            END;
+
+            CREATE TRIGGER undo_props_delete AFTER DELETE ON props
+            WHEN (SELECT value FROM undo_state WHERE key = 'replaying') = 0
+            BEGIN
+                DELETE FROM undo_entries WHERE step_id IN (SELECT id FROM undo_steps WHERE undone = 1);
+                DELETE FROM undo_steps WHERE undone = 1;
+                DELETE FROM undo_entries WHERE step_id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                DELETE FROM undo_steps WHERE id <= (SELECT MAX(id) FROM undo_steps) - 1000;
+                INSERT INTO undo_steps (label, created_at)
+                SELECT 'delete prop', CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER)
+                WHERE (SELECT value FROM undo_state WHERE key = 'group') IS NULL;
+                INSERT INTO undo_entries (step_id, entity, entity_id, before_row, after_row)
+                VALUES (
+                    COALESCE((SELECT value FROM undo_state WHERE key = 'group'), last_insert_rowid()),
+                    'prop', OLD.id, json_object('id', OLD.id, 'key', OLD.key, 'value', OLD.value, 'bloc_id', OLD.bloc_id), NULL
+                );
+            END;
+        "#,
+    },
+    Migration {
+        version: 8,
+        description: "modèles de pages",
+        sql: r#"
+            CREATE TABLE templates (
+                page_id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                -- NULL ou 'daily'
+                recurrence TEXT,
+                target_path TEXT NOT NULL DEFAULT '/',
+                title_pattern TEXT NOT NULL DEFAULT '{{title}}',
+                -- date (AAAA-MM-JJ) de la dernière page créée par récurrence
+                last_run TEXT
+            );
+        "#,
+    },
+    Migration {
+        version: 9,
+        description: "pages du journal",
+        sql: r#"
+            CREATE TABLE journal (
+                -- AAAA-MM-JJ : l'ordre lexical est l'ordre des jours
+                day TEXT PRIMARY KEY,
+                page_id TEXT NOT NULL
+            );
+
+            CREATE INDEX idx_journal_page_id ON journal(page_id);
+        "#,
+    },
+    Migration {
+        version: 10,
+        description: "étiquettes partagées",
+        sql: r#"
+            CREATE TABLE tags (
+                id TEXT PRIMARY KEY,
+                -- chemin complet, les niveaux séparés par '/' : "project/alpha"
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                color TEXT,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE taggings (
+                tag_id TEXT NOT NULL,
+                -- 'page', 'bloc', 'event' ou 'card'
+                entity TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (tag_id, entity, entity_id)
+            );
+
+            CREATE INDEX idx_taggings_entity ON taggings(entity, entity_id);
+        "#,
+    },
+    Migration {
+        version: 11,
+        description: "historique de navigation et onglets ouverts",
+        sql: r#"
+            CREATE TABLE visits (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                page_id TEXT NOT NULL,
+                -- position de défilement / sélection, JSON opaque du front
+                anchor TEXT,
+                visited_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX idx_visits_page_id ON visits(page_id, visited_at);
+            CREATE INDEX idx_visits_visited_at ON visits(visited_at);
+
+            CREATE TABLE open_tabs (
+                -- libellé de la fenêtre Tauri
+                window TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                page_id TEXT NOT NULL,
+                anchor TEXT,
+                pinned INTEGER NOT NULL DEFAULT 0,
+                active INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (window, position)
+            );
+        "#,
+    },
+    Migration {
+        version: 12,
+        description: "réglages",
+        sql: r#"
+            CREATE TABLE settings (
+                key TEXT PRIMARY KEY,
+                -- valeur JSON, validée par settings::SETTINGS
+                value TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE settings_state (
+                key TEXT PRIMARY KEY,
+                value INTEGER
+            );
+            INSERT INTO settings_state (key, value) VALUES ('version', 1);
+        "#,
+    },
+    Migration {
+        version: 13,
+        description: "index des titres de toutes les pages",
+        sql: r#"
+            CREATE TABLE headings (
+                page_id TEXT NOT NULL,
+                -- rang du titre dans la page
+                ord INTEGER NOT NULL,
+                bloc_id TEXT NOT NULL,
+                level INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                anchor TEXT NOT NULL,
+                PRIMARY KEY (page_id, ord)
+            );
+
+            CREATE INDEX idx_headings_text ON headings(text COLLATE NOCASE);
+        "#,
+    },
+    Migration {
+        version: 14,
+        description: "index des références de blocs",
+        sql: r#"
+            CREATE INDEX idx_blocs_reference_source ON blocs(json_extract(content, '$.source'))
+            WHERE bloc_type = 'bloc-reference';
+        "#,
+    },
+    Migration {
+        version: 15,
+        description: "cartes de révision et historique des révisions",
+        sql: r#"
+            -- une carte par bloc ; état SM-2
+            CREATE TABLE cards (
+                bloc_id TEXT PRIMARY KEY,
+                ease REAL NOT NULL DEFAULT 2.5,
+                interval_days INTEGER NOT NULL DEFAULT 0,
+                repetitions INTEGER NOT NULL DEFAULT 0,
+                lapses INTEGER NOT NULL DEFAULT 0,
+                -- AAAA-MM-JJ
+                due TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX idx_cards_due ON cards(due);
+
+            CREATE TABLE card_reviews (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                bloc_id TEXT NOT NULL,
+                grade INTEGER NOT NULL,
+                reviewed_on TEXT NOT NULL,
+                -- état après la révision
+                ease REAL NOT NULL,
+                interval_days INTEGER NOT NULL,
+                due TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX idx_card_reviews_bloc_id ON card_reviews(bloc_id, id);
+        "#,
+    },
+    Migration {
+        version: 16,
+        description: "rappels",
+        sql: r#"
+            CREATE TABLE reminders (
+                id TEXT PRIMARY KEY,
+                bloc_id TEXT,
+                page_id TEXT,
+                title TEXT NOT NULL,
+                -- millisecondes depuis l'epoch
+                fire_at INTEGER NOT NULL,
+                -- 'pending', 'fired' ou 'dismissed'
+                status TEXT NOT NULL DEFAULT 'pending',
+                -- clé de la prop d'origine ; NULL pour un rappel créé à la main
+                prop_key TEXT,
+                -- valeur de la prop au moment du calcul de fire_at
+                prop_value TEXT,
+                snooze_count INTEGER NOT NULL DEFAULT 0,
+                fired_at INTEGER,
+                created_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX idx_reminders_pending ON reminders(status, fire_at);
+            CREATE UNIQUE INDEX idx_reminders_prop ON reminders(bloc_id, prop_key) WHERE prop_key IS NOT NULL;
+        "#,
+    },
+    Migration {
+        version: 17,
+        description: "titre par défaut des modèles récurrents",
+        // "{{title}}" donnait des notes titrées "{{title}}"
+        sql: r#"
+            UPDATE templates SET title_pattern = '{{date}}'
+            WHERE recurrence IS NOT NULL AND trim(title_pattern) IN ('', '{{title}}');
+        "#,
+    },
+    Migration {
+        version: 18,
+        description: "aperçus des pages hors de pages.cache",
+        // pages.cache reste à l'éditeur ; les aperçus qui l'avaient remplacé
+        // y sont déplacés
+        sql: r#"
+            CREATE TABLE page_previews (
+                page_id TEXT PRIMARY KEY,
+                preview TEXT NOT NULL
+            );
+
+            INSERT INTO page_previews (page_id, preview)
+            SELECT id, cache FROM pages
+            WHERE json_valid(cache) AND json_type(cache, '$.checksum') = 'text';
+
+            UPDATE pages SET cache = '' WHERE id IN (SELECT page_id FROM page_previews);
+        "#,
+    },
+];
+
+#[derive(Debug, Serialize)]
+pub struct MigrationReport {
+    pub from_version: i64,
+    pub to_version: i64,
+    pub applied: Vec<String>,
+}
+
+impl Database {
+    pub fn latest_schema_version() -> i64 {
+        MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+    }
+
+    pub async fn schema_version(&self) -> Result<i64> {
+        let version = sqlx::query("PRAGMA user_version")
+            .fetch_one(&self.pool)
+            .await?
+            .get(0);
+        Ok(version)
+    }
+
+    // applique dans l'ordre toutes les migrations plus récentes que la base
+    pub async fn migrate(&self) -> Result<MigrationReport> {
+        let from_version = self.schema_version().await?;
+        let mut applied = Vec::new();
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version > from_version) {
+            let mut tx = self.pool.begin().await?;
+
+            sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+            // PRAGMA n'accepte pas de paramètre lié
+            sqlx::query(&format!("PRAGMA user_version = {}", migration.version))
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            applied.push(format!("{}: {}", migration.version, migration.description));
+        }
+
+        Ok(MigrationReport {
+            from_version,
+            to_version: self.schema_version().await?,
+            applied,
+        })
+    }
+}
@@ -0,0 +1,217 @@
+// Historique de navigation et onglets ouverts, gardés dans la base plutôt que
+// dans le localStorage : partagés entre les fenêtres, conservés au redémarrage.
+// Les pages supprimées depuis sont ignorées à la lecture.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::database::{now_millis, Database};
+
+// nombre de visites gardées, les plus anciennes sont oubliées
+pub const HISTORY_LIMIT: i64 = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct VisitJson {
+    pub id: i64,
+    pub page_id: String,
+    pub path: String,
+    pub title: String,
+    pub anchor: Option<String>,
+    pub visited_at: i64,
+}
+
+// une page de l'historique, pour les listes "récentes" et "fréquentes"
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct PageVisits {
+    pub page_id: String,
+    pub path: String,
+    pub title: String,
+    pub visit_count: i64,
+    pub last_visited_at: i64,
+    // ancre de la dernière visite
+    pub anchor: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct TabJson {
+    pub page_id: String,
+    pub anchor: Option<String>,
+    pub pinned: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TabSession {
+    pub tabs: Vec<TabJson>,
+    // index dans `tabs` de l'onglet actif
+    pub active: Option<usize>,
+}
+
+const PAGE_VISITS: &str = "SELECT v.page_id, p.path, p.title,
+    COUNT(*) AS visit_count,
+    MAX(v.visited_at) AS last_visited_at,
+    (SELECT l.anchor FROM visits l WHERE l.page_id = v.page_id ORDER BY l.id DESC LIMIT 1) AS anchor
+    FROM visits v JOIN pages p ON p.id = v.page_id";
+
+impl Database {
+    // enregistre une visite ; revenir sur la page de la dernière visite ne fait
+    // que la mettre à jour. Renvoie l'id de la visite.
+    pub async fn record_visit(&self, page_id: String, anchor: Option<String>) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let now = now_millis();
+
+        let last = sqlx::query("SELECT id, page_id FROM visits ORDER BY id DESC LIMIT 1")
+            .fetch_optional(&mut *tx)
+            .await?;
+        let id = match last {
+            Some(row) if row.get::<String, _>("page_id") == page_id => {
+                let id: i64 = row.get("id");
+                sqlx::query("UPDATE visits SET anchor = COALESCE(?, anchor), visited_at = ? WHERE id = ?")
+                    .bind(anchor)
+                    .bind(now)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                id
+            }
+            _ => {
+                let id = sqlx::query(
+                    "INSERT INTO visits (page_id, anchor, visited_at) VALUES (?, ?, ?) RETURNING id",
+                )
+                .bind(&page_id)
+                .bind(anchor)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?
+                .get(0);
+                sqlx::query("DELETE FROM visits WHERE id <= ? - ?")
+                    .bind(id)
+                    .bind(HISTORY_LIMIT)
+                    .execute(&mut *tx)
+                    .await?;
+                id
+            }
+        };
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    // position de défilement / sélection à la sortie de la page
+    pub async fn update_visit_anchor(&self, id: i64, anchor: Option<String>) -> Result<bool> {
+        let rows_affected = sqlx::query("UPDATE visits SET anchor = ? WHERE id = ?")
+            .bind(anchor)
+            .bind(id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    // visites les plus récentes d'abord ; `before` (id) pour la page suivante
+    pub async fn get_visits(&self, limit: i64, before: Option<i64>) -> Result<Vec<VisitJson>> {
+        let visits = sqlx::query_as::<_, VisitJson>(
+            "SELECT v.id, v.page_id, p.path, p.title, v.anchor, v.visited_at
+            FROM visits v JOIN pages p ON p.id = v.page_id
+            WHERE v.id < COALESCE(?, 9223372036854775807)
+            ORDER BY v.id DESC LIMIT ?",
+        )
+        .bind(before)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(visits)
+    }
+
+    pub async fn get_recent_pages(&self, limit: i64) -> Result<Vec<PageVisits>> {
+        let pages = sqlx::query_as::<_, PageVisits>(&format!(
+            "{} GROUP BY v.page_id ORDER BY MAX(v.id) DESC LIMIT ?",
+            PAGE_VISITS
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(pages)
+    }
+
+    // pages les plus visitées depuis `since` (ms), toutes si None
+    pub async fn get_frequent_pages(&self, limit: i64, since: Option<i64>) -> Result<Vec<PageVisits>> {
+        let pages = sqlx::query_as::<_, PageVisits>(&format!(
+            "{} WHERE v.visited_at >= COALESCE(?, 0)
+            GROUP BY v.page_id ORDER BY visit_count DESC, MAX(v.id) DESC LIMIT ?",
+            PAGE_VISITS
+        ))
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(pages)
+    }
+
+    pub async fn delete_page_visits(&self, page_id: String) -> Result<u64> {
+        let rows_affected = sqlx::query("DELETE FROM visits WHERE page_id = ?")
+            .bind(page_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    pub async fn clear_visits(&self) -> Result<()> {
+        sqlx::query("DELETE FROM visits").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    // remplace les onglets de la fenêtre `window`
+    pub async fn save_tab_session(&self, window: String, session: &TabSession) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM open_tabs WHERE window = ?")
+            .bind(&window)
+            .execute(&mut *tx)
+            .await?;
+
+        for (position, tab) in session.tabs.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO open_tabs (window, position, page_id, anchor, pinned, active)
+                VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&window)
+            .bind(position as i64)
+            .bind(&tab.page_id)
+            .bind(&tab.anchor)
+            .bind(tab.pinned)
+            .bind(session.active == Some(position))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // onglets de la fenêtre, sans ceux dont la page a été supprimée
+    pub async fn get_tab_session(&self, window: String) -> Result<TabSession> {
+        let rows = sqlx::query(
+            "SELECT t.page_id, t.anchor, t.pinned, t.active FROM open_tabs t
+            JOIN pages p ON p.id = t.page_id
+            WHERE t.window = ? ORDER BY t.position",
+        )
+        .bind(window)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut session = TabSession::default();
+        for row in rows {
+            if row.get::<bool, _>("active") {
+                session.active = Some(session.tabs.len());
+            }
+            session.tabs.push(TabJson {
+                page_id: row.get("page_id"),
+                anchor: row.get("anchor"),
+                pinned: row.get("pinned"),
+            });
+        }
+        Ok(session)
+    }
+}
@@ -0,0 +1,65 @@
+// Journal des opérations (table operations, remplie par les triggers de la
+// migration 6). Le curseur est le numéro de séquence de la dernière opération lue.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::database::Database;
+
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Deserialize, Serialize)]
+pub struct Operation {
+    pub seq: i64,
+    // "insert", "update" ou "delete"
+    pub op: String,
+    // "page", "bloc", "prop", "thread" ou "comment"
+    pub entity: String,
+    pub entity_id: Option<String>,
+    pub page_id: Option<String>,
+    pub before_checksum: Option<String>,
+    pub after_checksum: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangeFeed {
+    pub operations: Vec<Operation>,
+    // à repasser au prochain appel ; inchangé s'il n'y a rien de nouveau
+    pub cursor: i64,
+    // vrai si `limit` a coupé la lecture
+    pub has_more: bool,
+}
+
+impl Database {
+    // opérations postérieures au curseur (0 pour tout relire), dans l'ordre
+    pub async fn changes_since(&self, cursor: i64, limit: i64) -> Result<ChangeFeed> {
+        // une limite nulle ou négative ne ferait jamais avancer le curseur
+        if limit < 1 {
+            bail!("limit must be at least 1");
+        }
+        let mut operations = sqlx::query_as::<_, Operation>(
+            "SELECT * FROM operations WHERE seq > ? ORDER BY seq LIMIT ?",
+        )
+        .bind(cursor)
+        .bind(limit + 1)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let has_more = operations.len() as i64 > limit;
+        operations.truncate(limit as usize);
+
+        Ok(ChangeFeed {
+            cursor: operations.last().map(|op| op.seq).unwrap_or(cursor),
+            operations,
+            has_more,
+        })
+    }
+
+    // curseur courant, pour ne suivre que les changements à venir
+    pub async fn latest_cursor(&self) -> Result<i64> {
+        let seq: Option<i64> = sqlx::query("SELECT MAX(seq) FROM operations")
+            .fetch_one(&self.pool)
+            .await?
+            .get(0);
+        Ok(seq.unwrap_or(0))
+    }
+}
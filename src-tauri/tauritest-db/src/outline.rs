@@ -0,0 +1,157 @@
+// Plan d'une page (titres imbriqués, avec leur bloc et une ancre stable) et
+// index des titres de tout l'espace, tenu à jour avec l'aperçu de la page
+// (previews.rs) pour la palette de commandes.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Sqlite, Transaction};
+use std::collections::{HashMap, HashSet};
+
+use crate::database::Database;
+use crate::previews::OutlineEntry;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineNode {
+    pub level: usize,
+    pub text: String,
+    pub bloc_id: String,
+    // unique dans la page : "introduction", "introduction-1"…
+    pub anchor: String,
+    pub children: Vec<OutlineNode>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct HeadingHit {
+    pub page_id: String,
+    pub page_title: String,
+    pub path: String,
+    pub bloc_id: String,
+    pub level: i64,
+    pub text: String,
+    pub anchor: String,
+}
+
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    if slug.is_empty() {
+        slug.push_str("heading");
+    }
+    slug
+}
+
+// ancres des titres, dans l'ordre de la page. Un doublon reçoit le premier
+// suffixe libre : "Notes", "Notes", "Notes 1" donnent notes, notes-1, notes-1-1.
+pub fn anchors(entries: &[OutlineEntry]) -> Vec<String> {
+    let mut emitted: HashSet<String> = HashSet::new();
+    let mut next_suffix: HashMap<String, usize> = HashMap::new();
+    entries
+        .iter()
+        .map(|entry| {
+            let slug = slugify(&entry.text);
+            let mut anchor = slug.clone();
+            let suffix = next_suffix.entry(slug.clone()).or_insert(1);
+            while emitted.contains(&anchor) {
+                anchor = format!("{}-{}", slug, suffix);
+                *suffix += 1;
+            }
+            emitted.insert(anchor.clone());
+            anchor
+        })
+        .collect()
+}
+
+// imbrique les titres : un titre contient les suivants de niveau supérieur
+pub fn build_outline(entries: &[OutlineEntry]) -> Vec<OutlineNode> {
+    fn nest(entries: &[(OutlineEntry, String)], next: &mut usize, parent_level: usize) -> Vec<OutlineNode> {
+        let mut nodes = Vec::new();
+        while let Some((entry, anchor)) = entries.get(*next) {
+            if entry.level <= parent_level {
+                break;
+            }
+            *next += 1;
+            let children = nest(entries, next, entry.level);
+            nodes.push(OutlineNode {
+                level: entry.level,
+                text: entry.text.clone(),
+                bloc_id: entry.bloc_id.clone(),
+                anchor: anchor.clone(),
+                children,
+            });
+        }
+        nodes
+    }
+
+    let entries: Vec<(OutlineEntry, String)> = entries.iter().cloned().zip(anchors(entries)).collect();
+    nest(&entries, &mut 0, 0)
+}
+
+// remplace les titres de la page dans l'index
+pub(crate) async fn index_headings(tx: &mut Transaction<'_, Sqlite>, page_id: &str, entries: &[OutlineEntry]) -> Result<()> {
+    sqlx::query("DELETE FROM headings WHERE page_id = ?")
+        .bind(page_id)
+        .execute(&mut **tx)
+        .await?;
+
+    for (ord, (entry, anchor)) in entries.iter().zip(anchors(entries)).enumerate() {
+        sqlx::query(
+            "INSERT INTO headings (page_id, ord, bloc_id, level, text, anchor) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(page_id)
+        .bind(ord as i64)
+        .bind(&entry.bloc_id)
+        .bind(entry.level as i64)
+        .bind(&entry.text)
+        .bind(anchor)
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+impl Database {
+    pub async fn get_page_outline(&self, page_id: String) -> Result<Vec<OutlineNode>> {
+        let preview = self.refresh_page_preview(page_id).await?;
+        Ok(build_outline(&preview.outline))
+    }
+
+    // recalcule aperçus et titres de toutes les pages ; renvoie le nombre de pages
+    pub async fn rebuild_heading_index(&self) -> Result<usize> {
+        sqlx::query("DELETE FROM headings WHERE page_id NOT IN (SELECT id FROM pages)")
+            .execute(&self.pool)
+            .await?;
+        let page_ids: Vec<String> = sqlx::query_scalar("SELECT id FROM pages ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        for page_id in &page_ids {
+            self.rebuild_page_preview(page_id.clone()).await?;
+        }
+        Ok(page_ids.len())
+    }
+
+    // titres contenant `query`, ceux qui commencent par `query` d'abord
+    pub async fn search_headings(&self, query: String, limit: i64) -> Result<Vec<HeadingHit>> {
+        let pattern = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let hits = sqlx::query_as::<_, HeadingHit>(
+            "SELECT h.page_id, p.title AS page_title, p.path, h.bloc_id, h.level, h.text, h.anchor
+            FROM headings h JOIN pages p ON p.id = h.page_id
+            WHERE h.text LIKE '%' || ?1 || '%' ESCAPE '\\'
+            ORDER BY h.text LIKE ?1 || '%' ESCAPE '\\' DESC, h.level, p.title, h.ord
+            LIMIT ?2",
+        )
+        .bind(pattern)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(hits)
+    }
+}
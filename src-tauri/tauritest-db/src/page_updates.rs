@@ -0,0 +1,264 @@
+// Journal des mises à jour CRDT (encodage Yjs v1) de chaque page. Cette couche
+// ne fait que stocker les octets : la fusion est faite par tauritest-crdt.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, Sqlite, Transaction};
+use std::collections::HashMap;
+
+use crate::database::{checksum, now_millis, BlocJson, Database};
+use crate::tags::index_hashtags;
+use crate::undo::{begin_untracked, end_untracked};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageUpdates {
+    pub page_id: String,
+    // id de la dernière mise à jour lue, à passer à compact_page_updates
+    pub last_id: i64,
+    pub updates: Vec<Vec<u8>>,
+}
+
+// ce que tauritest-crdt tire du journal et des blocs actuels d'une page
+#[derive(Debug, Clone, Default)]
+pub struct PageMerge {
+    // mises à jour à ajouter au journal, dans l'ordre
+    pub updates: Vec<Vec<u8>>,
+    // blocs à matérialiser, None pour laisser la table telle quelle
+    pub blocs: Option<Vec<BlocJson>>,
+    // état fusionné qui remplace tout le journal de la page
+    pub compacted: Option<Vec<u8>>,
+}
+
+impl Database {
+    // identifiant de client Yjs de cette base, tiré au premier appel. Un id
+    // neuf à chaque document ajouterait une entrée au vecteur d'état à chaque
+    // modification locale. Tiré sur 32 bits comme le fait Yjs.
+    pub async fn crdt_client_id(&self) -> Result<u64> {
+        sqlx::query("INSERT OR IGNORE INTO sync_state (key, value) VALUES ('crdt_client_id', ?)")
+            .bind((uuid::Uuid::new_v4().as_u128() as u32).to_string())
+            .execute(&self.pool)
+            .await?;
+
+        let id: String = sqlx::query("SELECT value FROM sync_state WHERE key = 'crdt_client_id'")
+            .fetch_one(&self.pool)
+            .await?
+            .get(0);
+        Ok(id.parse()?)
+    }
+
+    pub async fn append_page_update(&self, page_id: String, update: &[u8]) -> Result<i64> {
+        let id = sqlx::query(
+            "INSERT INTO page_updates (page_id, update_data, created_at)
+            VALUES (?, ?, ?)
+            RETURNING id",
+        )
+        .bind(&page_id)
+        .bind(update)
+        .bind(now_millis())
+        .fetch_one(&self.pool)
+        .await?
+        .get(0);
+
+        Ok(id)
+    }
+
+    // toutes les mises à jour de la page, dans l'ordre d'arrivée
+    pub async fn get_page_updates(&self, page_id: String) -> Result<PageUpdates> {
+        let rows = sqlx::query("SELECT id, update_data FROM page_updates WHERE page_id = ? ORDER BY id")
+            .bind(&page_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(PageUpdates {
+            last_id: rows.last().map(|row| row.get("id")).unwrap_or(0),
+            updates: rows.iter().map(|row| row.get("update_data")).collect(),
+            page_id,
+        })
+    }
+
+    // remplace les mises à jour jusqu'à `up_to_id` par leur fusion ; celles
+    // arrivées entre-temps (id plus grand) sont gardées
+    pub async fn compact_page_updates(&self, page_id: String, up_to_id: i64, merged: &[u8]) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // la fusion prend la place de la dernière mise à jour compactée : les
+        // ids sont communs à toutes les pages, on n'en invente pas
+        let rows_affected = sqlx::query("UPDATE page_updates SET update_data = ?, created_at = ? WHERE page_id = ? AND id = ?")
+            .bind(merged)
+            .bind(now_millis())
+            .bind(&page_id)
+            .bind(up_to_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM page_updates WHERE page_id = ? AND id < ?")
+            .bind(&page_id)
+            .bind(up_to_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    // lit le journal et les blocs de la page puis applique ce que `merge` en
+    // tire, dans une seule transaction IMMEDIATE : aucune écriture locale ne
+    // peut se glisser entre la lecture et la réécriture des blocs. Hors du
+    // journal d'annulation, comme replace_page_blocs.
+    pub async fn merge_page<F, R>(&self, page_id: String, merge: F) -> Result<R>
+    where
+        F: FnOnce(&PageUpdates, &[BlocJson]) -> Result<(PageMerge, R)>,
+    {
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        begin_untracked(&mut tx).await?;
+
+        let rows = sqlx::query("SELECT id, update_data FROM page_updates WHERE page_id = ? ORDER BY id")
+            .bind(&page_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        let log = PageUpdates {
+            last_id: rows.last().map(|row| row.get("id")).unwrap_or(0),
+            updates: rows.iter().map(|row| row.get("update_data")).collect(),
+            page_id: page_id.clone(),
+        };
+        let blocs = sqlx::query_as::<_, BlocJson>(
+            "SELECT id, position, content, page_id, bloc_type, created_at, updated_at FROM blocs
+            WHERE page_id = ? ORDER BY position, id",
+        )
+        .bind(&page_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let (result, output) = merge(&log, &blocs)?;
+        let now = now_millis();
+        let mut last_id = log.last_id;
+        for update in &result.updates {
+            last_id = sqlx::query(
+                "INSERT INTO page_updates (page_id, update_data, created_at)
+                VALUES (?, ?, ?)
+                RETURNING id",
+            )
+            .bind(&page_id)
+            .bind(update)
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await?
+            .get(0);
+        }
+        // même règle que compact_page_updates : la fusion reprend l'id de la
+        // dernière mise à jour
+        if let Some(merged) = &result.compacted {
+            sqlx::query("UPDATE page_updates SET update_data = ?, created_at = ? WHERE page_id = ? AND id = ?")
+                .bind(merged)
+                .bind(now)
+                .bind(&page_id)
+                .bind(last_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM page_updates WHERE page_id = ? AND id < ?")
+                .bind(&page_id)
+                .bind(last_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some(blocs) = &result.blocs {
+            write_page_blocs(&mut tx, &page_id, blocs).await?;
+        }
+
+        end_untracked(&mut tx).await?;
+        tx.commit().await?;
+        Ok(output)
+    }
+
+    // remplace les blocs de la page par l'état fusionné, en une transaction :
+    // les blocs absents de `blocs` sont supprimés. Hors du journal
+    // d'annulation : la fusion n'est pas une action de l'utilisateur.
+    pub async fn replace_page_blocs(&self, page_id: String, blocs: &[BlocJson]) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        begin_untracked(&mut tx).await?;
+        let written = write_page_blocs(&mut tx, &page_id, blocs).await?;
+        end_untracked(&mut tx).await?;
+        tx.commit().await?;
+        Ok(written)
+    }
+}
+
+// écrit l'état fusionné par différence avec les lignes actuelles : seuls les
+// blocs modifiés, ajoutés ou retirés (avec leurs props) passent dans le
+// journal des opérations. Renvoie le nombre de blocs écrits.
+pub(crate) async fn write_page_blocs(tx: &mut Transaction<'_, Sqlite>, page_id: &str, blocs: &[BlocJson]) -> Result<usize> {
+    let mut current: HashMap<String, BlocJson> =
+        sqlx::query_as::<_, BlocJson>("SELECT id, position, content, page_id, bloc_type, created_at, updated_at FROM blocs WHERE page_id = ?")
+            .bind(page_id)
+            .fetch_all(&mut **tx)
+            .await?
+            .into_iter()
+            .filter_map(|bloc| Some((bloc.id.clone()?, bloc)))
+            .collect();
+
+    let mut written = 0;
+    for bloc in blocs {
+        let Some(id) = bloc.id.as_deref() else { continue };
+        let before = current.remove(id);
+        match &before {
+            Some(row)
+                if row.position == bloc.position
+                    && row.content == bloc.content
+                    && row.bloc_type == bloc.bloc_type
+                    && row.created_at == bloc.created_at
+                    && row.updated_at == bloc.updated_at =>
+            {
+                continue
+            }
+            Some(_) => {
+                sqlx::query(
+                    "UPDATE blocs SET position = ?, content = ?, checksum = ?, bloc_type = ?, created_at = ?, updated_at = ?
+                    WHERE id = ?",
+                )
+                .bind(&bloc.position)
+                .bind(&bloc.content)
+                .bind(checksum(&bloc.content))
+                .bind(&bloc.bloc_type)
+                .bind(bloc.created_at)
+                .bind(bloc.updated_at)
+                .bind(id)
+                .execute(&mut **tx)
+                .await?;
+            }
+            None => {
+                sqlx::query(
+                    "INSERT INTO blocs (id, position, content, checksum, page_id, bloc_type, created_at, updated_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(id)
+                .bind(&bloc.position)
+                .bind(&bloc.content)
+                .bind(checksum(&bloc.content))
+                .bind(page_id)
+                .bind(&bloc.bloc_type)
+                .bind(bloc.created_at)
+                .bind(bloc.updated_at)
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+        index_hashtags(tx, id, before.as_ref().map(|row| row.content.as_str()), &bloc.content).await?;
+        written += 1;
+    }
+
+    // ce qui reste n'est plus dans l'état fusionné
+    for (id, row) in current {
+        sqlx::query("DELETE FROM props WHERE bloc_id = ?")
+            .bind(&id)
+            .execute(&mut **tx)
+            .await?;
+        sqlx::query("DELETE FROM blocs WHERE id = ?")
+            .bind(&id)
+            .execute(&mut **tx)
+            .await?;
+        index_hashtags(tx, &id, Some(&row.content), "").await?;
+    }
+    Ok(written)
+}
@@ -0,0 +1,141 @@
+// Lecture par tranches des blocs d'une page (ordre des positions) et des pages
+// d'un dossier (les plus récemment modifiées d'abord). Le curseur est la clé de
+// la dernière ligne lue : une insertion entre deux appels ne décale rien.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use crate::database::{BlocJson, Database, PageJson};
+
+// taille de tranche maximale acceptée
+pub const MAX_CHUNK: i64 = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlocCursor {
+    pub position: String,
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlocChunk {
+    pub blocs: Vec<BlocJson>,
+    // sources des blocs références de la tranche (transclusion.rs)
+    #[serde(default)]
+    pub references: HashMap<String, JsonValue>,
+    // None : dernière tranche
+    pub next: Option<BlocCursor>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub updated_at: i64,
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageChunk {
+    pub pages: Vec<PageJson>,
+    pub next: Option<PageCursor>,
+}
+
+fn check_limit(limit: i64) -> Result<()> {
+    if !(1..=MAX_CHUNK).contains(&limit) {
+        bail!("chunk size must be between 1 and {}", MAX_CHUNK);
+    }
+    Ok(())
+}
+
+impl Database {
+    // au plus `limit` blocs de la page après `after` (depuis le début si None)
+    pub async fn get_blocs_chunk(&self, page_id: String, after: Option<BlocCursor>, limit: i64) -> Result<BlocChunk> {
+        check_limit(limit)?;
+        let (position, id) = match after {
+            Some(cursor) => (Some(cursor.position), Some(cursor.id)),
+            None => (None, None),
+        };
+
+        // une ligne de plus pour savoir s'il reste une tranche
+        let mut blocs = sqlx::query_as::<_, BlocJson>(
+            "SELECT id, position, content, checksum, page_id, bloc_type, created_at, updated_at
+            FROM blocs
+            WHERE page_id = ?1 AND (?2 IS NULL OR (position, id) > (?2, ?3))
+            ORDER BY position, id
+            LIMIT ?4",
+        )
+        .bind(page_id)
+        .bind(position)
+        .bind(id)
+        .bind(limit + 1)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let next = if blocs.len() as i64 > limit {
+            blocs.truncate(limit as usize);
+            blocs.last().map(|bloc| BlocCursor {
+                position: bloc.position.clone(),
+                id: bloc.id.clone().unwrap_or_default(),
+            })
+        } else {
+            None
+        };
+        let references = self.resolve_references(&blocs).await?;
+        Ok(BlocChunk { blocs, references, next })
+    }
+
+    // pages du dossier, les plus récemment modifiées d'abord
+    pub async fn get_pages_chunk(&self, path: String, after: Option<PageCursor>, limit: i64) -> Result<PageChunk> {
+        check_limit(limit)?;
+        let (updated_at, id) = match after {
+            Some(cursor) => (Some(cursor.updated_at), Some(cursor.id)),
+            None => (None, None),
+        };
+
+        let mut pages = sqlx::query_as::<_, PageJson>(
+            "SELECT id, path, title, '' as cache, created_at, updated_at
+            FROM pages
+            WHERE path = ?1 AND (?2 IS NULL OR (updated_at, id) < (?2, ?3))
+            ORDER BY updated_at DESC, id DESC
+            LIMIT ?4",
+        )
+        .bind(path)
+        .bind(updated_at)
+        .bind(id)
+        .bind(limit + 1)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let next = if pages.len() as i64 > limit {
+            pages.truncate(limit as usize);
+            pages.last().map(|page| PageCursor {
+                updated_at: page.updated_at,
+                id: page.id.clone().unwrap_or_default(),
+            })
+        } else {
+            None
+        };
+        Ok(PageChunk { pages, next })
+    }
+
+    // passe les blocs de la page à `on_chunk` par tranches de `chunk_size`, avec
+    // les sources de leurs références : une seule tranche est en mémoire à la
+    // fois. Renvoie le nombre de blocs.
+    pub async fn stream_blocs_by_page_id<F>(&self, page_id: String, chunk_size: i64, mut on_chunk: F) -> Result<usize>
+    where
+        F: FnMut(Vec<BlocJson>, HashMap<String, JsonValue>) -> Result<()>,
+    {
+        let mut after = None;
+        let mut count = 0;
+        loop {
+            let chunk = self.get_blocs_chunk(page_id.clone(), after, chunk_size).await?;
+            count += chunk.blocs.len();
+            if !chunk.blocs.is_empty() {
+                on_chunk(chunk.blocs, chunk.references)?;
+            }
+            match chunk.next {
+                Some(next) => after = Some(next),
+                None => return Ok(count),
+            }
+        }
+    }
+}
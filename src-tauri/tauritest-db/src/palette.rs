@@ -0,0 +1,418 @@
+// Recherche floue de la palette de commandes : titres et chemins des pages,
+// titres de l'index (outline.rs), étiquettes et commandes déclarées par le
+// front. L'index est gardé en mémoire pour répondre à chaque frappe et mis à
+// jour depuis le journal des opérations (oplog.rs) : seules les pages touchées
+// sont relues. Le score flou est complété par la fréquence et la récence des
+// visites (navigation.rs).
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::collections::{HashMap, HashSet};
+
+use crate::database::Database;
+
+// opérations lues par appel à changes_since pendant une mise à jour
+const CHANGES_BATCH: i64 = 1000;
+
+const PAGES_FILTER: &str = "WHERE ?1 IS NULL OR p.id IN (SELECT value FROM json_each(?1))";
+
+// demi-vie de la récence d'une visite
+const RECENCY_HALF_LIFE_MS: f64 = 3.0 * 24.0 * 3600.0 * 1000.0;
+const FREQUENCY_WEIGHT: f64 = 6.0;
+const RECENCY_WEIGHT: f64 = 10.0;
+// une correspondance sur le détail (chemin, page) compte moins que sur le libellé
+const DETAIL_PENALTY: i32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaletteKind {
+    Page,
+    Heading,
+    Tag,
+    Command,
+}
+
+impl PaletteKind {
+    fn weight(&self) -> f64 {
+        match self {
+            PaletteKind::Command => 2.0,
+            PaletteKind::Page => 0.0,
+            PaletteKind::Tag => -1.0,
+            PaletteKind::Heading => -2.0,
+        }
+    }
+}
+
+// commande de l'application, déclarée par le front au démarrage
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaletteCommand {
+    pub id: String,
+    pub label: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaletteEntry {
+    pub kind: PaletteKind,
+    // id de la page, de l'étiquette ou de la commande ; bloc du titre
+    pub id: String,
+    pub label: String,
+    pub detail: String,
+    pub page_id: Option<String>,
+    pub anchor: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaletteHit {
+    #[serde(flatten)]
+    pub entry: PaletteEntry,
+    pub score: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VisitStats {
+    pub count: i64,
+    pub last_visited_at: i64,
+}
+
+struct Indexed {
+    entry: PaletteEntry,
+    label: Box<[char]>,
+    detail: Box<[char]>,
+    // lettres et chiffres ASCII présents, pour écarter vite les entrées
+    mask: u64,
+}
+
+pub struct PaletteIndex {
+    entries: Vec<Indexed>,
+    visits: HashMap<String, VisitStats>,
+    // curseur du journal des opérations à la dernière mise à jour
+    pub cursor: i64,
+    // date de la dernière lecture des étiquettes et des visites, qui ne
+    // passent pas par le journal
+    pub built_at: i64,
+}
+
+fn index_entry(entry: PaletteEntry) -> Indexed {
+    let label = lower(&entry.label);
+    let detail = lower(&entry.detail);
+    let mask = char_mask(&label) | char_mask(&detail);
+    Indexed { entry, label, detail, mask }
+}
+
+fn char_mask(chars: &[char]) -> u64 {
+    let mut mask = 0;
+    for c in chars {
+        match c {
+            'a'..='z' => mask |= 1 << (*c as u32 - 'a' as u32),
+            '0'..='9' => mask |= 1 << (26 + *c as u32 - '0' as u32),
+            _ => {}
+        }
+    }
+    mask
+}
+
+fn lower(text: &str) -> Box<[char]> {
+    text.chars().flat_map(char::to_lowercase).collect()
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, ' ' | '/' | '-' | '_' | '.' | ':' | '#')
+}
+
+// correspondance floue de `query` (en minuscules) dans `target` ; None si les
+// lettres n'y sont pas toutes, dans l'ordre
+pub fn fuzzy_score(query: &[char], target: &[char]) -> Option<i32> {
+    let first = *query.first()?;
+    let mut best = None;
+    // essaie quelques départs : le premier n'est pas toujours le meilleur
+    for start in target
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c == first)
+        .map(|(i, _)| i)
+        .take(4)
+    {
+        let mut score = 0;
+        let mut position = start;
+        let mut previous: Option<usize> = None;
+        let mut matched = true;
+        for &c in query {
+            let Some(offset) = target[position..].iter().position(|t| *t == c) else {
+                matched = false;
+                break;
+            };
+            let i = position + offset;
+            score += 1;
+            if i == 0 {
+                score += 10;
+            } else if is_separator(target[i - 1]) {
+                score += 8;
+            }
+            match previous {
+                Some(p) if p + 1 == i => score += 5,
+                Some(p) => score -= ((i - p - 1) as i32).min(5),
+                None => score -= (i as i32).min(5),
+            }
+            previous = Some(i);
+            position = i + 1;
+        }
+        if matched {
+            // les libellés courts passent devant à score égal
+            score -= (target.len() as i32 / 16).min(4);
+            best = best.max(Some(score));
+        }
+    }
+    best
+}
+
+impl PaletteIndex {
+    pub fn new(entries: Vec<PaletteEntry>, visits: HashMap<String, VisitStats>, cursor: i64, built_at: i64) -> Self {
+        let entries = entries.into_iter().map(index_entry).collect();
+        PaletteIndex { entries, visits, cursor, built_at }
+    }
+
+    // remplace les entrées (page et titres) des pages `page_ids` par `entries`
+    pub fn replace_pages(&mut self, page_ids: &HashSet<String>, entries: Vec<PaletteEntry>) {
+        self.entries.retain(|indexed| {
+            !matches!(indexed.entry.kind, PaletteKind::Page | PaletteKind::Heading)
+                || !indexed.entry.page_id.as_ref().is_some_and(|page_id| page_ids.contains(page_id))
+        });
+        self.entries.extend(entries.into_iter().map(index_entry));
+    }
+
+    // pages dont un titre indexé vient de l'un des blocs
+    fn heading_pages(&self, bloc_ids: &HashSet<String>) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|indexed| indexed.entry.kind == PaletteKind::Heading && bloc_ids.contains(&indexed.entry.id))
+            .filter_map(|indexed| indexed.entry.page_id.clone())
+            .collect()
+    }
+
+    // remplace toutes les entrées de ce type
+    pub fn replace_kind(&mut self, kind: PaletteKind, entries: Vec<PaletteEntry>) {
+        self.entries.retain(|indexed| indexed.entry.kind != kind);
+        self.entries.extend(entries.into_iter().map(index_entry));
+    }
+
+    pub fn set_visits(&mut self, visits: HashMap<String, VisitStats>) {
+        self.visits = visits;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn boost(&self, entry: &PaletteEntry, now: i64) -> f64 {
+        let Some(stats) = entry.page_id.as_ref().and_then(|page_id| self.visits.get(page_id)) else {
+            return 0.0;
+        };
+        let age = (now - stats.last_visited_at).max(0) as f64;
+        let recency = 0.5f64.powf(age / RECENCY_HALF_LIFE_MS);
+        let boost = FREQUENCY_WEIGHT * (1.0 + stats.count as f64).ln() + RECENCY_WEIGHT * recency;
+        // un titre profite moins des visites de sa page que la page elle-même
+        if entry.kind == PaletteKind::Heading {
+            boost / 2.0
+        } else {
+            boost
+        }
+    }
+
+    // meilleurs résultats pour `query` ; sans recherche, les pages les plus
+    // visitées et les commandes
+    pub fn search(&self, query: &str, limit: usize, now: i64) -> Vec<PaletteHit> {
+        let terms: Vec<Box<[char]>> = query.split_whitespace().map(lower).collect();
+        let query_mask = terms.iter().fold(0, |mask, term| mask | char_mask(term));
+
+        let mut scored: Vec<(f64, usize)> = Vec::new();
+        for (i, indexed) in self.entries.iter().enumerate() {
+            if indexed.mask & query_mask != query_mask {
+                continue;
+            }
+            let mut fuzzy = 0;
+            let mut matched = true;
+            for term in &terms {
+                let on_label = fuzzy_score(term, &indexed.label);
+                let on_detail = fuzzy_score(term, &indexed.detail).map(|score| score - DETAIL_PENALTY);
+                match on_label.max(on_detail) {
+                    Some(score) => fuzzy += score,
+                    None => {
+                        matched = false;
+                        break;
+                    }
+                }
+            }
+            if !matched {
+                continue;
+            }
+            let boost = self.boost(&indexed.entry, now);
+            if terms.is_empty() {
+                let listed = match indexed.entry.kind {
+                    PaletteKind::Command => true,
+                    PaletteKind::Page => boost > 0.0,
+                    _ => false,
+                };
+                if !listed {
+                    continue;
+                }
+            }
+            scored.push((fuzzy as f64 + boost + indexed.entry.kind.weight(), i));
+        }
+
+        let by_score = |a: &(f64, usize), b: &(f64, usize)| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1));
+        if scored.len() > limit && limit > 0 {
+            scored.select_nth_unstable_by(limit - 1, by_score);
+            scored.truncate(limit);
+        }
+        scored.sort_by(by_score);
+        scored.truncate(limit);
+
+        scored
+            .into_iter()
+            .map(|(score, i)| PaletteHit { entry: self.entries[i].entry.clone(), score })
+            .collect()
+    }
+}
+
+impl Database {
+    // pages et titres de l'index ; toutes les pages si `page_ids` vaut None
+    async fn palette_page_entries(&self, page_ids: Option<&HashSet<String>>) -> Result<Vec<PaletteEntry>> {
+        // tableau JSON des ids, lu par json_each
+        let page_ids = page_ids.map(serde_json::to_string).transpose()?;
+        let mut entries = Vec::new();
+
+        for row in sqlx::query(&format!("SELECT p.id, p.path, p.title FROM pages p {}", PAGES_FILTER))
+            .bind(&page_ids)
+            .fetch_all(&self.pool)
+            .await?
+        {
+            let id: String = row.get("id");
+            entries.push(PaletteEntry {
+                kind: PaletteKind::Page,
+                label: row.get("title"),
+                detail: row.get("path"),
+                page_id: Some(id.clone()),
+                anchor: None,
+                id,
+            });
+        }
+
+        for row in sqlx::query(&format!(
+            "SELECT h.bloc_id, h.text, h.anchor, h.page_id, p.title
+            FROM headings h JOIN pages p ON p.id = h.page_id {}",
+            PAGES_FILTER
+        ))
+        .bind(&page_ids)
+        .fetch_all(&self.pool)
+        .await?
+        {
+            entries.push(PaletteEntry {
+                kind: PaletteKind::Heading,
+                id: row.get("bloc_id"),
+                label: row.get("text"),
+                detail: row.get("title"),
+                page_id: Some(row.get("page_id")),
+                anchor: Some(row.get("anchor")),
+            });
+        }
+        Ok(entries)
+    }
+
+    async fn palette_tag_entries(&self) -> Result<Vec<PaletteEntry>> {
+        Ok(self
+            .get_tags()
+            .await?
+            .into_iter()
+            .map(|tag| PaletteEntry {
+                kind: PaletteKind::Tag,
+                detail: format!("#{}", tag.name),
+                label: tag.name,
+                id: tag.id,
+                page_id: None,
+                anchor: None,
+            })
+            .collect())
+    }
+
+    async fn palette_visits(&self) -> Result<HashMap<String, VisitStats>> {
+        let visits = sqlx::query("SELECT page_id, COUNT(*) AS count, MAX(visited_at) AS last FROM visits GROUP BY page_id")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| {
+                let stats = VisitStats { count: row.get("count"), last_visited_at: row.get("last") };
+                (row.get("page_id"), stats)
+            })
+            .collect();
+        Ok(visits)
+    }
+
+    // construit l'index de la palette depuis la base et les commandes du front
+    pub async fn build_palette_index(&self, commands: &[PaletteCommand], now: i64) -> Result<PaletteIndex> {
+        let cursor = self.latest_cursor().await?;
+        let mut entries = self.palette_page_entries(None).await?;
+        entries.extend(self.palette_tag_entries().await?);
+
+        for command in commands {
+            entries.push(PaletteEntry {
+                kind: PaletteKind::Command,
+                id: command.id.clone(),
+                label: command.label.clone(),
+                detail: command.keywords.join(" "),
+                page_id: None,
+                anchor: None,
+            });
+        }
+
+        Ok(PaletteIndex::new(entries, self.palette_visits().await?, cursor, now))
+    }
+
+    // relit les pages touchées depuis le curseur de l'index ; si `now` est
+    // donné, relit aussi étiquettes et visites
+    pub async fn update_palette_index(&self, index: &mut PaletteIndex, now: Option<i64>) -> Result<()> {
+        let mut page_ids = HashSet::new();
+        let mut bloc_ids = HashSet::new();
+        let mut cursor = index.cursor;
+        loop {
+            let feed = self.changes_since(cursor, CHANGES_BATCH).await?;
+            for operation in feed.operations {
+                // les titres suivent les blocs de leur page
+                match operation.entity.as_str() {
+                    "page" => page_ids.extend(operation.entity_id),
+                    "bloc" => {
+                        page_ids.extend(operation.page_id);
+                        bloc_ids.extend(operation.entity_id);
+                    }
+                    _ => {}
+                }
+            }
+            cursor = feed.cursor;
+            if !feed.has_more {
+                break;
+            }
+        }
+
+        // un bloc déplacé laisse ses titres sous l'ancienne page
+        if !bloc_ids.is_empty() {
+            page_ids.extend(index.heading_pages(&bloc_ids));
+        }
+        if !page_ids.is_empty() {
+            let entries = self.palette_page_entries(Some(&page_ids)).await?;
+            index.replace_pages(&page_ids, entries);
+        }
+        index.cursor = cursor;
+
+        if let Some(now) = now {
+            index.replace_kind(PaletteKind::Tag, self.palette_tag_entries().await?);
+            index.set_visits(self.palette_visits().await?);
+            index.built_at = now;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+// Ordre des blocs d'une page : contrôle des clés reçues, recompactage et
+// déplacement de blocs vers une autre page.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::Row;
+
+use crate::database::{checksum, now_millis, update_position_in, Database};
+use crate::fractional_index;
+use crate::previews::rebuild_preview_in;
+use crate::undo::{begin_undo_group, end_undo_group};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlocPosition {
+    pub id: String,
+    pub position: String,
+}
+
+// place des blocs déplacés dans la page cible ; None : à la fin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveAnchor {
+    Before(String),
+    After(String),
+}
+
+// au-delà, la clé la plus longue d'une page justifie un recompactage
+pub const REBALANCE_THRESHOLD: usize = 24;
+
+impl Database {
+    // comme update_bloc_position, mais vérifie aussi que la clé tombe entre les
+    // voisins vus par l'éditeur (prev_id / next_id, dans la même page)
+    pub async fn update_bloc_position_between(
+        &self,
+        id: String,
+        new_position: String,
+        prev_id: Option<String>,
+        next_id: Option<String>,
+        updated_at: i64,
+    ) -> Result<i8> {
+        // voisins lus et clé écrite dans la même transaction, prise en écriture
+        // dès le début : un déplacement concurrent attend son tour
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let page_id: String = sqlx::query("SELECT page_id FROM blocs WHERE id = ?")
+            .bind(&id)
+            .fetch_one(&mut *tx)
+            .await?
+            .get(0);
+
+        for (neighbor, must_be_before) in [(prev_id, true), (next_id, false)] {
+            let Some(neighbor) = neighbor else { continue };
+            let row = sqlx::query("SELECT position FROM blocs WHERE id = ? AND page_id = ?")
+                .bind(&neighbor)
+                .bind(&page_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            let Some(row) = row else {
+                return Ok(Self::ERROR);
+            };
+            let position: String = row.get(0);
+            let in_order = if must_be_before {
+                position < new_position
+            } else {
+                new_position < position
+            };
+            if !in_order {
+                return Ok(Self::ERROR);
+            }
+        }
+
+        let status = update_position_in(&mut tx, &id, &new_position, updated_at).await?;
+        tx.commit().await?;
+        Ok(status)
+    }
+
+    // positions de la page dans l'ordre courant
+    pub async fn get_page_positions(&self, page_id: String) -> Result<Vec<BlocPosition>> {
+        let rows = sqlx::query(
+            "SELECT id, position FROM blocs
+            WHERE page_id = ?
+            ORDER BY position, created_at, id",
+        )
+        .bind(&page_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| BlocPosition {
+                id: row.get("id"),
+                position: row.get("position"),
+            })
+            .collect())
+    }
+
+    pub async fn needs_rebalance(&self, page_id: String) -> Result<bool> {
+        let longest: Option<i64> = sqlx::query("SELECT MAX(length(position)) FROM blocs WHERE page_id = ?")
+            .bind(&page_id)
+            .fetch_one(&self.pool)
+            .await?
+            .get(0);
+        Ok(longest.unwrap_or(0) as usize > REBALANCE_THRESHOLD)
+    }
+
+    // réécrit toutes les clés de la page en a0, a1, ... sans changer l'ordre,
+    // y compris l'état "$".position du contenu, en une transaction
+    pub async fn rebalance_page_positions(&self, page_id: String) -> Result<Vec<BlocPosition>> {
+        let now = now_millis();
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "rebalance positions").await?;
+
+        // les doublons éventuels sont départagés par date de création puis id
+        let rows = sqlx::query(
+            "SELECT id, position, content FROM blocs
+            WHERE page_id = ?
+            ORDER BY position, created_at, id",
+        )
+        .bind(&page_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let keys = fractional_index::generate_n_keys_between(None, None, rows.len())?;
+        let mut positions = Vec::with_capacity(rows.len());
+
+        for (row, key) in rows.iter().zip(keys) {
+            let id: String = row.get("id");
+            let old_position: String = row.get("position");
+            let mut content: String = row.get("content");
+
+            if let Ok(mut node) = serde_json::from_str::<JsonValue>(&content) {
+                if let Some(state) = node.get_mut("$").and_then(JsonValue::as_object_mut) {
+                    state.insert("position".to_string(), JsonValue::String(key.clone()));
+                    content = serde_json::to_string(&node)?;
+                }
+            }
+
+            if old_position != key {
+                sqlx::query(
+                    "UPDATE blocs SET position = ?, content = ?, checksum = ?, updated_at = ?
+                    WHERE id = ? AND page_id = ?",
+                )
+                .bind(&key)
+                .bind(&content)
+                .bind(checksum(&content))
+                .bind(now)
+                .bind(&id)
+                .bind(&page_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            positions.push(BlocPosition { id, position: key });
+        }
+
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
+        Ok(positions)
+    }
+
+    // déplace une sélection contiguë de blocs d'une page vers `target_page`
+    // (éventuellement la même), en une étape d'annulation. Les blocs gardent
+    // leur ordre et reçoivent de nouvelles clés entre les voisins de l'ancre ;
+    // leurs props suivent (elles référencent l'id du bloc).
+    pub async fn move_blocs(
+        &self,
+        ids: Vec<String>,
+        target_page: String,
+        anchor: Option<MoveAnchor>,
+    ) -> Result<Vec<BlocPosition>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let now = now_millis();
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "move blocs").await?;
+
+        let source_page: String = match sqlx::query("SELECT page_id FROM blocs WHERE id = ?")
+            .bind(&ids[0])
+            .fetch_optional(&mut *tx)
+            .await?
+        {
+            Some(row) => row.get(0),
+            None => bail!("bloc {} not found", ids[0]),
+        };
+        let page_exists = sqlx::query("SELECT 1 FROM pages WHERE id = ?")
+            .bind(&target_page)
+            .fetch_optional(&mut *tx)
+            .await?
+            .is_some();
+        if !page_exists {
+            bail!("page {} not found", target_page);
+        }
+
+        // la sélection doit former une suite sans trou dans la page d'origine
+        let source: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM blocs WHERE page_id = ? ORDER BY position, created_at, id",
+        )
+        .bind(&source_page)
+        .fetch_all(&mut *tx)
+        .await?;
+        let Some(start) = source.iter().position(|id| ids.contains(id)) else {
+            bail!("blocs must belong to the same page");
+        };
+        let selection = &source[start..(start + ids.len()).min(source.len())];
+        if selection.len() != ids.len() || !selection.iter().all(|id| ids.contains(id)) {
+            bail!("blocs must be contiguous and on the same page");
+        }
+        let selection = selection.to_vec();
+
+        // voisins de l'ancre dans la page cible, sans les blocs déplacés
+        let target: Vec<(String, String)> = sqlx::query(
+            "SELECT id, position FROM blocs WHERE page_id = ? ORDER BY position, created_at, id",
+        )
+        .bind(&target_page)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| (row.get("id"), row.get("position")))
+        .filter(|(id, _): &(String, String)| !selection.contains(id))
+        .collect();
+        let index_of = |anchor: &str| -> Result<usize> {
+            match target.iter().position(|(id, _)| id == anchor) {
+                Some(i) => Ok(i),
+                None => bail!("anchor {} is not a bloc of the target page outside the selection", anchor),
+            }
+        };
+        let (prev, next) = match &anchor {
+            None => (target.last(), None),
+            Some(MoveAnchor::Before(id)) => {
+                let i = index_of(id)?;
+                (i.checked_sub(1).map(|i| &target[i]), Some(&target[i]))
+            }
+            Some(MoveAnchor::After(id)) => {
+                let i = index_of(id)?;
+                (Some(&target[i]), target.get(i + 1))
+            }
+        };
+        let keys = fractional_index::generate_n_keys_between(
+            prev.map(|(_, position)| position.as_str()),
+            next.map(|(_, position)| position.as_str()),
+            selection.len(),
+        )?;
+
+        let mut positions = Vec::with_capacity(selection.len());
+        for (id, key) in selection.into_iter().zip(keys) {
+            let mut content: String = sqlx::query("SELECT content FROM blocs WHERE id = ?")
+                .bind(&id)
+                .fetch_one(&mut *tx)
+                .await?
+                .get(0);
+            if let Ok(mut node) = serde_json::from_str::<JsonValue>(&content) {
+                if let Some(state) = node.get_mut("$").and_then(JsonValue::as_object_mut) {
+                    state.insert("position".to_string(), JsonValue::String(key.clone()));
+                    content = serde_json::to_string(&node)?;
+                }
+            }
+
+            sqlx::query(
+                "UPDATE blocs SET page_id = ?, position = ?, content = ?, checksum = ?, updated_at = ?
+                WHERE id = ?",
+            )
+            .bind(&target_page)
+            .bind(&key)
+            .bind(&content)
+            .bind(checksum(&content))
+            .bind(now)
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+            positions.push(BlocPosition { id, position: key });
+        }
+
+        sqlx::query("UPDATE pages SET updated_at = ? WHERE id IN (?, ?)")
+            .bind(now)
+            .bind(&source_page)
+            .bind(&target_page)
+            .execute(&mut *tx)
+            .await?;
+
+        // aperçus et titres indexés des deux pages, écrits avec le déplacement
+        rebuild_preview_in(&mut tx, &source_page).await?;
+        if target_page != source_page {
+            rebuild_preview_in(&mut tx, &target_page).await?;
+        }
+
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
+        Ok(positions)
+    }
+}
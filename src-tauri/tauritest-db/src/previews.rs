@@ -0,0 +1,182 @@
+// Aperçu d'une page (extrait, nombre de mots, plan, première image, temps de
+// lecture), calculé depuis ses blocs et gardé en JSON dans `page_previews`
+// (`pages.cache` appartient à l'éditeur). Il
+// porte l'empreinte des checksums des blocs dont il est issu : s'ils ont changé
+// depuis, l'aperçu est recalculé à la lecture. Les titres sont aussi copiés
+// dans l'index de outline.rs.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{Row, Sqlite, SqliteConnection, Transaction};
+
+use crate::database::{checksum, Database};
+use crate::lexical;
+use crate::outline::index_headings;
+
+pub const EXCERPT_CHARS: usize = 280;
+pub const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineEntry {
+    pub level: usize,
+    pub text: String,
+    pub bloc_id: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PagePreview {
+    pub excerpt: String,
+    pub word_count: usize,
+    pub outline: Vec<OutlineEntry>,
+    // `src` de la première image
+    pub first_image: Option<String>,
+    pub reading_minutes: usize,
+    // empreinte des checksums des blocs, dans l'ordre des positions
+    pub checksum: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageWithPreview {
+    pub id: String,
+    pub path: String,
+    pub title: String,
+    pub updated_at: i64,
+    pub preview: PagePreview,
+}
+
+// calcule l'aperçu à partir des blocs (id, contenu), dans l'ordre des positions
+pub fn build_preview(blocs: &[(String, String)], blocs_checksum: String) -> PagePreview {
+    let mut preview = PagePreview { checksum: blocs_checksum, ..PagePreview::default() };
+    let mut texts = Vec::new();
+
+    for (bloc_id, content) in blocs {
+        let Ok(node) = serde_json::from_str::<JsonValue>(content) else {
+            continue;
+        };
+        lexical::walk(&node, &mut |n| {
+            if let Some(level) = lexical::heading_level(n) {
+                preview.outline.push(OutlineEntry {
+                    level,
+                    text: lexical::plain_text(n),
+                    bloc_id: bloc_id.clone(),
+                });
+            }
+            if preview.first_image.is_none() && lexical::node_type(n) == "image" {
+                preview.first_image = n.get("src").and_then(JsonValue::as_str).map(str::to_string);
+            }
+        });
+        texts.push(lexical::plain_text(&node));
+    }
+
+    let text = texts.join("\n");
+    preview.word_count = text.split_whitespace().count();
+    preview.reading_minutes = preview.word_count.div_ceil(WORDS_PER_MINUTE);
+
+    // extrait sur une ligne, coupé à la fin d'un mot
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= EXCERPT_CHARS {
+        preview.excerpt = flat;
+    } else {
+        let cut: String = flat.chars().take(EXCERPT_CHARS).collect();
+        let cut = match cut.rfind(' ') {
+            Some(end) => cut[..end].to_string(),
+            None => cut,
+        };
+        preview.excerpt = format!("{}…", cut);
+    }
+
+    preview
+}
+
+// (id, contenu) des blocs et empreinte de leurs checksums
+async fn page_blocs_checksum(
+    conn: &mut SqliteConnection,
+    page_id: &str,
+    with_content: bool,
+) -> Result<(Vec<(String, String)>, String)> {
+    let rows = sqlx::query(if with_content {
+        "SELECT id, checksum, content FROM blocs WHERE page_id = ? ORDER BY position, id"
+    } else {
+        "SELECT id, checksum, '' AS content FROM blocs WHERE page_id = ? ORDER BY position, id"
+    })
+    .bind(page_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut fingerprint = String::new();
+    let mut blocs = Vec::new();
+    for row in rows {
+        let id: String = row.get("id");
+        fingerprint.push_str(&id);
+        fingerprint.push(':');
+        fingerprint.push_str(row.get("checksum"));
+        fingerprint.push('\n');
+        if with_content {
+            blocs.push((id, row.get("content")));
+        }
+    }
+    Ok((blocs, checksum(&fingerprint)))
+}
+
+// recalcule l'aperçu et les titres indexés de la page dans la transaction de
+// l'appelant, pour les écritures qui changent ses blocs
+pub(crate) async fn rebuild_preview_in(tx: &mut Transaction<'_, Sqlite>, page_id: &str) -> Result<PagePreview> {
+    let (blocs, current) = page_blocs_checksum(tx, page_id, true).await?;
+    let preview = build_preview(&blocs, current);
+
+    sqlx::query("INSERT OR REPLACE INTO page_previews (page_id, preview) VALUES (?, ?)")
+        .bind(page_id)
+        .bind(serde_json::to_string(&preview)?)
+        .execute(&mut **tx)
+        .await?;
+    index_headings(tx, page_id, &preview.outline).await?;
+    Ok(preview)
+}
+
+impl Database {
+    // recalcule l'aperçu si les blocs ont changé depuis ; à appeler à
+    // l'enregistrement de la page
+    pub async fn refresh_page_preview(&self, page_id: String) -> Result<PagePreview> {
+        let cache: Option<String> = sqlx::query("SELECT preview FROM page_previews WHERE page_id = ?")
+            .bind(&page_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get(0));
+        let cached = cache.and_then(|cache| serde_json::from_str::<PagePreview>(&cache).ok());
+
+        let (_, current) = page_blocs_checksum(&mut *self.pool.acquire().await?, &page_id, false).await?;
+        if let Some(preview) = cached.filter(|preview| preview.checksum == current) {
+            return Ok(preview);
+        }
+        self.rebuild_page_preview(page_id).await
+    }
+
+    // recalcule l'aperçu et les titres indexés de la page, même s'ils sont à jour
+    pub async fn rebuild_page_preview(&self, page_id: String) -> Result<PagePreview> {
+        let mut tx = self.pool.begin().await?;
+        let preview = rebuild_preview_in(&mut tx, &page_id).await?;
+        tx.commit().await?;
+        Ok(preview)
+    }
+
+    // pages du dossier avec leur aperçu, pour l'accueil et les listes
+    pub async fn get_page_previews_by_path(&self, path: String) -> Result<Vec<PageWithPreview>> {
+        let rows = sqlx::query("SELECT id, path, title, updated_at FROM pages WHERE path = ? ORDER BY updated_at DESC, id")
+            .bind(&path)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut pages = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.get("id");
+            pages.push(PageWithPreview {
+                preview: self.refresh_page_preview(id.clone()).await?,
+                id,
+                path: row.get("path"),
+                title: row.get("title"),
+                updated_at: row.get("updated_at"),
+            });
+        }
+        Ok(pages)
+    }
+}
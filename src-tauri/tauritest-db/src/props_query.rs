@@ -0,0 +1,557 @@
+// Petit langage de requête sur les props des blocs, évalué en Rust :
+//
+//   status = "todo" AND due < today() SORT BY priority DESC LIMIT 20
+//
+// Comparaisons = != < <= > >= CONTAINS sur une clé de prop, EXISTS clé, AND,
+// OR, NOT et parenthèses. Les valeurs sont des chaînes, des nombres, true /
+// false ou today() (+/- n jours). Deux valeurs numériques se comparent en
+// nombres, deux dates AAAA-MM-JJ en dates, le reste en texte. Une prop absente
+// ne vérifie aucune comparaison. `page.title`, `page.path` et `bloc.type`
+// désignent la page et le bloc plutôt qu'une prop.
+use anyhow::{bail, Result};
+use chrono::{NaiveDate, TimeDelta};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::Row;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::database::Database;
+use crate::lexical;
+
+// imbrication maximale (parenthèses, NOT, suites de AND / OR) : au-delà, la
+// requête est refusée plutôt que d'épuiser la pile
+pub const MAX_QUERY_DEPTH: usize = 128;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+    Plus,
+    Minus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Literal {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    // today() décalé de n jours
+    Today(i64),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropsExpr {
+    Compare { key: String, op: CompareOp, value: Literal },
+    Exists(String),
+    Not(Box<PropsExpr>),
+    And(Box<PropsExpr>, Box<PropsExpr>),
+    Or(Box<PropsExpr>, Box<PropsExpr>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub key: String,
+    pub descending: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PropsQuery {
+    // None : tous les blocs qui ont au moins une prop
+    pub filter: Option<PropsExpr>,
+    pub sort: Vec<SortKey>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PropsMatch {
+    pub bloc_id: String,
+    pub bloc_type: String,
+    pub page_id: String,
+    pub page_title: String,
+    pub page_path: String,
+    pub props: BTreeMap<String, String>,
+    pub text: String,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '+' => Token::Plus,
+            '-' if !chars.get(i + 1).is_some_and(char::is_ascii_digit) => Token::Minus,
+            '=' => Token::Op("="),
+            '!' if chars.get(i + 1) == Some(&'=') => {
+                i += 1;
+                Token::Op("!=")
+            }
+            '<' | '>' => {
+                let with_eq = chars.get(i + 1) == Some(&'=');
+                if with_eq {
+                    i += 1;
+                }
+                Token::Op(match (c, with_eq) {
+                    ('<', false) => "<",
+                    ('<', true) => "<=",
+                    ('>', false) => ">",
+                    _ => ">=",
+                })
+            }
+            '"' | '\'' | '`' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => bail!("unterminated string at {}", start),
+                        Some('\\') if chars.get(i + 1).is_some() => {
+                            text.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(q) if *q == c => break,
+                        Some(other) => {
+                            text.push(*other);
+                            i += 1;
+                        }
+                    }
+                }
+                // `clé avec espaces`
+                if c == '`' {
+                    Token::Ident(text)
+                } else {
+                    Token::Str(text)
+                }
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+                    end += 1;
+                }
+                let text: String = chars[i..end].iter().collect();
+                i = end - 1;
+                Token::Num(text.parse().map_err(|_| anyhow::anyhow!("invalid number at {}", start))?)
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].is_alphanumeric() || matches!(chars[end], '_' | '.' | '-')) {
+                    end += 1;
+                }
+                let text: String = chars[i..end].iter().collect();
+                i = end - 1;
+                Token::Ident(text)
+            }
+            other => bail!("unexpected character '{}' at {}", other, start),
+        };
+        tokens.push((start, token));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    len: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.next).map_or(self.len, |(at, _)| *at)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).map(|(_, token)| token.clone());
+        self.next += 1;
+        token
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case(word) => {
+                self.next += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        let at = self.position();
+        match self.advance() {
+            Some(token) if token == expected => Ok(()),
+            _ => bail!("expected {:?} at {}", expected, at),
+        }
+    }
+
+    fn key(&mut self) -> Result<String> {
+        let at = self.position();
+        match self.advance() {
+            Some(Token::Ident(key)) if !is_reserved(&key) => Ok(key),
+            _ => bail!("expected a property name at {}", at),
+        }
+    }
+
+    // un niveau de plus dans l'arbre de la requête
+    fn deeper(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_QUERY_DEPTH {
+            bail!("query nested too deeply at {}", self.position());
+        }
+        Ok(())
+    }
+
+    fn or(&mut self) -> Result<PropsExpr> {
+        let depth = self.depth;
+        let mut left = self.and()?;
+        while self.keyword("OR") {
+            self.deeper()?;
+            left = PropsExpr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<PropsExpr> {
+        let depth = self.depth;
+        let mut left = self.not()?;
+        while self.keyword("AND") {
+            self.deeper()?;
+            left = PropsExpr::And(Box::new(left), Box::new(self.not()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<PropsExpr> {
+        if self.keyword("NOT") {
+            self.deeper()?;
+            let expr = PropsExpr::Not(Box::new(self.not()?));
+            self.depth -= 1;
+            return Ok(expr);
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.next += 1;
+            self.deeper()?;
+            let expr = self.or()?;
+            self.expect(Token::RParen)?;
+            self.depth -= 1;
+            return Ok(expr);
+        }
+        if self.keyword("EXISTS") {
+            return Ok(PropsExpr::Exists(self.key()?));
+        }
+
+        let key = self.key()?;
+        let at = self.position();
+        let op = match self.advance() {
+            Some(Token::Op("=")) => CompareOp::Eq,
+            Some(Token::Op("!=")) => CompareOp::Ne,
+            Some(Token::Op("<")) => CompareOp::Lt,
+            Some(Token::Op("<=")) => CompareOp::Le,
+            Some(Token::Op(">")) => CompareOp::Gt,
+            Some(Token::Op(">=")) => CompareOp::Ge,
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("CONTAINS") => CompareOp::Contains,
+            _ => bail!("expected a comparison after {} at {}", key, at),
+        };
+        Ok(PropsExpr::Compare { key, op, value: self.literal()? })
+    }
+
+    fn literal(&mut self) -> Result<Literal> {
+        let at = self.position();
+        Ok(match self.advance() {
+            Some(Token::Str(text)) => Literal::Text(text),
+            Some(Token::Num(n)) => Literal::Number(n),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("true") => Literal::Bool(true),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("false") => Literal::Bool(false),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("today") => {
+                self.expect(Token::LParen)?;
+                self.expect(Token::RParen)?;
+                let sign = match self.peek() {
+                    Some(Token::Plus) => 1,
+                    Some(Token::Minus) => -1,
+                    // "today()-7" : le signe est lu avec le nombre
+                    Some(Token::Num(days)) if *days < 0.0 && days.fract() == 0.0 => {
+                        let days = *days as i64;
+                        self.next += 1;
+                        return today_offset(days, at);
+                    }
+                    _ => return Ok(Literal::Today(0)),
+                };
+                self.next += 1;
+                let at = self.position();
+                match self.advance() {
+                    Some(Token::Num(days)) if days.fract() == 0.0 => today_offset(sign * days as i64, at)?,
+                    _ => bail!("expected a number of days at {}", at),
+                }
+            }
+            _ => bail!("expected a value at {}", at),
+        })
+    }
+}
+
+// décalage de today() ; les nombres trop grands (saturés par `as i64`) sont
+// refusés ici plutôt qu'à l'évaluation
+fn today_offset(days: i64, at: usize) -> Result<Literal> {
+    if TimeDelta::try_days(days).is_none() {
+        bail!("number of days out of range at {}", at);
+    }
+    Ok(Literal::Today(days))
+}
+
+fn is_reserved(word: &str) -> bool {
+    ["AND", "OR", "NOT", "EXISTS", "SORT", "BY", "LIMIT", "CONTAINS"]
+        .iter()
+        .any(|reserved| word.eq_ignore_ascii_case(reserved))
+}
+
+pub fn parse_props_query(input: &str) -> Result<PropsQuery> {
+    let mut parser = Parser { tokens: tokenize(input)?, next: 0, len: input.chars().count(), depth: 0 };
+    let mut query = PropsQuery::default();
+
+    let starts_clause = |parser: &Parser| {
+        matches!(parser.peek(), Some(Token::Ident(word)) if word.eq_ignore_ascii_case("SORT") || word.eq_ignore_ascii_case("LIMIT"))
+    };
+    if parser.peek().is_some() && !starts_clause(&parser) {
+        query.filter = Some(parser.or()?);
+    }
+    if parser.keyword("SORT") {
+        if !parser.keyword("BY") {
+            bail!("expected BY at {}", parser.position());
+        }
+        loop {
+            let key = parser.key()?;
+            let descending = if parser.keyword("DESC") {
+                true
+            } else {
+                parser.keyword("ASC");
+                false
+            };
+            query.sort.push(SortKey { key, descending });
+            if parser.peek() != Some(&Token::Comma) {
+                break;
+            }
+            parser.next += 1;
+        }
+    }
+    if parser.keyword("LIMIT") {
+        let at = parser.position();
+        match parser.advance() {
+            Some(Token::Num(n)) if n >= 0.0 && n.fract() == 0.0 => query.limit = Some(n as usize),
+            _ => bail!("expected a limit at {}", at),
+        }
+    }
+    if parser.peek().is_some() {
+        bail!("unexpected input at {}", parser.position());
+    }
+    Ok(query)
+}
+
+// valeur de prop classée pour le tri ; rank() ordonne les classes
+enum SortValue<'a> {
+    Number(f64),
+    Date(NaiveDate),
+    Text(&'a str),
+}
+
+impl<'a> SortValue<'a> {
+    fn of(text: &'a str) -> Self {
+        if let Ok(n) = text.trim().parse::<f64>() {
+            return SortValue::Number(n);
+        }
+        match NaiveDate::parse_from_str(text.get(..10).unwrap_or(text), "%Y-%m-%d") {
+            Ok(date) => SortValue::Date(date),
+            Err(_) => SortValue::Text(text),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortValue::Number(_) => 0,
+            SortValue::Date(_) => 1,
+            SortValue::Text(_) => 2,
+        }
+    }
+}
+
+// ordre total entre deux valeurs : nombres, puis dates, puis texte, chaque
+// classe comparée à part (sinon "9" < "10" < "1a" < "9" et sort_by panique)
+fn compare_values(left: &str, right: &str) -> Ordering {
+    match (SortValue::of(left), SortValue::of(right)) {
+        (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(&b),
+        (SortValue::Date(a), SortValue::Date(b)) => a.cmp(&b),
+        (SortValue::Text(a), SortValue::Text(b)) => a.cmp(b),
+        (a, b) => a.rank().cmp(&b.rank()),
+    }
+}
+
+struct Fields<'a> {
+    page_title: &'a str,
+    page_path: &'a str,
+    bloc_type: &'a str,
+    props: &'a BTreeMap<String, String>,
+}
+
+impl Fields<'_> {
+    fn value(&self, key: &str) -> Option<&str> {
+        match key {
+            "page.title" => Some(self.page_title),
+            "page.path" => Some(self.page_path),
+            "bloc.type" => Some(self.bloc_type),
+            _ => self.props.get(key).map(String::as_str),
+        }
+    }
+}
+
+fn literal_text(literal: &Literal, today: NaiveDate) -> Result<String> {
+    Ok(match literal {
+        Literal::Text(text) => text.clone(),
+        Literal::Number(n) => n.to_string(),
+        Literal::Bool(b) => b.to_string(),
+        Literal::Today(days) => match TimeDelta::try_days(*days).and_then(|delta| today.checked_add_signed(delta)) {
+            Some(date) => date.format("%Y-%m-%d").to_string(),
+            None => bail!("today() {:+} days is out of range", days),
+        },
+    })
+}
+
+fn eval(expr: &PropsExpr, row: &Fields, today: NaiveDate) -> Result<bool> {
+    Ok(match expr {
+        PropsExpr::And(a, b) => eval(a, row, today)? && eval(b, row, today)?,
+        PropsExpr::Or(a, b) => eval(a, row, today)? || eval(b, row, today)?,
+        PropsExpr::Not(a) => !eval(a, row, today)?,
+        PropsExpr::Exists(key) => row.value(key).is_some(),
+        PropsExpr::Compare { key, op, value } => {
+            let Some(actual) = row.value(key) else {
+                return Ok(false);
+            };
+            let expected = literal_text(value, today)?;
+            match op {
+                CompareOp::Contains => actual.to_lowercase().contains(&expected.to_lowercase()),
+                CompareOp::Eq => compare_values(actual, &expected) == Ordering::Equal,
+                CompareOp::Ne => compare_values(actual, &expected) != Ordering::Equal,
+                CompareOp::Lt => compare_values(actual, &expected) == Ordering::Less,
+                CompareOp::Le => compare_values(actual, &expected) != Ordering::Greater,
+                CompareOp::Gt => compare_values(actual, &expected) == Ordering::Greater,
+                CompareOp::Ge => compare_values(actual, &expected) != Ordering::Less,
+            }
+        }
+    })
+}
+
+impl Database {
+    // blocs dont les props vérifient la requête, avec leur page ; `today` sert
+    // à today()
+    pub async fn query_props(&self, query: &str, today: NaiveDate) -> Result<Vec<PropsMatch>> {
+        let query = parse_props_query(query)?;
+
+        let mut props: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+        for row in sqlx::query("SELECT bloc_id, key, value FROM props").fetch_all(&self.pool).await? {
+            props.entry(row.get("bloc_id")).or_default().insert(row.get("key"), row.get("value"));
+        }
+
+        // ordre par défaut : celui des pages puis des blocs
+        let rows = sqlx::query(
+            "SELECT b.id, b.bloc_type, b.content, p.id AS page_id, p.title, p.path
+            FROM blocs b JOIN pages p ON p.id = b.page_id
+            WHERE b.id IN (SELECT bloc_id FROM props)
+            ORDER BY p.path, p.title, b.position",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut found = Vec::new();
+        for row in rows {
+            let bloc_id: String = row.get("id");
+            let Some(bloc_props) = props.remove(&bloc_id) else {
+                continue;
+            };
+            let candidate = PropsMatch {
+                bloc_id,
+                bloc_type: row.get("bloc_type"),
+                page_id: row.get("page_id"),
+                page_title: row.get("title"),
+                page_path: row.get("path"),
+                props: bloc_props,
+                text: String::new(),
+            };
+            let keep = match &query.filter {
+                Some(filter) => eval(filter, &as_row(&candidate), today)?,
+                None => true,
+            };
+            if keep {
+                let content: String = row.get("content");
+                let text = serde_json::from_str::<JsonValue>(&content)
+                    .map(|node| lexical::plain_text(&node))
+                    .unwrap_or_default();
+                found.push(PropsMatch { text, ..candidate });
+            }
+        }
+
+        // tri stable : les valeurs absentes passent après les autres
+        if !query.sort.is_empty() {
+            found.sort_by(|a, b| {
+                let (a, b) = (as_row(a), as_row(b));
+                for sort in &query.sort {
+                    let ordering = match (a.value(&sort.key), b.value(&sort.key)) {
+                        (Some(x), Some(y)) if sort.descending => compare_values(y, x),
+                        (Some(x), Some(y)) => compare_values(x, y),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    };
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                Ordering::Equal
+            });
+        }
+        if let Some(limit) = query.limit {
+            found.truncate(limit);
+        }
+        Ok(found)
+    }
+}
+
+fn as_row(bloc: &PropsMatch) -> Fields<'_> {
+    Fields {
+        page_title: &bloc.page_title,
+        page_path: &bloc.page_path,
+        bloc_type: &bloc.bloc_type,
+        props: &bloc.props,
+    }
+}
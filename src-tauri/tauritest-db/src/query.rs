@@ -0,0 +1,239 @@
+// Requêtes JSON sur le contenu des blocs (noeuds Lexical sérialisés).
+// Les filtres s'appliquent à chaque objet de l'arbre (json_tree), pas seulement
+// à la racine : on peut viser un listitem non coché au fond d'une liste.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::sqlite::SqliteArguments;
+use sqlx::{query::Query, Row, Sqlite};
+
+use crate::database::Database;
+use crate::lexical;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    #[default]
+    Eq,
+    Ne,
+    // sous-chaîne, insensible à la casse
+    Contains,
+}
+
+// `path` est un chemin json_extract relatif au noeud testé, ex. "$.type"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeFilter {
+    pub path: String,
+    #[serde(default)]
+    pub op: FilterOp,
+    pub value: JsonValue,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContentQuery {
+    // tous les filtres doivent correspondre au même noeud
+    pub filters: Vec<NodeFilter>,
+    // limite aux blocs de ce type (bloc_type), ex. "code"
+    #[serde(default)]
+    pub bloc_type: Option<String>,
+    // limite aux pages de ce chemin
+    #[serde(default)]
+    pub page_path: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentMatch {
+    pub bloc_id: String,
+    pub bloc_type: String,
+    pub page_id: String,
+    pub page_title: String,
+    pub page_path: String,
+    // chemin du noeud dans le bloc, "$" pour la racine
+    pub node_path: String,
+    pub node: JsonValue,
+    pub text: String,
+}
+
+// valeur liée à la requête, dans le type que renvoie json_extract
+enum SqlValue {
+    Text(String),
+    Integer(i64),
+    Real(f64),
+}
+
+fn bind_all<'q>(
+    mut query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    values: &'q [SqlValue],
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    for value in values {
+        query = match value {
+            SqlValue::Text(text) => query.bind(text),
+            SqlValue::Integer(n) => query.bind(n),
+            SqlValue::Real(n) => query.bind(n),
+        };
+    }
+    query
+}
+
+// condition SQL sur `node` (le JSON du noeud testé)
+fn filter_sql(filter: &NodeFilter, values: &mut Vec<SqlValue>) -> String {
+    values.push(SqlValue::Text(filter.path.clone()));
+    let extract = "json_extract(node, ?)";
+
+    if filter.op == FilterOp::Contains {
+        let needle = match &filter.value {
+            JsonValue::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        values.push(SqlValue::Text(needle));
+        return format!("instr(lower({}), lower(?)) > 0", extract);
+    }
+
+    let op = if filter.op == FilterOp::Eq { "=" } else { "<>" };
+    match &filter.value {
+        JsonValue::Null => {
+            // json_extract renvoie NULL pour une clé absente comme pour null
+            let test = if filter.op == FilterOp::Eq { "IS NULL" } else { "IS NOT NULL" };
+            format!("{} {}", extract, test)
+        }
+        // json_extract rend les booléens en 0 / 1
+        JsonValue::Bool(b) => {
+            values.push(SqlValue::Integer(*b as i64));
+            format!("{} {} ?", extract, op)
+        }
+        JsonValue::Number(n) => {
+            values.push(match n.as_i64() {
+                Some(i) => SqlValue::Integer(i),
+                None => SqlValue::Real(n.as_f64().unwrap_or(0.0)),
+            });
+            format!("{} {} ?", extract, op)
+        }
+        JsonValue::String(text) => {
+            values.push(SqlValue::Text(text.clone()));
+            format!("{} {} ?", extract, op)
+        }
+        // objets et tableaux : comparaison sur le JSON minifié
+        other => {
+            values.push(SqlValue::Text(other.to_string()));
+            format!("{} {} json(?)", extract, op)
+        }
+    }
+}
+
+impl Database {
+    // noeuds de tous les blocs qui satisfont les filtres, avec leur page
+    pub async fn query_nodes(&self, query: &ContentQuery) -> Result<Vec<ContentMatch>> {
+        let mut values = Vec::new();
+        let mut conditions = vec!["t.type = 'object'".to_string()];
+
+        if let Some(bloc_type) = &query.bloc_type {
+            conditions.push("b.bloc_type = ?".to_string());
+            values.push(SqlValue::Text(bloc_type.clone()));
+        }
+        if let Some(page_path) = &query.page_path {
+            conditions.push("p.path = ?".to_string());
+            values.push(SqlValue::Text(page_path.clone()));
+        }
+
+        let mut filters = Vec::new();
+        for filter in &query.filters {
+            filters.push(filter_sql(filter, &mut values));
+        }
+
+        let limit = match query.limit {
+            Some(limit) => {
+                values.push(SqlValue::Integer(limit));
+                "LIMIT ?"
+            }
+            None => "",
+        };
+
+        // un contenu invalide ferait échouer json_tree pour toute la requête
+        let sql = format!(
+            "SELECT bloc_id, bloc_type, page_id, page_title, page_path, node_path, node
+            FROM (
+                SELECT b.id as bloc_id, b.bloc_type, b.position, p.id as page_id,
+                    p.title as page_title, p.path as page_path,
+                    t.fullkey as node_path, t.value as node
+                FROM blocs b
+                JOIN pages p ON p.id = b.page_id,
+                json_tree(CASE WHEN json_valid(b.content) THEN b.content ELSE '{{}}' END) t
+                WHERE {}
+            )
+            {}
+            ORDER BY page_path, page_title, position, node_path
+            {}",
+            conditions.join(" AND "),
+            if filters.is_empty() {
+                String::new()
+            } else {
+                format!("WHERE {}", filters.join(" AND "))
+            },
+            limit
+        );
+
+        let rows = bind_all(sqlx::query(&sql), &values)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut matches = Vec::with_capacity(rows.len());
+        for row in rows {
+            let node: JsonValue = serde_json::from_str(row.get("node"))?;
+            matches.push(ContentMatch {
+                bloc_id: row.get("bloc_id"),
+                bloc_type: row.get("bloc_type"),
+                page_id: row.get("page_id"),
+                page_title: row.get("page_title"),
+                page_path: row.get("page_path"),
+                node_path: row.get("node_path"),
+                text: lexical::plain_text(&node),
+                node,
+            });
+        }
+
+        Ok(matches)
+    }
+
+    // contenu des blocs dont la racine vérifie json_extract(content, json_path) = value,
+    // désérialisé dans le type demandé
+    pub async fn query<T: serde::de::DeserializeOwned>(
+        &self,
+        json_path: &str,
+        value: JsonValue,
+    ) -> Result<Vec<T>> {
+        let mut values = Vec::new();
+        let condition = filter_sql(
+            &NodeFilter {
+                path: json_path.to_string(),
+                op: FilterOp::Eq,
+                value,
+            },
+            &mut values,
+        );
+
+        let sql = format!(
+            "SELECT content FROM (
+                SELECT CASE WHEN json_valid(content) THEN content ELSE '{{}}' END as node,
+                    content, page_id, position
+                FROM blocs
+            )
+            WHERE {}
+            ORDER BY page_id, position",
+            condition
+        );
+
+        let rows = bind_all(sqlx::query(&sql), &values)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let data: String = row.get("content");
+            results.push(serde_json::from_str(&data)?);
+        }
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,367 @@
+// Rappels : créés à la main ou tirés des props de date des blocs ("due",
+// "remind"), gardés en base jusqu'à leur déclenchement. Le planificateur tourne
+// sur tokio et dort jusqu'au prochain rappel ; au redémarrage, les rappels
+// passés pendant l'arrêt sont déclenchés aussitôt, marqués en retard. L'heure
+// vient d'une horloge injectable (ManualClock pour les tests).
+use anyhow::{bail, Result};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::Row;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::database::{now_millis, Database};
+use crate::lexical;
+
+// props de date suivies par défaut
+pub const REMINDER_PROPS: &[&str] = &["due", "remind"];
+// un rappel déclenché plus tard que ça après son heure est signalé en retard
+pub const LATE_AFTER_MS: i64 = 60_000;
+// réveil périodique du planificateur, même sans rappel proche
+pub const MAX_SLEEP_MS: i64 = 60_000;
+// première attente avant de retenter un tour en erreur
+pub const RETRY_MIN_MS: i64 = 1_000;
+const TITLE_CHARS: usize = 120;
+
+pub trait Clock: Send + Sync {
+    // millisecondes depuis l'epoch
+    fn now_millis(&self) -> i64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        now_millis()
+    }
+}
+
+// horloge réglée à la main, partagée entre ses clones
+#[derive(Clone, Default)]
+pub struct ManualClock(Arc<AtomicI64>);
+
+impl ManualClock {
+    pub fn new(now: i64) -> Self {
+        ManualClock(Arc::new(AtomicI64::new(now)))
+    }
+
+    pub fn set(&self, now: i64) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, millis: i64) {
+        self.0.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> i64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct ReminderJson {
+    pub id: String,
+    pub bloc_id: Option<String>,
+    pub page_id: Option<String>,
+    pub title: String,
+    pub fire_at: i64,
+    pub status: String,
+    pub prop_key: Option<String>,
+    pub prop_value: Option<String>,
+    pub snooze_count: i64,
+    pub fired_at: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FiredReminder {
+    #[serde(flatten)]
+    pub reminder: ReminderJson,
+    // déclenché après coup (application fermée à l'heure prévue)
+    pub late: bool,
+}
+
+// "AAAA-MM-JJ" (à `default_time`), "AAAA-MM-JJ HH:MM" ou RFC 3339 ; les dates
+// sans fuseau sont lues dans `offset`
+pub fn parse_reminder_date(value: &str, default_time: NaiveTime, offset: FixedOffset) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.timestamp_millis());
+    }
+    let local = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(|date| date.and_time(default_time)))?;
+    offset.from_local_datetime(&local).single().map(|date| date.timestamp_millis())
+}
+
+fn bloc_title(content: &str) -> String {
+    let text = serde_json::from_str::<JsonValue>(content)
+        .map(|node| lexical::plain_text(&node))
+        .unwrap_or_default();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= TITLE_CHARS {
+        text
+    } else {
+        format!("{}…", text.chars().take(TITLE_CHARS).collect::<String>())
+    }
+}
+
+impl Database {
+    pub async fn new_reminder(
+        &self,
+        bloc_id: Option<String>,
+        page_id: Option<String>,
+        title: String,
+        fire_at: i64,
+    ) -> Result<ReminderJson> {
+        let reminder = sqlx::query_as::<_, ReminderJson>(
+            "INSERT INTO reminders (id, bloc_id, page_id, title, fire_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(bloc_id)
+        .bind(page_id)
+        .bind(title)
+        .bind(fire_at)
+        .bind(now_millis())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(reminder)
+    }
+
+    // rappels à venir ; avec `include_done`, aussi ceux déjà déclenchés ou écartés
+    pub async fn get_reminders(&self, include_done: bool) -> Result<Vec<ReminderJson>> {
+        let reminders = sqlx::query_as::<_, ReminderJson>(
+            "SELECT * FROM reminders WHERE ? OR status = 'pending' ORDER BY fire_at, id",
+        )
+        .bind(include_done)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(reminders)
+    }
+
+    // reporte le rappel (déclenché ou non) à `until`
+    pub async fn snooze_reminder(&self, id: String, until: i64) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            "UPDATE reminders SET status = 'pending', fire_at = ?, fired_at = NULL, snooze_count = snooze_count + 1
+            WHERE id = ?",
+        )
+        .bind(until)
+        .bind(id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn dismiss_reminder(&self, id: String) -> Result<bool> {
+        let rows_affected = sqlx::query("UPDATE reminders SET status = 'dismissed' WHERE id = ? AND status <> 'dismissed'")
+            .bind(id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn delete_reminder(&self, id: String) -> Result<bool> {
+        let rows_affected = sqlx::query("DELETE FROM reminders WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn next_reminder_at(&self) -> Result<Option<i64>> {
+        let next: Option<i64> = sqlx::query("SELECT MIN(fire_at) FROM reminders WHERE status = 'pending'")
+            .fetch_one(&self.pool)
+            .await?
+            .get(0);
+        Ok(next)
+    }
+
+    // passe en "fired" les rappels échus à `now` et les renvoie
+    pub async fn fire_due_reminders(&self, now: i64) -> Result<Vec<FiredReminder>> {
+        let mut tx = self.pool.begin().await?;
+        let due = sqlx::query_as::<_, ReminderJson>(
+            "SELECT * FROM reminders WHERE status = 'pending' AND fire_at <= ? ORDER BY fire_at, id",
+        )
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut fired = Vec::with_capacity(due.len());
+        for mut reminder in due {
+            sqlx::query("UPDATE reminders SET status = 'fired', fired_at = ? WHERE id = ?")
+                .bind(now)
+                .bind(&reminder.id)
+                .execute(&mut *tx)
+                .await?;
+            let late = now - reminder.fire_at > LATE_AFTER_MS;
+            reminder.status = "fired".to_string();
+            reminder.fired_at = Some(now);
+            fired.push(FiredReminder { reminder, late });
+        }
+        tx.commit().await?;
+        Ok(fired)
+    }
+
+    // accorde les rappels aux props de date `keys` : un rappel par bloc et par
+    // clé, recalculé quand la valeur change, retiré avec la prop. Renvoie le
+    // nombre de rappels créés ou replanifiés.
+    pub async fn sync_prop_reminders(&self, keys: &[String], default_time: NaiveTime, offset: FixedOffset) -> Result<usize> {
+        if keys.is_empty() {
+            bail!("no reminder property");
+        }
+        let placeholders = vec!["?"; keys.len()].join(", ");
+        let sql = format!(
+            "SELECT pr.bloc_id, pr.key, pr.value, b.page_id, b.content
+            FROM props pr JOIN blocs b ON b.id = pr.bloc_id
+            WHERE pr.key IN ({})",
+            placeholders
+        );
+        let mut query = sqlx::query(&sql);
+        for key in keys {
+            query = query.bind(key);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let rows = query.fetch_all(&mut *tx).await?;
+        let now = now_millis();
+        let mut kept = HashSet::new();
+        let mut changed = 0;
+
+        for row in rows {
+            let bloc_id: String = row.get("bloc_id");
+            let key: String = row.get("key");
+            let value: String = row.get("value");
+            let Some(fire_at) = parse_reminder_date(&value, default_time, offset) else {
+                continue;
+            };
+            kept.insert((bloc_id.clone(), key.clone()));
+
+            let current: Option<String> = sqlx::query_scalar(
+                "SELECT prop_value FROM reminders WHERE bloc_id = ? AND prop_key = ?",
+            )
+            .bind(&bloc_id)
+            .bind(&key)
+            .fetch_optional(&mut *tx)
+            .await?
+            .flatten();
+            if current.as_deref() == Some(value.as_str()) {
+                continue;
+            }
+
+            // une nouvelle date remet le rappel à zéro
+            sqlx::query(
+                "INSERT INTO reminders (id, bloc_id, page_id, title, fire_at, prop_key, prop_value, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT (bloc_id, prop_key) WHERE prop_key IS NOT NULL DO UPDATE SET
+                    page_id = excluded.page_id, title = excluded.title, fire_at = excluded.fire_at,
+                    prop_value = excluded.prop_value, status = 'pending', fired_at = NULL, snooze_count = 0",
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&bloc_id)
+            .bind(row.get::<String, _>("page_id"))
+            .bind(bloc_title(row.get("content")))
+            .bind(fire_at)
+            .bind(&key)
+            .bind(&value)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            changed += 1;
+        }
+
+        let existing: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT id, bloc_id, prop_key FROM reminders WHERE prop_key IS NOT NULL")
+                .fetch_all(&mut *tx)
+                .await?;
+        for (id, bloc_id, key) in existing {
+            if !kept.contains(&(bloc_id, key)) {
+                sqlx::query("DELETE FROM reminders WHERE id = ?")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(changed)
+    }
+}
+
+pub struct ReminderScheduler {
+    db: Database,
+    clock: Arc<dyn Clock>,
+    wake: Arc<Notify>,
+}
+
+impl ReminderScheduler {
+    pub fn new(db: Database, clock: Arc<dyn Clock>) -> Self {
+        ReminderScheduler { db, clock, wake: Arc::new(Notify::new()) }
+    }
+
+    // à signaler après un ajout ou un report pour recalculer le prochain réveil
+    pub fn waker(&self) -> Arc<Notify> {
+        self.wake.clone()
+    }
+
+    // déclenche les rappels échus à l'heure de l'horloge
+    pub async fn tick(&self) -> Result<Vec<FiredReminder>> {
+        self.db.fire_due_reminders(self.clock.now_millis()).await
+    }
+
+    // boucle du planificateur : `on_fire` reçoit chaque rappel déclenché. Le
+    // premier tour rattrape les rappels manqués pendant l'arrêt. Une erreur
+    // (base verrouillée, disque plein…) est passée à `on_error` puis le tour
+    // est retenté après une attente qui double jusqu'à `MAX_SLEEP_MS`.
+    pub async fn run<F, E>(&self, mut on_fire: F, mut on_error: E)
+    where
+        F: FnMut(FiredReminder),
+        E: FnMut(anyhow::Error),
+    {
+        let mut retry = RETRY_MIN_MS;
+        loop {
+            let delay = match self.turn(&mut on_fire).await {
+                Ok(delay) => {
+                    retry = RETRY_MIN_MS;
+                    delay
+                }
+                Err(e) => {
+                    on_error(e);
+                    let delay = retry;
+                    retry = (retry * 2).min(MAX_SLEEP_MS);
+                    delay
+                }
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(delay as u64)) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+
+    // un tour de boucle : déclenche les rappels échus et renvoie l'attente
+    // avant le prochain
+    async fn turn<F>(&self, on_fire: &mut F) -> Result<i64>
+    where
+        F: FnMut(FiredReminder),
+    {
+        for fired in self.tick().await? {
+            on_fire(fired);
+        }
+        Ok(match self.db.next_reminder_at().await? {
+            Some(at) => (at - self.clock.now_millis()).clamp(0, MAX_SLEEP_MS),
+            None => MAX_SLEEP_MS,
+        })
+    }
+}
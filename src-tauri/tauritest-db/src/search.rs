@@ -0,0 +1,119 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::Row;
+
+use crate::database::Database;
+use crate::lexical;
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub page_id: String,
+    pub page_title: String,
+    pub page_path: String,
+    // None quand c'est le titre de la page qui correspond
+    pub bloc_id: Option<String>,
+    pub excerpt: String,
+}
+
+const EXCERPT_RADIUS: usize = 40;
+
+// (début, fin) en caractères de la première occurrence de `needle`, comparée
+// caractère par caractère en minuscules : to_lowercase peut changer le nombre
+// de caractères ("İ" en donne deux), les indices restent ceux de `chars`
+fn find_ignore_case(chars: &[char], needle: &str) -> Option<(usize, usize)> {
+    let needle = needle.to_lowercase();
+    (0..=chars.len()).find_map(|start| {
+        let mut rest = needle.as_str();
+        let mut end = start;
+        while !rest.is_empty() && end < chars.len() {
+            let lower: String = chars[end].to_lowercase().collect();
+            rest = rest.strip_prefix(lower.as_str())?;
+            end += 1;
+        }
+        rest.is_empty().then_some((start, end))
+    })
+}
+
+// extrait centré sur la première occurrence (insensible à la casse)
+fn excerpt(text: &str, needle: &str) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let (start, end) = find_ignore_case(&chars, needle)?;
+    let from = start.saturating_sub(EXCERPT_RADIUS);
+    let to = (end + EXCERPT_RADIUS).min(chars.len());
+
+    let mut out: String = chars[from..to].iter().collect();
+    out = out.replace('\n', " ");
+    if from > 0 {
+        out = format!("…{}", out);
+    }
+    if to < chars.len() {
+        out.push('…');
+    }
+    Some(out)
+}
+
+impl Database {
+    // recherche plein texte simple sur les titres et le texte des blocs
+    pub async fn search(&self, text: String, limit: usize) -> Result<Vec<SearchHit>> {
+        let mut hits = Vec::new();
+        if text.trim().is_empty() {
+            return Ok(hits);
+        }
+
+        let pages = sqlx::query(
+            "SELECT id, title, path FROM pages
+            WHERE instr(lower(title), lower(?)) > 0
+            ORDER BY updated_at DESC",
+        )
+        .bind(&text)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for row in pages {
+            let title: String = row.get("title");
+            hits.push(SearchHit {
+                page_id: row.get("id"),
+                page_path: row.get("path"),
+                excerpt: title.clone(),
+                page_title: title,
+                bloc_id: None,
+            });
+        }
+
+        // le filtre SQL sur le JSON brut ne sert qu'à réduire les candidats,
+        // la correspondance est vérifiée sur le texte extrait
+        let blocs = sqlx::query(
+            "SELECT b.id, b.content, p.id as page_id, p.title, p.path
+            FROM blocs b
+            JOIN pages p ON p.id = b.page_id
+            WHERE instr(lower(b.content), lower(?)) > 0
+            ORDER BY p.updated_at DESC, b.position",
+        )
+        .bind(&text)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for row in blocs {
+            if hits.len() >= limit {
+                break;
+            }
+            let content: String = row.get("content");
+            let node: serde_json::Value = match serde_json::from_str(&content) {
+                Ok(node) => node,
+                Err(_) => continue,
+            };
+            if let Some(excerpt) = excerpt(&lexical::plain_text(&node), &text) {
+                hits.push(SearchHit {
+                    page_id: row.get("page_id"),
+                    page_title: row.get("title"),
+                    page_path: row.get("path"),
+                    bloc_id: Some(row.get("id")),
+                    excerpt,
+                });
+            }
+        }
+
+        hits.truncate(limit);
+        Ok(hits)
+    }
+}
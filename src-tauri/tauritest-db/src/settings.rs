@@ -0,0 +1,347 @@
+// Réglages typés. Chaque base garde ses valeurs dans la table `settings` : la
+// base de l'espace de travail pour la portée Workspace, une base à part (dans
+// le dossier de configuration) pour la portée Global. La valeur effective est
+// celle de l'espace, sinon la globale, sinon la valeur par défaut.
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sqlx::{Row, Sqlite, Transaction};
+use std::collections::BTreeMap;
+
+use crate::database::{now_millis, Database};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingScope {
+    Global,
+    Workspace,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingSource {
+    Default,
+    Global,
+    Workspace,
+}
+
+#[derive(Debug)]
+pub enum SettingKind {
+    Bool,
+    Integer { min: i64, max: i64 },
+    Choice(&'static [&'static str]),
+    Text,
+}
+
+pub struct SettingDef {
+    pub key: &'static str,
+    pub kind: SettingKind,
+    // valeur par défaut, en JSON
+    pub default: &'static str,
+    // peut être redéfini par espace de travail
+    pub per_workspace: bool,
+}
+
+const fn flag(key: &'static str, default: &'static str) -> SettingDef {
+    SettingDef { key, kind: SettingKind::Bool, default, per_workspace: true }
+}
+
+// les options de l'éditeur reprennent les noms de appSettings.ts
+pub const SETTINGS: &[SettingDef] = &[
+    flag("disableBeforeInput", "false"),
+    flag("emptyEditor", "false"),
+    flag("hasLinkAttributes", "false"),
+    flag("isAutocomplete", "false"),
+    flag("isCharLimit", "false"),
+    flag("isCharLimitUtf8", "false"),
+    flag("isCodeHighlighted", "true"),
+    flag("isCodeShiki", "false"),
+    flag("isCollab", "false"),
+    flag("isMaxLength", "false"),
+    flag("isRichText", "true"),
+    flag("listStrictIndent", "false"),
+    flag("measureTypingPerf", "false"),
+    flag("selectionAlwaysOnDisplay", "false"),
+    flag("shouldAllowHighlightingWithBrackets", "false"),
+    flag("shouldPreserveNewLinesInMarkdown", "false"),
+    flag("shouldUseLexicalContextMenu", "false"),
+    flag("showNestedEditorTreeView", "false"),
+    flag("showTableOfContents", "false"),
+    flag("showTreeView", "true"),
+    flag("tableCellBackgroundColor", "true"),
+    flag("tableCellMerge", "true"),
+    flag("tableHorizontalScroll", "true"),
+    SettingDef {
+        key: "theme",
+        kind: SettingKind::Choice(&["system", "light", "dark"]),
+        default: "\"system\"",
+        per_workspace: false,
+    },
+    SettingDef {
+        key: "autosaveDelayMs",
+        kind: SettingKind::Integer { min: 100, max: 60_000 },
+        default: "1000",
+        per_workspace: true,
+    },
+    SettingDef {
+        key: "journalPath",
+        kind: SettingKind::Text,
+        default: "\"/journal\"",
+        per_workspace: true,
+    },
+    SettingDef {
+        key: "journalTitleFormat",
+        kind: SettingKind::Text,
+        default: "\"%Y-%m-%d\"",
+        per_workspace: true,
+    },
+    flag("journalRollover", "false"),
+];
+
+// Changement de nom d'un réglage entre deux versions du schéma. Comme pour les
+// migrations de la base : ne jamais modifier une étape publiée, en ajouter une.
+pub struct SettingsMigration {
+    pub version: i64,
+    pub description: &'static str,
+    pub renames: &'static [(&'static str, &'static str)],
+}
+
+pub const SETTINGS_MIGRATIONS: &[SettingsMigration] = &[SettingsMigration {
+    version: 1,
+    description: "réglages de l'éditeur",
+    renames: &[],
+}];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SettingValue {
+    pub key: String,
+    pub value: JsonValue,
+    pub source: SettingSource,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SettingsExport {
+    pub version: i64,
+    pub global: BTreeMap<String, JsonValue>,
+    pub workspace: BTreeMap<String, JsonValue>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingsImport {
+    pub applied: Vec<String>,
+    // clés inconnues ou valeurs invalides
+    pub skipped: Vec<String>,
+}
+
+pub fn settings_version() -> i64 {
+    SETTINGS_MIGRATIONS.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+pub fn setting_def(key: &str) -> Result<&'static SettingDef> {
+    SETTINGS
+        .iter()
+        .find(|def| def.key == key)
+        .ok_or_else(|| anyhow!("unknown setting: {}", key))
+}
+
+pub fn default_value(def: &SettingDef) -> JsonValue {
+    serde_json::from_str(def.default).expect("invalid default setting value")
+}
+
+// vérifie la valeur et la portée ; renvoie la valeur à enregistrer
+pub fn validate_setting(key: &str, value: &JsonValue, scope: SettingScope) -> Result<JsonValue> {
+    let def = setting_def(key)?;
+    if scope == SettingScope::Workspace && !def.per_workspace {
+        bail!("setting {} can only be set globally", key);
+    }
+
+    let valid = match &def.kind {
+        SettingKind::Bool => value.is_boolean(),
+        SettingKind::Integer { min, max } => value.as_i64().is_some_and(|n| (*min..=*max).contains(&n)),
+        SettingKind::Choice(choices) => value.as_str().is_some_and(|s| choices.contains(&s)),
+        SettingKind::Text => value.is_string(),
+    };
+    if !valid {
+        bail!("invalid value for setting {} ({:?}): {}", key, def.kind, value);
+    }
+    Ok(value.clone())
+}
+
+// valeurs effectives de tous les réglages
+pub fn resolve_settings(
+    global: &BTreeMap<String, JsonValue>,
+    workspace: &BTreeMap<String, JsonValue>,
+) -> Vec<SettingValue> {
+    SETTINGS
+        .iter()
+        .map(|def| {
+            let (value, source) = if let Some(value) = workspace.get(def.key).filter(|_| def.per_workspace) {
+                (value.clone(), SettingSource::Workspace)
+            } else if let Some(value) = global.get(def.key) {
+                (value.clone(), SettingSource::Global)
+            } else {
+                (default_value(def), SettingSource::Default)
+            };
+            SettingValue { key: def.key.to_string(), value, source }
+        })
+        .collect()
+}
+
+// renomme les clés enregistrées sous une version plus ancienne du schéma
+async fn upgrade_settings(tx: &mut Transaction<'_, Sqlite>) -> Result<()> {
+    let version: i64 = sqlx::query("SELECT value FROM settings_state WHERE key = 'version'")
+        .fetch_one(&mut **tx)
+        .await?
+        .get(0);
+
+    for migration in SETTINGS_MIGRATIONS.iter().filter(|migration| migration.version > version) {
+        for (from, to) in migration.renames {
+            sqlx::query("UPDATE OR REPLACE settings SET key = ? WHERE key = ?")
+                .bind(to)
+                .bind(from)
+                .execute(&mut **tx)
+                .await?;
+        }
+    }
+
+    if version < settings_version() {
+        sqlx::query("UPDATE settings_state SET value = ? WHERE key = 'version'")
+            .bind(settings_version())
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+// renomme les clés d'un export fait sous la version `from_version` du schéma,
+// comme upgrade_settings le fait pour les valeurs enregistrées
+pub fn upgrade_setting_keys(
+    values: &Map<String, JsonValue>,
+    from_version: i64,
+    migrations: &[SettingsMigration],
+) -> Map<String, JsonValue> {
+    let mut values = values.clone();
+    for migration in migrations.iter().filter(|migration| migration.version > from_version) {
+        for (from, to) in migration.renames {
+            if let Some(value) = values.remove(*from) {
+                values.insert(to.to_string(), value);
+            }
+        }
+    }
+    values
+}
+
+async fn store_setting(tx: &mut Transaction<'_, Sqlite>, key: &str, value: &JsonValue) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+    )
+    .bind(key)
+    .bind(serde_json::to_string(value)?)
+    .bind(now_millis())
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+impl Database {
+    // valeurs enregistrées dans cette base ; celles devenues invalides (réglage
+    // retiré, type changé) sont ignorées
+    pub async fn get_stored_settings(&self, scope: SettingScope) -> Result<BTreeMap<String, JsonValue>> {
+        let mut tx = self.pool.begin().await?;
+        upgrade_settings(&mut tx).await?;
+        let rows = sqlx::query("SELECT key, value FROM settings ORDER BY key")
+            .fetch_all(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        let mut values = BTreeMap::new();
+        for row in rows {
+            let key: String = row.get("key");
+            let Ok(value) = serde_json::from_str::<JsonValue>(row.get("value")) else {
+                continue;
+            };
+            if let Ok(value) = validate_setting(&key, &value, scope) {
+                values.insert(key, value);
+            }
+        }
+        Ok(values)
+    }
+
+    pub async fn set_setting(&self, scope: SettingScope, key: String, value: JsonValue) -> Result<JsonValue> {
+        let value = validate_setting(&key, &value, scope)?;
+        let mut tx = self.pool.begin().await?;
+        upgrade_settings(&mut tx).await?;
+        store_setting(&mut tx, &key, &value).await?;
+        tx.commit().await?;
+        Ok(value)
+    }
+
+    // retire la valeur de cette portée (toutes si `key` est None) ; renvoie les
+    // clés retirées
+    pub async fn reset_setting(&self, key: Option<String>) -> Result<Vec<String>> {
+        let rows = match key {
+            Some(key) => {
+                sqlx::query("DELETE FROM settings WHERE key = ? RETURNING key")
+                    .bind(key)
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+            None => {
+                sqlx::query("DELETE FROM settings RETURNING key")
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+        };
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    // import en bloc, ex. les réglages gardés jusqu'ici dans le localStorage ;
+    // `version` est celle de l'export (SettingsExport.version), ses clés
+    // renommées depuis sont migrées avant d'être vérifiées
+    pub async fn import_settings(
+        &self,
+        scope: SettingScope,
+        values: &Map<String, JsonValue>,
+        version: i64,
+    ) -> Result<SettingsImport> {
+        let values = upgrade_setting_keys(values, version, SETTINGS_MIGRATIONS);
+        let mut report = SettingsImport::default();
+        let mut tx = self.pool.begin().await?;
+        upgrade_settings(&mut tx).await?;
+
+        for (key, value) in &values {
+            match validate_setting(key, value, scope) {
+                Ok(value) => {
+                    store_setting(&mut tx, key, &value).await?;
+                    report.applied.push(key.clone());
+                }
+                Err(_) => report.skipped.push(key.clone()),
+            }
+        }
+
+        tx.commit().await?;
+        Ok(report)
+    }
+}
+
+// réglages effectifs ; `workspace` vaut None tant qu'aucun espace n'est ouvert
+pub async fn get_settings(global: &Database, workspace: Option<&Database>) -> Result<Vec<SettingValue>> {
+    let global_values = global.get_stored_settings(SettingScope::Global).await?;
+    let workspace_values = match workspace {
+        Some(workspace) => workspace.get_stored_settings(SettingScope::Workspace).await?,
+        None => BTreeMap::new(),
+    };
+    Ok(resolve_settings(&global_values, &workspace_values))
+}
+
+pub async fn export_settings(global: &Database, workspace: Option<&Database>) -> Result<SettingsExport> {
+    Ok(SettingsExport {
+        version: settings_version(),
+        global: global.get_stored_settings(SettingScope::Global).await?,
+        workspace: match workspace {
+            Some(workspace) => workspace.get_stored_settings(SettingScope::Workspace).await?,
+            None => BTreeMap::new(),
+        },
+    })
+}
@@ -0,0 +1,656 @@
+// Synchronisation pair à pair sur le réseau local. Chaque message est une trame
+// (longueur u32 big-endian puis octets) ; les messages sont en JSON, le contenu
+// des pièces jointes suit son en-tête en trame brute.
+//
+// Déroulement, identique des deux côtés :
+// 1. Hello : identité de l'instance et défi, puis Proof : HMAC du défi du pair
+//    avec le secret d'appairage, puis SyncPoint : dernière synchronisation
+//    connue avec ce pair
+// 2. Changes : pages, blocs et props modifiés depuis ce point, avec les
+//    suppressions tirées du journal des opérations
+// 3. Manifest : pièces jointes présentes
+// 4. Attachment* : fichiers qui manquent au pair, puis Done
+// Un bloc modifié des deux côtés est départagé par updated_at, puis par checksum :
+// les deux instances choisissent le même gagnant. Un bloc ou un fichier dont le
+// checksum ne correspond pas fait échouer la synchronisation, qui sera reprise
+// depuis le même point.
+use anyhow::{anyhow, bail, Result};
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, Sqlite, Transaction};
+use std::collections::hash_map::DefaultHasher;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::time::timeout;
+
+use crate::database::{checksum, now_millis, BlocJson, Database, PageJson, PropsJson};
+use crate::tags::index_hashtags;
+use crate::undo::{begin_untracked, end_untracked};
+
+pub const PROTOCOL_VERSION: u32 = 2;
+
+// une trame plus grande est refusée plutôt que d'allouer n'importe quoi
+const MAX_FRAME: u32 = 256 * 1024 * 1024;
+// limite tant que le pair n'a pas prouvé qu'il connaît le secret : Hello et
+// Proof tiennent en quelques centaines d'octets
+const MAX_HANDSHAKE_FRAME: u32 = 4 * 1024;
+
+// un pair muet plus longtemps est abandonné
+pub const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Default)]
+pub struct SyncOptions {
+    // dossier (à plat) des pièces jointes à échanger
+    pub attachments_dir: Option<PathBuf>,
+    // secret d'appairage, le même des deux côtés ; obligatoire
+    pub secret: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub peer_id: String,
+    // point de départ utilisé pour les changements
+    pub since: i64,
+    pub pages_sent: usize,
+    pub blocs_sent: usize,
+    pub pages_applied: usize,
+    pub blocs_applied: usize,
+    // blocs modifiés des deux côtés depuis la dernière synchronisation
+    pub conflicts: Vec<String>,
+    pub deletions_sent: usize,
+    pub deletions_applied: usize,
+    pub attachments_sent: usize,
+    pub attachments_received: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentInfo {
+    pub name: String,
+    pub size: u64,
+    pub checksum: String,
+}
+
+// page ou bloc supprimé, daté par le journal des opérations
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub id: String,
+    pub deleted_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Hello {
+        protocol_version: u32,
+        instance_id: String,
+        challenge: String,
+    },
+    Proof {
+        proof: Vec<u8>,
+    },
+    SyncPoint {
+        last_sync: i64,
+    },
+    Changes {
+        pages: Vec<PageJson>,
+        blocs: Vec<BlocJson>,
+        props: Vec<PropsJson>,
+        // checksum enregistré de chaque bloc envoyé
+        checksums: HashMap<String, String>,
+        deleted_pages: Vec<Tombstone>,
+        deleted_blocs: Vec<Tombstone>,
+    },
+    Manifest {
+        files: Vec<AttachmentInfo>,
+    },
+    Attachment {
+        name: String,
+        size: u64,
+    },
+    Done,
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, bytes: &[u8]) -> Result<()> {
+    if bytes.len() > MAX_FRAME as usize {
+        bail!("frame too large: {} bytes", bytes.len());
+    }
+    timeout(IO_TIMEOUT, async {
+        writer.write_u32(bytes.len() as u32).await?;
+        writer.write_all(bytes).await
+    })
+    .await
+    .map_err(|_| anyhow!("sync peer timed out"))??;
+    Ok(())
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R, max_len: u32) -> Result<Vec<u8>> {
+    let len = timeout(IO_TIMEOUT, reader.read_u32())
+        .await
+        .map_err(|_| anyhow!("sync peer timed out"))??;
+    if len > max_len {
+        bail!("frame too large: {} bytes", len);
+    }
+    let mut bytes = vec![0; len as usize];
+    timeout(IO_TIMEOUT, reader.read_exact(&mut bytes))
+        .await
+        .map_err(|_| anyhow!("sync peer timed out"))??;
+    Ok(bytes)
+}
+
+async fn send<W: AsyncWrite + Unpin, T: Serialize>(writer: &mut W, message: &T) -> Result<()> {
+    write_frame(writer, &serde_json::to_vec(message)?).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn receive<R: AsyncRead + Unpin, T: DeserializeOwned>(reader: &mut R, max_len: u32) -> Result<T> {
+    Ok(serde_json::from_slice(&read_frame(reader, max_len).await?)?)
+}
+
+fn bytes_checksum(bytes: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish().to_string()
+}
+
+// preuve que `instance_id` connaît le secret, liée au défi du pair
+fn pairing_proof(secret: &str, challenge: &str, instance_id: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(challenge.as_bytes());
+    mac.update(b"\n");
+    mac.update(instance_id.as_bytes());
+    mac
+}
+
+// un nom venu du réseau ne doit pas sortir du dossier des pièces jointes
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\'])
+        && !name.contains('\0')
+}
+
+async fn list_attachments(dir: &Path) -> Result<Vec<AttachmentInfo>> {
+    let mut files = Vec::new();
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+        Err(e) => return Err(e.into()),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        if !entry.file_type().await?.is_file() {
+            continue;
+        }
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        let bytes = tokio::fs::read(entry.path()).await?;
+        files.push(AttachmentInfo {
+            name,
+            size: bytes.len() as u64,
+            checksum: bytes_checksum(&bytes),
+        });
+    }
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+// le bloc distant remplace-t-il le bloc local ? Même règle des deux côtés.
+fn remote_wins(remote_updated_at: i64, remote_key: &str, local_updated_at: i64, local_key: &str) -> bool {
+    (remote_updated_at, remote_key) > (local_updated_at, local_key)
+}
+
+fn page_key(page: &PageJson) -> String {
+    checksum(&format!("{}\n{}", page.path, page.title))
+}
+
+// checksum du contenu, départagé par la place du bloc
+fn bloc_key(checksum: &str, page_id: &str, position: &str, bloc_type: &str) -> String {
+    format!("{}\n{}\n{}\n{}", checksum, page_id, position, bloc_type)
+}
+
+// date de la dernière suppression locale de l'entité, d'après le journal
+async fn deleted_at(tx: &mut Transaction<'_, Sqlite>, entity: &str, id: &str) -> Result<Option<i64>> {
+    let deleted_at = sqlx::query("SELECT MAX(created_at) FROM operations WHERE op = 'delete' AND entity = ? AND entity_id = ?")
+        .bind(entity)
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await?
+        .get(0);
+    Ok(deleted_at)
+}
+
+// supprime la ligne sauf si elle a été modifiée après la suppression distante
+async fn apply_tombstone(tx: &mut Transaction<'_, Sqlite>, table: &str, tombstone: &Tombstone) -> Result<bool> {
+    let deleted = sqlx::query(&format!("DELETE FROM {} WHERE id = ? AND updated_at <= ?", table))
+        .bind(&tombstone.id)
+        .bind(tombstone.deleted_at)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+    if deleted > 0 && table == "blocs" {
+        sqlx::query("DELETE FROM props WHERE bloc_id = ?")
+            .bind(&tombstone.id)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(deleted > 0)
+}
+
+async fn apply_page(tx: &mut Transaction<'_, Sqlite>, page: &PageJson) -> Result<bool> {
+    let local = sqlx::query_as::<_, PageJson>("SELECT * FROM pages WHERE id = ?")
+        .bind(&page.id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    if let Some(local) = local {
+        let (remote_key, local_key) = (page_key(page), page_key(&local));
+        if remote_key == local_key || !remote_wins(page.updated_at, &remote_key, local.updated_at, &local_key) {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM pages WHERE id = ?")
+            .bind(&page.id)
+            .execute(&mut **tx)
+            .await?;
+    } else if deleted_at(tx, "page", page.id.as_deref().unwrap_or_default()).await? >= Some(page.updated_at) {
+        // supprimée ici après la dernière modification distante
+        return Ok(false);
+    }
+
+    sqlx::query(
+        "INSERT INTO pages (id, path, title, cache, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&page.id)
+    .bind(&page.path)
+    .bind(&page.title)
+    .bind(&page.cache)
+    .bind(page.created_at)
+    .bind(page.updated_at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(true)
+}
+
+// (bloc distant appliqué, conflit) ; il y a conflit quand le bloc a aussi
+// changé localement depuis la dernière synchronisation, quel que soit le gagnant
+async fn apply_bloc(
+    tx: &mut Transaction<'_, Sqlite>,
+    bloc: &BlocJson,
+    props: &[PropsJson],
+    since: i64,
+) -> Result<(bool, bool)> {
+    let remote_checksum = checksum(&bloc.content);
+    let local = sqlx::query(
+        "SELECT checksum, page_id, position, bloc_type, content, updated_at FROM blocs WHERE id = ?",
+    )
+    .bind(&bloc.id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let mut conflict = false;
+    let mut before: Option<String> = None;
+    if let Some(local) = local {
+        before = Some(local.get("content"));
+        let local_updated_at: i64 = local.get("updated_at");
+        let local_key = bloc_key(
+            local.get("checksum"),
+            local.get("page_id"),
+            local.get("position"),
+            local.get("bloc_type"),
+        );
+        let remote_key = bloc_key(&remote_checksum, &bloc.page_id, &bloc.position, &bloc.bloc_type);
+
+        if remote_key == local_key {
+            return Ok((false, false));
+        }
+        conflict = local_updated_at > since;
+        if !remote_wins(bloc.updated_at, &remote_key, local_updated_at, &local_key) {
+            return Ok((false, conflict));
+        }
+
+        sqlx::query("DELETE FROM blocs WHERE id = ?")
+            .bind(&bloc.id)
+            .execute(&mut **tx)
+            .await?;
+    } else if deleted_at(tx, "bloc", bloc.id.as_deref().unwrap_or_default()).await? >= Some(bloc.updated_at) {
+        return Ok((false, false));
+    }
+
+    sqlx::query(
+        "INSERT INTO blocs (id, position, content, checksum, page_id, bloc_type, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&bloc.id)
+    .bind(&bloc.position)
+    .bind(&bloc.content)
+    .bind(&remote_checksum)
+    .bind(&bloc.page_id)
+    .bind(&bloc.bloc_type)
+    .bind(bloc.created_at)
+    .bind(bloc.updated_at)
+    .execute(&mut **tx)
+    .await?;
+    if let Some(id) = bloc.id.as_deref() {
+        index_hashtags(tx, id, before.as_deref(), &bloc.content).await?;
+    }
+
+    // les props suivent le bloc gagnant
+    sqlx::query("DELETE FROM props WHERE bloc_id = ?")
+        .bind(&bloc.id)
+        .execute(&mut **tx)
+        .await?;
+    for prop in props.iter().filter(|p| Some(&p.bloc_id) == bloc.id.as_ref()) {
+        sqlx::query("INSERT INTO props (id, key, value, bloc_id) VALUES (?, ?, ?, ?)")
+            .bind(&prop.id)
+            .bind(&prop.key)
+            .bind(&prop.value)
+            .bind(&prop.bloc_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    Ok((true, conflict))
+}
+
+impl Database {
+    // identifiant stable de cette base, créé au premier appel
+    pub async fn instance_id(&self) -> Result<String> {
+        sqlx::query("INSERT OR IGNORE INTO sync_state (key, value) VALUES ('instance_id', ?)")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .execute(&self.pool)
+            .await?;
+
+        let id = sqlx::query("SELECT value FROM sync_state WHERE key = 'instance_id'")
+            .fetch_one(&self.pool)
+            .await?
+            .get(0);
+        Ok(id)
+    }
+
+    // 0 si jamais synchronisé avec ce pair
+    pub async fn last_sync_with(&self, peer_id: &str) -> Result<i64> {
+        let last = sqlx::query("SELECT last_sync_at FROM sync_peers WHERE peer_id = ?")
+            .bind(peer_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get(0))
+            .unwrap_or(0);
+        Ok(last)
+    }
+
+    // suppressions postérieures à `since` d'entités qui n'existent plus
+    async fn tombstones_since(&self, entity: &str, table: &str, since: i64) -> Result<Vec<Tombstone>> {
+        let tombstones = sqlx::query_as::<_, Tombstone>(&format!(
+            "SELECT entity_id AS id, MAX(created_at) AS deleted_at FROM operations
+            WHERE op = 'delete' AND entity = ? AND created_at > ? AND entity_id NOT IN (SELECT id FROM {})
+            GROUP BY entity_id ORDER BY entity_id",
+            table
+        ))
+        .bind(entity)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tombstones)
+    }
+
+    async fn bloc_checksums_since(&self, since: i64) -> Result<HashMap<String, String>> {
+        let rows = sqlx::query("SELECT id, checksum FROM blocs WHERE updated_at > ?")
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| (row.get("id"), row.get("checksum"))).collect())
+    }
+
+    async fn rows_changed_since(&self, since: i64) -> Result<(Vec<PageJson>, Vec<BlocJson>, Vec<PropsJson>)> {
+        let pages = sqlx::query_as::<_, PageJson>("SELECT * FROM pages WHERE updated_at > ? ORDER BY id")
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+        let blocs = sqlx::query_as::<_, BlocJson>("SELECT * FROM blocs WHERE updated_at > ? ORDER BY id")
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+        let props = sqlx::query_as::<_, PropsJson>(
+            "SELECT * FROM props WHERE bloc_id IN (SELECT id FROM blocs WHERE updated_at > ?) ORDER BY id",
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((pages, blocs, props))
+    }
+
+    // applique les changements du pair en une transaction ; rien n'est écrit
+    // si un bloc ne correspond pas à son checksum
+    async fn apply_changes(&self, changes: Message, since: i64, report: &mut SyncReport) -> Result<()> {
+        let Message::Changes { pages, blocs, props, checksums, deleted_pages, deleted_blocs } = changes else {
+            bail!("expected changes, got {:?}", changes);
+        };
+        for bloc in &blocs {
+            let id = bloc.id.clone().unwrap_or_default();
+            if checksums.get(&id) != Some(&checksum(&bloc.content)) {
+                bail!("bloc {} does not match its checksum", id);
+            }
+        }
+
+        // les changements du pair ne sont pas des étapes à annuler
+        let mut tx = self.pool.begin().await?;
+        begin_untracked(&mut tx).await?;
+
+        for page in &pages {
+            if apply_page(&mut tx, page).await? {
+                report.pages_applied += 1;
+            }
+        }
+        for bloc in &blocs {
+            let (applied, conflict) = apply_bloc(&mut tx, bloc, &props, since).await?;
+            if applied {
+                report.blocs_applied += 1;
+            }
+            if conflict {
+                report.conflicts.extend(bloc.id.clone());
+            }
+        }
+        for tombstone in &deleted_blocs {
+            if apply_tombstone(&mut tx, "blocs", tombstone).await? {
+                report.deletions_applied += 1;
+            }
+        }
+        for tombstone in &deleted_pages {
+            if apply_tombstone(&mut tx, "pages", tombstone).await? {
+                report.deletions_applied += 1;
+            }
+        }
+
+        end_untracked(&mut tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // déroule le protocole sur une connexion déjà ouverte (TCP ou autre)
+    pub async fn sync_over<S>(&self, stream: S, options: &SyncOptions) -> Result<SyncReport>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        if options.secret.is_empty() {
+            bail!("a pairing secret is required to sync");
+        }
+        let started_at = now_millis();
+        let (mut reader, mut writer) = tokio::io::split(stream);
+
+        let instance_id = self.instance_id().await?;
+        let challenge = uuid::Uuid::new_v4().to_string();
+        send(
+            &mut writer,
+            &Message::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                instance_id: instance_id.clone(),
+                challenge: challenge.clone(),
+            },
+        )
+        .await?;
+        let (peer_id, peer_challenge) = match receive(&mut reader, MAX_HANDSHAKE_FRAME).await? {
+            Message::Hello { protocol_version, instance_id: peer_id, challenge } => {
+                if protocol_version != PROTOCOL_VERSION {
+                    bail!("unsupported sync protocol version {}", protocol_version);
+                }
+                (peer_id, challenge)
+            }
+            other => bail!("expected hello, got {:?}", other),
+        };
+        if peer_id == instance_id {
+            bail!("cannot sync a database with itself");
+        }
+
+        // chacun prouve qu'il connaît le secret sans l'envoyer
+        let proof = pairing_proof(&options.secret, &peer_challenge, &instance_id).finalize().into_bytes().to_vec();
+        send(&mut writer, &Message::Proof { proof }).await?;
+        match receive(&mut reader, MAX_HANDSHAKE_FRAME).await? {
+            Message::Proof { proof } => pairing_proof(&options.secret, &challenge, &peer_id)
+                .verify_slice(&proof)
+                .map_err(|_| anyhow!("peer {} does not know the pairing secret", peer_id))?,
+            other => bail!("expected proof, got {:?}", other),
+        }
+
+        // chacun annonce son point de synchronisation, le plus ancien fait foi
+        let own_last_sync = self.last_sync_with(&peer_id).await?;
+        send(&mut writer, &Message::SyncPoint { last_sync: own_last_sync }).await?;
+        let since = match receive(&mut reader, MAX_FRAME).await? {
+            Message::SyncPoint { last_sync } => own_last_sync.min(last_sync),
+            other => bail!("expected sync point, got {:?}", other),
+        };
+
+        let mut report = SyncReport {
+            peer_id: peer_id.clone(),
+            since,
+            ..Default::default()
+        };
+
+        let (pages, blocs, props) = self.rows_changed_since(since).await?;
+        let checksums = self.bloc_checksums_since(since).await?;
+        let deleted_pages = self.tombstones_since("page", "pages", since).await?;
+        let deleted_blocs = self.tombstones_since("bloc", "blocs", since).await?;
+        report.pages_sent = pages.len();
+        report.blocs_sent = blocs.len();
+        report.deletions_sent = deleted_pages.len() + deleted_blocs.len();
+        let own_files = match &options.attachments_dir {
+            Some(dir) => list_attachments(dir).await?,
+            None => Vec::new(),
+        };
+
+        // envoi et réception en parallèle : les deux pairs écrivent en même temps
+        let outgoing = async {
+            let changes = Message::Changes { pages, blocs, props, checksums, deleted_pages, deleted_blocs };
+            send(&mut writer, &changes).await?;
+            send(&mut writer, &Message::Manifest { files: own_files.clone() }).await?;
+            anyhow::Ok(())
+        };
+        let incoming = async {
+            let changes = receive::<_, Message>(&mut reader, MAX_FRAME).await?;
+            let manifest = receive::<_, Message>(&mut reader, MAX_FRAME).await?;
+            anyhow::Ok((changes, manifest))
+        };
+        let ((), (changes, manifest)) = tokio::try_join!(outgoing, incoming)?;
+
+        self.apply_changes(changes, since, &mut report).await?;
+        let peer_files: HashMap<String, AttachmentInfo> = match manifest {
+            Message::Manifest { files } => files.into_iter().map(|f| (f.name.clone(), f)).collect(),
+            other => bail!("expected manifest, got {:?}", other),
+        };
+
+        // fichiers absents chez le pair ; un même nom n'est jamais écrasé
+        let to_send: Vec<&AttachmentInfo> = own_files
+            .iter()
+            .filter(|f| !peer_files.contains_key(&f.name))
+            .collect();
+        report.attachments_sent = to_send.len();
+
+        let outgoing = async {
+            if let Some(dir) = &options.attachments_dir {
+                for file in &to_send {
+                    let bytes = tokio::fs::read(dir.join(&file.name)).await?;
+                    send(
+                        &mut writer,
+                        &Message::Attachment {
+                            name: file.name.clone(),
+                            size: bytes.len() as u64,
+                        },
+                    )
+                    .await?;
+                    write_frame(&mut writer, &bytes).await?;
+                }
+            }
+            send(&mut writer, &Message::Done).await?;
+            anyhow::Ok(())
+        };
+        let incoming = async {
+            let mut received = 0;
+            loop {
+                match receive::<_, Message>(&mut reader, MAX_FRAME).await? {
+                    Message::Attachment { name, size } => {
+                        let bytes = read_frame(&mut reader, MAX_FRAME).await?;
+                        if bytes.len() as u64 != size {
+                            bail!("attachment {} truncated", name);
+                        }
+                        if !is_safe_name(&name) {
+                            bail!("invalid attachment name: {}", name);
+                        }
+                        // le fichier doit être celui annoncé dans le manifeste
+                        match peer_files.get(&name) {
+                            Some(info) if info.size == size && info.checksum == bytes_checksum(&bytes) => {}
+                            _ => bail!("attachment {} does not match the peer's manifest", name),
+                        }
+                        if let Some(dir) = &options.attachments_dir {
+                            let path = dir.join(&name);
+                            if tokio::fs::try_exists(&path).await? {
+                                continue;
+                            }
+                            tokio::fs::create_dir_all(dir).await?;
+                            tokio::fs::write(path, bytes).await?;
+                            received += 1;
+                        }
+                    }
+                    Message::Done => break,
+                    other => bail!("expected attachment, got {:?}", other),
+                }
+            }
+            anyhow::Ok(received)
+        };
+        let ((), received) = tokio::try_join!(outgoing, incoming)?;
+        report.attachments_received = received;
+
+        sqlx::query(
+            "INSERT INTO sync_peers (peer_id, last_sync_at) VALUES (?, ?)
+            ON CONFLICT(peer_id) DO UPDATE SET last_sync_at = excluded.last_sync_at",
+        )
+        .bind(&peer_id)
+        .bind(started_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(report)
+    }
+
+    pub async fn sync_with_peer<A: ToSocketAddrs>(&self, address: A, options: &SyncOptions) -> Result<SyncReport> {
+        let stream = TcpStream::connect(address).await?;
+        stream.set_nodelay(true)?;
+        self.sync_over(stream, options).await
+    }
+
+    // attend un pair et synchronise avec lui
+    pub async fn accept_sync(&self, listener: &TcpListener, options: &SyncOptions) -> Result<SyncReport> {
+        let (stream, _) = listener.accept().await?;
+        stream.set_nodelay(true)?;
+        self.sync_over(stream, options).await
+    }
+}
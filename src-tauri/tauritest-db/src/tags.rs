@@ -0,0 +1,505 @@
+// Étiquettes communes aux pages, blocs, événements de l'agenda et cartes kanban.
+// La hiérarchie suit le nom : "project/alpha" est rangée sous "project", créée
+// au besoin. Événements et cartes vivent côté front : seules leurs ids sont
+// connues ici. Renommer ou fusionner une étiquette réécrit aussi les hashtags
+// des blocs qui la portent, hors du journal d'annulation qui ne suit pas les
+// étiquettes. Les hashtags d'un bloc lui sont attribués à chaque écriture.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{Row, Sqlite, Transaction};
+
+use crate::database::{checksum, now_millis, Database};
+use crate::undo::{begin_untracked, end_untracked};
+
+// l'étiquette `?1` et ses descendantes (les noms sont insensibles à la casse)
+const SUBTREE: &str = "(name = ?1 OR substr(name, 1, length(?1) + 1) = (?1 || '/') COLLATE NOCASE)";
+
+const SELECT_TAGS: &str = "SELECT t.id, t.name, t.color, t.created_at,
+    (SELECT COUNT(*) FROM taggings g WHERE g.tag_id = t.id) AS usage
+    FROM tags t";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagEntity {
+    Page,
+    Bloc,
+    Event,
+    Card,
+}
+
+impl TagEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagEntity::Page => "page",
+            TagEntity::Bloc => "bloc",
+            TagEntity::Event => "event",
+            TagEntity::Card => "card",
+        }
+    }
+
+    fn parse(entity: &str) -> Result<Self> {
+        Ok(match entity {
+            "page" => TagEntity::Page,
+            "bloc" => TagEntity::Bloc,
+            "event" => TagEntity::Event,
+            "card" => TagEntity::Card,
+            other => bail!("unknown tagged entity: {}", other),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct TagJson {
+    pub id: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: i64,
+    // nombre d'éléments qui portent l'étiquette (sans les descendantes)
+    pub usage: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaggedItem {
+    pub entity: TagEntity,
+    pub entity_id: String,
+    // étiquette portée, une descendante de celle demandée le cas échéant
+    pub tag: String,
+}
+
+// "#Project / alpha/" -> "Project/alpha"
+pub fn normalize_tag_name(name: &str) -> Result<String> {
+    let name = name.trim().trim_start_matches('#');
+    let levels: Vec<&str> = name.split('/').map(str::trim).filter(|level| !level.is_empty()).collect();
+    if levels.is_empty() {
+        bail!("empty tag name");
+    }
+    Ok(levels.join("/"))
+}
+
+fn in_subtree(name: &str, root: &str) -> bool {
+    name.eq_ignore_ascii_case(root)
+        || (name.len() > root.len()
+            && name.as_bytes()[root.len()] == b'/'
+            && name.is_char_boundary(root.len())
+            && name[..root.len()].eq_ignore_ascii_case(root))
+}
+
+// crée l'étiquette et ses ancêtres s'ils manquent ; renvoie son id
+async fn ensure_tag(tx: &mut Transaction<'_, Sqlite>, name: &str, color: Option<&str>) -> Result<String> {
+    let now = now_millis();
+    let mut prefix = String::new();
+    for level in name.split('/') {
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+        prefix.push_str(level);
+        let leaf = prefix.len() == name.len();
+        sqlx::query("INSERT OR IGNORE INTO tags (id, name, color, created_at) VALUES (?, ?, ?, ?)")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(&prefix)
+            .bind(if leaf { color } else { None })
+            .bind(now)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    let id = sqlx::query("SELECT id FROM tags WHERE name = ?")
+        .bind(name)
+        .fetch_one(&mut **tx)
+        .await?
+        .get(0);
+    Ok(id)
+}
+
+async fn tag_name(tx: &mut Transaction<'_, Sqlite>, id: &str) -> Result<String> {
+    match sqlx::query("SELECT name FROM tags WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await?
+    {
+        Some(row) => Ok(row.get(0)),
+        None => bail!("tag not found: {}", id),
+    }
+}
+
+// remplace "#from" et "#from/..." par "#to" et "#to/..." dans les hashtags
+fn rewrite_hashtags(node: &mut JsonValue, from: &str, to: &str) -> bool {
+    let mut changed = false;
+    if node.get("type").and_then(JsonValue::as_str) == Some("hashtag") {
+        if let Some(JsonValue::String(text)) = node.get_mut("text") {
+            if let Some(name) = text.strip_prefix('#') {
+                if in_subtree(name, from) {
+                    *text = format!("#{}{}", to, &name[from.len()..]);
+                    changed = true;
+                }
+            }
+        }
+    }
+    if let Some(JsonValue::Array(children)) = node.get_mut("children") {
+        for child in children {
+            changed |= rewrite_hashtags(child, from, to);
+        }
+    }
+    changed
+}
+
+// noms normalisés des hashtags d'un contenu lexical, sans doublon
+fn hashtags(content: &str) -> Vec<String> {
+    fn walk(node: &JsonValue, names: &mut Vec<String>) {
+        if node.get("type").and_then(JsonValue::as_str) == Some("hashtag") {
+            if let Some(Ok(name)) = node.get("text").and_then(JsonValue::as_str).map(normalize_tag_name) {
+                if !names.iter().any(|known| known.eq_ignore_ascii_case(&name)) {
+                    names.push(name);
+                }
+            }
+        }
+        if let Some(JsonValue::Array(children)) = node.get("children") {
+            for child in children {
+                walk(child, names);
+            }
+        }
+    }
+
+    let mut names = Vec::new();
+    if let Ok(node) = serde_json::from_str::<JsonValue>(content) {
+        walk(&node, &mut names);
+    }
+    names
+}
+
+// tient à jour les attributions d'un bloc d'après ses hashtags : celles des
+// hashtags retirés depuis `before` disparaissent, les autres sont ajoutées ;
+// une étiquette posée à la main sur le bloc reste
+pub(crate) async fn index_hashtags(
+    tx: &mut Transaction<'_, Sqlite>,
+    bloc_id: &str,
+    before: Option<&str>,
+    after: &str,
+) -> Result<()> {
+    let added = hashtags(after);
+    for name in before.map(hashtags).unwrap_or_default() {
+        if added.iter().any(|known| known.eq_ignore_ascii_case(&name)) {
+            continue;
+        }
+        sqlx::query(
+            "DELETE FROM taggings
+            WHERE entity = 'bloc' AND entity_id = ? AND tag_id = (SELECT id FROM tags WHERE name = ?)",
+        )
+        .bind(bloc_id)
+        .bind(&name)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    let now = now_millis();
+    for name in &added {
+        let tag_id = ensure_tag(tx, name, None).await?;
+        sqlx::query("INSERT OR IGNORE INTO taggings (tag_id, entity, entity_id, created_at) VALUES (?, 'bloc', ?, ?)")
+            .bind(&tag_id)
+            .bind(bloc_id)
+            .bind(now)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+// déplace l'arbre `from` sous le nom `to` ; si `merge`, une étiquette qui existe
+// déjà sous le nouveau nom récupère les éléments de l'ancienne
+async fn move_tree(tx: &mut Transaction<'_, Sqlite>, from: &str, to: &str, merge: bool) -> Result<()> {
+    if in_subtree(to, from) && !to.eq_ignore_ascii_case(from) {
+        bail!("cannot move tag {} under itself", from);
+    }
+
+    let bloc_ids: Vec<String> = sqlx::query(&format!(
+        "SELECT DISTINCT g.entity_id FROM taggings g JOIN tags t ON t.id = g.tag_id
+        WHERE g.entity = 'bloc' AND t.id IN (SELECT id FROM tags WHERE {})",
+        SUBTREE
+    ))
+    .bind(from)
+    .fetch_all(&mut **tx)
+    .await?
+    .iter()
+    .map(|row| row.get(0))
+    .collect();
+
+    let tree = sqlx::query(&format!("SELECT id, name FROM tags WHERE {} ORDER BY length(name)", SUBTREE))
+        .bind(from)
+        .fetch_all(&mut **tx)
+        .await?;
+    for row in tree {
+        let id: String = row.get("id");
+        let name: String = row.get("name");
+        let new_name = format!("{}{}", to, &name[from.len()..]);
+
+        let existing: Option<String> = sqlx::query("SELECT id FROM tags WHERE name = ? AND id != ?")
+            .bind(&new_name)
+            .bind(&id)
+            .fetch_optional(&mut **tx)
+            .await?
+            .map(|row| row.get(0));
+        match existing {
+            Some(_) if !merge => bail!("tag already exists: {}", new_name),
+            Some(target) => {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO taggings (tag_id, entity, entity_id, created_at)
+                    SELECT ?, entity, entity_id, created_at FROM taggings WHERE tag_id = ?",
+                )
+                .bind(&target)
+                .bind(&id)
+                .execute(&mut **tx)
+                .await?;
+                sqlx::query("DELETE FROM taggings WHERE tag_id = ?")
+                    .bind(&id)
+                    .execute(&mut **tx)
+                    .await?;
+                sqlx::query("DELETE FROM tags WHERE id = ?")
+                    .bind(&id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            None => {
+                sqlx::query("UPDATE tags SET name = ? WHERE id = ?")
+                    .bind(&new_name)
+                    .bind(&id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+        }
+    }
+
+    // "alpha" renommée en "project/alpha" : "project" doit exister
+    if let Some((parent, _)) = to.rsplit_once('/') {
+        ensure_tag(tx, parent, None).await?;
+    }
+
+    let now = now_millis();
+    for bloc_id in bloc_ids {
+        let content: Option<String> = sqlx::query("SELECT content FROM blocs WHERE id = ?")
+            .bind(&bloc_id)
+            .fetch_optional(&mut **tx)
+            .await?
+            .map(|row| row.get(0));
+        let Some(mut node) = content.and_then(|content| serde_json::from_str::<JsonValue>(&content).ok()) else {
+            continue;
+        };
+        if !rewrite_hashtags(&mut node, from, to) {
+            continue;
+        }
+        let content = serde_json::to_string(&node)?;
+        sqlx::query("UPDATE blocs SET content = ?, checksum = ?, updated_at = ? WHERE id = ?")
+            .bind(&content)
+            .bind(checksum(&content))
+            .bind(now)
+            .bind(&bloc_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    Ok(())
+}
+
+impl Database {
+    // crée l'étiquette (et ses ancêtres) ; renvoie l'existante si le nom est pris
+    pub async fn new_tag(&self, name: String, color: Option<String>) -> Result<TagJson> {
+        let name = normalize_tag_name(&name)?;
+        let mut tx = self.pool.begin().await?;
+        let id = ensure_tag(&mut tx, &name, color.as_deref()).await?;
+        let tag = sqlx::query_as::<_, TagJson>(&format!("{} WHERE t.id = ?", SELECT_TAGS))
+            .bind(&id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(tag)
+    }
+
+    pub async fn update_tag_color(&self, id: String, color: Option<String>) -> Result<bool> {
+        let rows_affected = sqlx::query("UPDATE tags SET color = ? WHERE id = ?")
+            .bind(color)
+            .bind(id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    // supprime l'étiquette, ses descendantes et leurs attributions ; renvoie
+    // le nombre d'étiquettes supprimées
+    pub async fn delete_tag(&self, id: String) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let name = tag_name(&mut tx, &id).await?;
+
+        sqlx::query(&format!(
+            "DELETE FROM taggings WHERE tag_id IN (SELECT id FROM tags WHERE {})",
+            SUBTREE
+        ))
+        .bind(&name)
+        .execute(&mut *tx)
+        .await?;
+        let rows_affected = sqlx::query(&format!("DELETE FROM tags WHERE {}", SUBTREE))
+            .bind(&name)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(rows_affected)
+    }
+
+    // toutes les étiquettes, les parents avant leurs enfants
+    pub async fn get_tags(&self) -> Result<Vec<TagJson>> {
+        let tags = sqlx::query_as::<_, TagJson>(&format!("{} ORDER BY t.name", SELECT_TAGS))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(tags)
+    }
+
+    pub async fn tag_entity(&self, entity: TagEntity, entity_id: String, name: String) -> Result<bool> {
+        let name = normalize_tag_name(&name)?;
+        let mut tx = self.pool.begin().await?;
+        let tag_id = ensure_tag(&mut tx, &name, None).await?;
+        let rows_affected = sqlx::query(
+            "INSERT OR IGNORE INTO taggings (tag_id, entity, entity_id, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&tag_id)
+        .bind(entity.as_str())
+        .bind(&entity_id)
+        .bind(now_millis())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn untag_entity(&self, entity: TagEntity, entity_id: String, name: String) -> Result<bool> {
+        let name = normalize_tag_name(&name)?;
+        let rows_affected = sqlx::query(
+            "DELETE FROM taggings
+            WHERE entity = ? AND entity_id = ? AND tag_id = (SELECT id FROM tags WHERE name = ?)",
+        )
+        .bind(entity.as_str())
+        .bind(entity_id)
+        .bind(name)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    // remplace les étiquettes d'un élément, ex. le tableau `tags` d'une carte
+    pub async fn set_entity_tags(&self, entity: TagEntity, entity_id: String, names: &[String]) -> Result<Vec<TagJson>> {
+        let names = names
+            .iter()
+            .map(|name| normalize_tag_name(name))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM taggings WHERE entity = ? AND entity_id = ?")
+            .bind(entity.as_str())
+            .bind(&entity_id)
+            .execute(&mut *tx)
+            .await?;
+        let now = now_millis();
+        for name in &names {
+            let tag_id = ensure_tag(&mut tx, name, None).await?;
+            sqlx::query(
+                "INSERT OR IGNORE INTO taggings (tag_id, entity, entity_id, created_at) VALUES (?, ?, ?, ?)",
+            )
+            .bind(&tag_id)
+            .bind(entity.as_str())
+            .bind(&entity_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        self.get_entity_tags(entity, entity_id).await
+    }
+
+    pub async fn get_entity_tags(&self, entity: TagEntity, entity_id: String) -> Result<Vec<TagJson>> {
+        let tags = sqlx::query_as::<_, TagJson>(&format!(
+            "{} JOIN taggings g ON g.tag_id = t.id WHERE g.entity = ? AND g.entity_id = ? ORDER BY t.name",
+            SELECT_TAGS
+        ))
+        .bind(entity.as_str())
+        .bind(entity_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tags)
+    }
+
+    // éléments qui portent l'étiquette (ou une descendante) ; les pages et blocs
+    // supprimés depuis sont ignorés
+    pub async fn get_tagged(&self, name: String, include_descendants: bool) -> Result<Vec<TaggedItem>> {
+        let name = normalize_tag_name(&name)?;
+        let rows = sqlx::query(
+            "SELECT g.entity, g.entity_id, t.name FROM taggings g JOIN tags t ON t.id = g.tag_id
+            WHERE (t.name = ?1 OR (?2 AND substr(t.name, 1, length(?1) + 1) = (?1 || '/') COLLATE NOCASE))
+            AND CASE g.entity
+                WHEN 'page' THEN EXISTS (SELECT 1 FROM pages p WHERE p.id = g.entity_id)
+                WHEN 'bloc' THEN EXISTS (SELECT 1 FROM blocs b WHERE b.id = g.entity_id)
+                ELSE 1
+            END
+            ORDER BY g.entity, g.created_at, g.entity_id",
+        )
+        .bind(&name)
+        .bind(include_descendants)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(TaggedItem {
+                    entity: TagEntity::parse(row.get("entity"))?,
+                    entity_id: row.get("entity_id"),
+                    tag: row.get("name"),
+                })
+            })
+            .collect()
+    }
+
+    // renomme l'étiquette et ses descendantes ; erreur si le nom est déjà pris
+    pub async fn rename_tag(&self, id: String, new_name: String) -> Result<TagJson> {
+        let new_name = normalize_tag_name(&new_name)?;
+        let mut tx = self.pool.begin().await?;
+        begin_untracked(&mut tx).await?;
+
+        let name = tag_name(&mut tx, &id).await?;
+        move_tree(&mut tx, &name, &new_name, false).await?;
+
+        end_untracked(&mut tx).await?;
+        let tag = sqlx::query_as::<_, TagJson>(&format!("{} WHERE t.id = ?", SELECT_TAGS))
+            .bind(&id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(tag)
+    }
+
+    // fond `source_id` (et ses descendantes) dans `target_id`
+    pub async fn merge_tags(&self, source_id: String, target_id: String) -> Result<TagJson> {
+        let mut tx = self.pool.begin().await?;
+        begin_untracked(&mut tx).await?;
+
+        let source = tag_name(&mut tx, &source_id).await?;
+        let target = tag_name(&mut tx, &target_id).await?;
+        if source_id == target_id {
+            bail!("cannot merge tag {} into itself", source);
+        }
+        move_tree(&mut tx, &source, &target, true).await?;
+
+        end_untracked(&mut tx).await?;
+        let tag = sqlx::query_as::<_, TagJson>(&format!("{} WHERE t.id = ?", SELECT_TAGS))
+            .bind(&target_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(tag)
+    }
+}
@@ -0,0 +1,388 @@
+// Modèles de pages : une page marquée comme modèle est recopiée (blocs et props,
+// avec de nouveaux ids et positions) en remplaçant les variables {{nom}} dans
+// le titre et les noeuds texte. Variables fournies d'office : date, title, weekday ;
+// les autres sont demandées à l'utilisateur (template_variables). Une variable
+// sans valeur reste telle quelle.
+use anyhow::{bail, Result};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{Row, Sqlite, Transaction};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::database::{checksum, now_millis, BlocJson, Database, PropsJson};
+use crate::fractional_index;
+use crate::lexical;
+use crate::tags::index_hashtags;
+use crate::undo::{begin_undo_group, begin_untracked, end_undo_group, end_untracked};
+
+pub const BUILTIN_VARIABLES: &[&str] = &["date", "title", "weekday"];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TemplateJson {
+    pub page_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    // chemin des pages créées par récurrence
+    #[serde(default = "default_target_path")]
+    pub target_path: String,
+    // titre des pages créées par récurrence, ex. "Journal {{date}}" ; {{title}}
+    // n'y a pas de sens (ce serait le motif lui-même)
+    #[serde(default = "default_title_pattern")]
+    pub title_pattern: String,
+    #[serde(default)]
+    pub last_run: Option<String>,
+}
+
+fn default_target_path() -> String {
+    "/".to_string()
+}
+
+fn default_title_pattern() -> String {
+    "{{date}}".to_string()
+}
+
+// appelle `found` pour chaque {{nom}} du texte ; renvoie le texte remplacé
+fn replace_variables(text: &str, found: &mut dyn FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let raw = &rest[start..start + 2 + len + 2];
+        let name = rest[start + 2..start + 2 + len].trim();
+        out.push_str(&rest[..start]);
+        match found(name) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(raw),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn substitute(text: &str, variables: &HashMap<String, String>) -> String {
+    replace_variables(text, &mut |name| variables.get(name).cloned())
+}
+
+fn variable_names(text: &str, names: &mut BTreeSet<String>) {
+    replace_variables(text, &mut |name| {
+        if !name.is_empty() {
+            names.insert(name.to_string());
+        }
+        None
+    });
+}
+
+// remplace dans les noeuds texte, à toute profondeur
+fn substitute_node(node: &mut JsonValue, variables: &HashMap<String, String>) {
+    if let Some(JsonValue::String(text)) = node.get_mut("text") {
+        *text = substitute(text, variables);
+    }
+    if let Some(JsonValue::Array(children)) = node.get_mut("children") {
+        for child in children {
+            substitute_node(child, variables);
+        }
+    }
+}
+
+fn builtin_variables(title: &str, date: NaiveDate) -> HashMap<String, String> {
+    HashMap::from([
+        ("date".to_string(), date.format("%Y-%m-%d").to_string()),
+        ("weekday".to_string(), date.format("%A").to_string()),
+        ("title".to_string(), title.to_string()),
+    ])
+}
+
+async fn fetch_template(tx: &mut Transaction<'_, Sqlite>, page_id: &str) -> Result<TemplateJson> {
+    let row = sqlx::query("SELECT * FROM templates WHERE page_id = ?")
+        .bind(page_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+    match row {
+        Some(row) => template_from_row(&row),
+        None => bail!("page {} is not a template", page_id),
+    }
+}
+
+fn template_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<TemplateJson> {
+    let recurrence: Option<String> = row.get("recurrence");
+    Ok(TemplateJson {
+        page_id: row.get("page_id"),
+        name: row.get("name"),
+        recurrence: match recurrence.as_deref() {
+            None => None,
+            Some(value) => Some(serde_json::from_value(JsonValue::String(value.to_string()))?),
+        },
+        target_path: row.get("target_path"),
+        title_pattern: row.get("title_pattern"),
+        last_run: row.get("last_run"),
+    })
+}
+
+// copie la page modèle dans la transaction ; renvoie l'id de la nouvelle page
+pub(crate) async fn instantiate_in(
+    tx: &mut Transaction<'_, Sqlite>,
+    template_id: &str,
+    path: &str,
+    title: &str,
+    variables: &HashMap<String, String>,
+    date: NaiveDate,
+) -> Result<String> {
+    fetch_template(tx, template_id).await?;
+
+    let mut all = builtin_variables(title, date);
+    for (name, value) in variables {
+        all.insert(name.clone(), value.clone());
+    }
+    let title = substitute(title, &all);
+    all.insert("title".to_string(), title.clone());
+
+    let now = now_millis();
+    let page_id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO pages (id, path, title, cache, created_at, updated_at)
+        VALUES (?, ?, ?, '', ?, ?)",
+    )
+    .bind(&page_id)
+    .bind(path)
+    .bind(&title)
+    .bind(now)
+    .bind(now)
+    .execute(&mut **tx)
+    .await?;
+
+    let blocs = sqlx::query_as::<_, BlocJson>(
+        "SELECT * FROM blocs WHERE page_id = ? ORDER BY position, created_at, id",
+    )
+    .bind(template_id)
+    .fetch_all(&mut **tx)
+    .await?;
+    let positions = fractional_index::generate_n_keys_between(None, None, blocs.len())?;
+
+    for (bloc, position) in blocs.iter().zip(positions) {
+        let bloc_id = uuid::Uuid::new_v4().to_string();
+        let content = match serde_json::from_str::<JsonValue>(&bloc.content) {
+            Ok(mut node) => {
+                substitute_node(&mut node, &all);
+                if node.get("$").is_some() {
+                    lexical::set_bloc_state(&mut node, &bloc_id, &position);
+                }
+                serde_json::to_string(&node)?
+            }
+            // contenu illisible : recopié tel quel
+            Err(_) => bloc.content.clone(),
+        };
+
+        sqlx::query(
+            "INSERT INTO blocs (id, position, content, checksum, page_id, bloc_type, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&bloc_id)
+        .bind(&position)
+        .bind(&content)
+        .bind(checksum(&content))
+        .bind(&page_id)
+        .bind(&bloc.bloc_type)
+        .bind(now)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+        index_hashtags(tx, &bloc_id, None, &content).await?;
+
+        let props = sqlx::query_as::<_, PropsJson>("SELECT * FROM props WHERE bloc_id = ? ORDER BY id")
+            .bind(&bloc.id)
+            .fetch_all(&mut **tx)
+            .await?;
+        for prop in props {
+            sqlx::query("INSERT INTO props (id, key, value, bloc_id) VALUES (?, ?, ?, ?)")
+                .bind(uuid::Uuid::new_v4().to_string())
+                .bind(&prop.key)
+                .bind(substitute(&prop.value, &all))
+                .bind(&bloc_id)
+                .execute(&mut **tx)
+                .await?;
+        }
+    }
+
+    Ok(page_id)
+}
+
+impl Database {
+    // marque une page comme modèle, ou met à jour ses réglages
+    pub async fn set_template(&self, template: &TemplateJson) -> Result<bool> {
+        if template.recurrence.is_some() {
+            let mut names = BTreeSet::new();
+            variable_names(&template.title_pattern, &mut names);
+            if template.title_pattern.trim().is_empty() || names.contains("title") {
+                bail!("a recurring template needs a title pattern without {{{{title}}}}, e.g. \"Journal {{{{date}}}}\"");
+            }
+        }
+        let recurrence = match &template.recurrence {
+            Some(recurrence) => serde_json::to_value(recurrence)?.as_str().map(str::to_string),
+            None => None,
+        };
+        let rows_affected = sqlx::query(
+            "INSERT INTO templates (page_id, name, recurrence, target_path, title_pattern, last_run)
+            SELECT id, ?, ?, ?, ?, ? FROM pages WHERE id = ?
+            ON CONFLICT(page_id) DO UPDATE SET
+                name = excluded.name,
+                recurrence = excluded.recurrence,
+                target_path = excluded.target_path,
+                title_pattern = excluded.title_pattern",
+        )
+        .bind(&template.name)
+        .bind(recurrence)
+        .bind(&template.target_path)
+        .bind(&template.title_pattern)
+        .bind(&template.last_run)
+        .bind(&template.page_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    // la page redevient une page ordinaire
+    pub async fn unset_template(&self, page_id: String) -> Result<bool> {
+        let rows_affected = sqlx::query("DELETE FROM templates WHERE page_id = ?")
+            .bind(page_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn get_templates(&self) -> Result<Vec<TemplateJson>> {
+        let rows = sqlx::query(
+            "SELECT t.* FROM templates t JOIN pages p ON p.id = t.page_id ORDER BY t.name, t.page_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(template_from_row).collect()
+    }
+
+    // variables à demander à l'utilisateur avant instantiate_template
+    pub async fn template_variables(&self, template_id: String) -> Result<Vec<String>> {
+        let mut tx = self.pool.begin().await?;
+        let template = fetch_template(&mut tx, &template_id).await?;
+
+        let mut names = BTreeSet::new();
+        variable_names(&template.title_pattern, &mut names);
+
+        let rows = sqlx::query(
+            "SELECT b.content FROM blocs b WHERE b.page_id = ?
+            UNION ALL
+            SELECT p.value FROM props p JOIN blocs b ON b.id = p.bloc_id WHERE b.page_id = ?",
+        )
+        .bind(&template_id)
+        .bind(&template_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        for row in rows {
+            let text: String = row.get(0);
+            match serde_json::from_str::<JsonValue>(&text) {
+                Ok(node) => lexical::walk(&node, &mut |n| {
+                    if let Some(text) = n.get("text").and_then(JsonValue::as_str) {
+                        variable_names(text, &mut names);
+                    }
+                }),
+                Err(_) => variable_names(&text, &mut names),
+            }
+        }
+
+        Ok(names
+            .into_iter()
+            .filter(|name| !BUILTIN_VARIABLES.contains(&name.as_str()))
+            .collect())
+    }
+
+    pub async fn instantiate_template(
+        &self,
+        template_id: String,
+        path: String,
+        title: String,
+        variables: &HashMap<String, String>,
+    ) -> Result<String> {
+        self.instantiate_template_on(template_id, path, title, variables, Local::now().date_naive())
+            .await
+    }
+
+    // comme instantiate_template, pour une date donnée ({{date}}, {{weekday}})
+    pub async fn instantiate_template_on(
+        &self,
+        template_id: String,
+        path: String,
+        title: String,
+        variables: &HashMap<String, String>,
+        date: NaiveDate,
+    ) -> Result<String> {
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "instantiate template").await?;
+
+        let page_id = instantiate_in(&mut tx, &template_id, &path, &title, variables, date).await?;
+
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
+        Ok(page_id)
+    }
+
+    // crée les pages des modèles récurrents qui n'ont pas encore tourné ce jour-là ;
+    // renvoie les ids des pages créées
+    pub async fn run_recurring_templates(&self, today: NaiveDate) -> Result<Vec<String>> {
+        let day = today.format("%Y-%m-%d").to_string();
+        let mut created = Vec::new();
+
+        for template in self.get_templates().await? {
+            if template.recurrence != Some(Recurrence::Daily) {
+                continue;
+            }
+
+            let mut tx = self.pool.begin().await?;
+            // la mise à jour conditionnelle évite un doublon si deux appels se croisent
+            let claimed = sqlx::query(
+                "UPDATE templates SET last_run = ?
+                WHERE page_id = ? AND (last_run IS NULL OR last_run < ?)",
+            )
+            .bind(&day)
+            .bind(&template.page_id)
+            .bind(&day)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if claimed == 0 {
+                continue;
+            }
+
+            // tâche de fond : pas une étape de l'utilisateur, sa pile de
+            // rétablissement reste intacte
+            begin_untracked(&mut tx).await?;
+            let page_id = instantiate_in(
+                &mut tx,
+                &template.page_id,
+                &template.target_path,
+                &template.title_pattern,
+                &HashMap::new(),
+                today,
+            )
+            .await?;
+            end_untracked(&mut tx).await?;
+
+            tx.commit().await?;
+            created.push(page_id);
+        }
+
+        Ok(created)
+    }
+}
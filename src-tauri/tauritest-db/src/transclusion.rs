@@ -0,0 +1,259 @@
+// Références de blocs (transclusion) : un bloc de type "bloc-reference" porte
+// l'id de son bloc source, {"type": "bloc-reference", "source": "<id>"}. Le
+// contenu des sources est rendu à part, par id du bloc référence, pour que
+// l'éditeur ne l'enregistre jamais avec le bloc ; une référence vers une
+// référence affiche la source finale.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use sqlx::{Row, SqliteConnection};
+use std::collections::HashMap;
+
+use crate::database::{checksum, now_millis, BlocJson, Database, PropsJson};
+use crate::lexical;
+use crate::tags::index_hashtags;
+use crate::undo::{begin_undo_group, end_undo_group};
+
+pub const REFERENCE_TYPE: &str = "bloc-reference";
+// au-delà, la chaîne de références est traitée comme une boucle
+pub const MAX_REFERENCE_DEPTH: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct BlocReference {
+    pub bloc_id: String,
+    pub page_id: String,
+    pub page_title: String,
+    pub path: String,
+}
+
+// blocs d'une page avec les sources de leurs références : ce que l'éditeur
+// charge, sans second aller-retour
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PageBlocs {
+    pub blocs: Vec<BlocJson>,
+    // sources des blocs références, par id du bloc référence
+    pub references: HashMap<String, JsonValue>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Resolution {
+    // bloc final de la chaîne
+    Source(BlocJson),
+    Missing(String),
+    Cycle,
+}
+
+// id de la source si `content` est une référence
+pub fn reference_source(content: &str) -> Option<String> {
+    let node: JsonValue = serde_json::from_str(content).ok()?;
+    if lexical::node_type(&node) != REFERENCE_TYPE {
+        return None;
+    }
+    node.get("source").and_then(JsonValue::as_str).map(str::to_string)
+}
+
+// suit la chaîne depuis `source` ; `from` est le bloc qui référence
+async fn resolve(conn: &mut SqliteConnection, from: &str, source: &str) -> Result<Resolution> {
+    let mut visited = vec![from.to_string()];
+    let mut current = source.to_string();
+    loop {
+        if visited.contains(&current) || visited.len() > MAX_REFERENCE_DEPTH {
+            return Ok(Resolution::Cycle);
+        }
+        let Some(bloc) = sqlx::query_as::<_, BlocJson>(
+            "SELECT id, position, content, page_id, bloc_type, created_at, updated_at FROM blocs WHERE id = ?",
+        )
+        .bind(&current)
+        .fetch_optional(&mut *conn)
+        .await?
+        else {
+            return Ok(Resolution::Missing(current));
+        };
+        match reference_source(&bloc.content) {
+            Some(next) => {
+                visited.push(current);
+                current = next;
+            }
+            None => return Ok(Resolution::Source(bloc)),
+        }
+    }
+}
+
+impl Database {
+    // source de chaque bloc référence de `blocs`, par id du bloc référence
+    pub(crate) async fn resolve_references(&self, blocs: &[BlocJson]) -> Result<HashMap<String, JsonValue>> {
+        let mut references = HashMap::new();
+        let mut conn = None;
+        for bloc in blocs {
+            let Ok(node) = serde_json::from_str::<JsonValue>(&bloc.content) else {
+                continue;
+            };
+            if lexical::node_type(&node) != REFERENCE_TYPE {
+                continue;
+            }
+            let source = node.get("source").and_then(JsonValue::as_str).unwrap_or_default().to_string();
+            let id = bloc.id.clone().unwrap_or_default();
+            // connexion prise au premier bloc référence seulement
+            let conn = match &mut conn {
+                Some(conn) => conn,
+                None => conn.insert(self.pool.acquire().await?),
+            };
+            let resolved = match resolve(conn, &id, &source).await? {
+                Resolution::Source(source) => json!({
+                    "bloc_id": source.id,
+                    "page_id": source.page_id,
+                    "bloc_type": source.bloc_type,
+                    "content": serde_json::from_str::<JsonValue>(&source.content).unwrap_or(JsonValue::Null),
+                }),
+                Resolution::Missing(missing) => json!({ "error": "missing", "bloc_id": missing }),
+                Resolution::Cycle => json!({ "error": "cycle" }),
+            };
+            references.insert(id, resolved);
+        }
+        Ok(references)
+    }
+
+    // sources des blocs références de la page, par id du bloc référence
+    pub async fn get_page_references(&self, page_id: String) -> Result<HashMap<String, JsonValue>> {
+        let blocs = sqlx::query_as::<_, BlocJson>(
+            "SELECT id, position, content, page_id, bloc_type, created_at, updated_at
+            FROM blocs WHERE page_id = ? AND bloc_type = ?",
+        )
+        .bind(page_id)
+        .bind(REFERENCE_TYPE)
+        .fetch_all(&self.pool)
+        .await?;
+        self.resolve_references(&blocs).await
+    }
+
+    // blocs de la page (get_blocs_by_page_id) et sources de leurs références
+    pub async fn get_page_blocs(&self, page_id: String) -> Result<PageBlocs> {
+        let blocs = self.get_blocs_by_page_id(page_id).await?;
+        let references = self.resolve_references(&blocs).await?;
+        Ok(PageBlocs { blocs, references })
+    }
+
+    // nouveau bloc de `page_id` qui affiche `source_id`
+    pub async fn new_bloc_reference(&self, page_id: String, source_id: String, position: String) -> Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let resolution = resolve(&mut *self.pool.acquire().await?, &id, &source_id).await?;
+        if let Resolution::Missing(missing) = resolution {
+            bail!("bloc {} not found", missing);
+        }
+
+        let mut node = json!({ "type": REFERENCE_TYPE, "source": source_id, "version": 1 });
+        lexical::set_bloc_state(&mut node, &id, &position);
+        let now = now_millis();
+        self.new_bloc(&BlocJson {
+            id: Some(id),
+            position,
+            content: serde_json::to_string(&node)?,
+            page_id,
+            bloc_type: REFERENCE_TYPE.to_string(),
+            created_at: now,
+            updated_at: now,
+        })
+        .await
+    }
+
+    // fait pointer la référence `id` vers `source_id` ; refusé si cela crée une boucle
+    pub async fn set_reference_source(&self, id: String, source_id: String) -> Result<bool> {
+        let bloc = self.get_bloc_by_id(id.clone()).await?;
+        let Ok(mut node) = serde_json::from_str::<JsonValue>(&bloc.content) else {
+            bail!("bloc {} is not a reference", id);
+        };
+        if lexical::node_type(&node) != REFERENCE_TYPE {
+            bail!("bloc {} is not a reference", id);
+        }
+        match resolve(&mut *self.pool.acquire().await?, &id, &source_id).await? {
+            Resolution::Cycle => bail!("reference from {} to {} would create a cycle", id, source_id),
+            Resolution::Missing(missing) => bail!("bloc {} not found", missing),
+            Resolution::Source(_) => {}
+        }
+
+        node["source"] = JsonValue::String(source_id);
+        let content = serde_json::to_string(&node)?;
+        let rows_affected = sqlx::query("UPDATE blocs SET content = ?, checksum = ?, updated_at = ? WHERE id = ?")
+            .bind(&content)
+            .bind(checksum(&content))
+            .bind(now_millis())
+            .bind(&id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(rows_affected > 0)
+    }
+
+    // blocs qui référencent directement `source_id`
+    pub async fn get_bloc_references(&self, source_id: String) -> Result<Vec<BlocReference>> {
+        let references = sqlx::query_as::<_, BlocReference>(
+            "SELECT b.id AS bloc_id, b.page_id, p.title AS page_title, p.path
+            FROM blocs b JOIN pages p ON p.id = b.page_id
+            WHERE b.bloc_type = 'bloc-reference' AND json_extract(b.content, '$.source') = ?
+            ORDER BY p.path, p.title, b.position",
+        )
+        .bind(source_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(references)
+    }
+
+    // remplace la référence par une copie indépendante de sa source (contenu et
+    // props), à la même place
+    pub async fn detach_bloc_reference(&self, id: String) -> Result<BlocJson> {
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "detach reference").await?;
+
+        let Some(row) = sqlx::query("SELECT position, content FROM blocs WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            bail!("bloc {} not found", id);
+        };
+        let position: String = row.get("position");
+        let Some(source_id) = reference_source(row.get::<&str, _>("content")) else {
+            bail!("bloc {} is not a reference", id);
+        };
+        let source = match resolve(&mut tx, &id, &source_id).await? {
+            Resolution::Source(source) => source,
+            Resolution::Missing(missing) => bail!("bloc {} not found", missing),
+            Resolution::Cycle => bail!("reference {} is part of a cycle", id),
+        };
+
+        let content = match serde_json::from_str::<JsonValue>(&source.content) {
+            Ok(mut node) => {
+                lexical::set_bloc_state(&mut node, &id, &position);
+                serde_json::to_string(&node)?
+            }
+            Err(_) => source.content.clone(),
+        };
+        sqlx::query("UPDATE blocs SET content = ?, checksum = ?, bloc_type = ?, updated_at = ? WHERE id = ?")
+            .bind(&content)
+            .bind(checksum(&content))
+            .bind(&source.bloc_type)
+            .bind(now_millis())
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+        index_hashtags(&mut tx, &id, None, &content).await?;
+
+        let props = sqlx::query_as::<_, PropsJson>("SELECT * FROM props WHERE bloc_id = ? ORDER BY id")
+            .bind(&source.id)
+            .fetch_all(&mut *tx)
+            .await?;
+        for prop in props {
+            sqlx::query("INSERT INTO props (id, key, value, bloc_id) VALUES (?, ?, ?, ?)")
+                .bind(uuid::Uuid::new_v4().to_string())
+                .bind(&prop.key)
+                .bind(&prop.value)
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
+        self.get_bloc_by_id(id).await
+    }
+}
@@ -0,0 +1,227 @@
+// Annuler / rétablir côté serveur, persistant entre les sessions. Les triggers
+// de la migration 7 gardent l'état avant / après de chaque ligne modifiée des
+// pages, blocs et props ; annuler une étape réécrit les états "avant" en sens
+// inverse, rétablir réécrit les états "après" dans l'ordre.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, Sqlite, Transaction};
+
+use crate::database::Database;
+use crate::tags::index_hashtags;
+
+// nombre d'étapes gardées, fixé dans les triggers de la migration 7
+pub const UNDO_LIMIT: i64 = 1000;
+
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow, Deserialize, Serialize)]
+pub struct UndoStep {
+    pub id: i64,
+    pub label: String,
+    pub undone: bool,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UndoStatus {
+    // libellés des prochaines étapes à annuler / rétablir
+    pub undo: Option<String>,
+    pub redo: Option<String>,
+}
+
+fn columns(entity: &str) -> Result<(&'static str, &'static [&'static str])> {
+    Ok(match entity {
+        "page" => ("pages", &["id", "path", "title", "cache", "created_at", "updated_at"]),
+        "bloc" => (
+            "blocs",
+            &["id", "position", "content", "checksum", "page_id", "bloc_type", "created_at", "updated_at"],
+        ),
+        "prop" => ("props", &["id", "key", "value", "bloc_id"]),
+        other => bail!("unknown undo entity: {}", other),
+    })
+}
+
+async fn delete_row(tx: &mut Transaction<'_, Sqlite>, entity: &str, id: &str) -> Result<()> {
+    let (table, _) = columns(entity)?;
+    sqlx::query(&format!("DELETE FROM {} WHERE id = ?", table))
+        .bind(id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// réinsère une ligne depuis son instantané JSON
+async fn insert_row(tx: &mut Transaction<'_, Sqlite>, entity: &str, row: &str) -> Result<()> {
+    let (table, cols) = columns(entity)?;
+    let values: Vec<String> = cols
+        .iter()
+        .map(|col| format!("json_extract(?1, '$.{}')", col))
+        .collect();
+    sqlx::query(&format!(
+        "INSERT INTO {} ({}) SELECT {}",
+        table,
+        cols.join(", "),
+        values.join(", ")
+    ))
+    .bind(row)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// contenu d'un bloc dans son instantané JSON
+fn row_content(row: &str) -> Option<String> {
+    let row: serde_json::Value = serde_json::from_str(row).ok()?;
+    row.get("content")?.as_str().map(str::to_string)
+}
+
+async fn set_replaying(tx: &mut Transaction<'_, Sqlite>, replaying: bool) -> Result<()> {
+    sqlx::query("UPDATE undo_state SET value = ? WHERE key = 'replaying'")
+        .bind(replaying)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// écritures qui ne viennent pas de l'utilisateur (synchronisation, fusion
+// CRDT) : les triggers ne les enregistrent pas, jusqu'à end_untracked
+pub(crate) async fn begin_untracked(tx: &mut Transaction<'_, Sqlite>) -> Result<()> {
+    set_replaying(tx, true).await
+}
+
+pub(crate) async fn end_untracked(tx: &mut Transaction<'_, Sqlite>) -> Result<()> {
+    set_replaying(tx, false).await
+}
+
+// regroupe les écritures qui suivent, dans la même transaction, en une étape
+pub(crate) async fn begin_undo_group(tx: &mut Transaction<'_, Sqlite>, label: &str) -> Result<()> {
+    let step_id: i64 = sqlx::query(
+        "INSERT INTO undo_steps (label, created_at)
+        VALUES (?, CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER))
+        RETURNING id",
+    )
+    .bind(label)
+    .fetch_one(&mut **tx)
+    .await?
+    .get(0);
+
+    sqlx::query("UPDATE undo_state SET value = ? WHERE key = 'group'")
+        .bind(step_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// ferme le groupe ; une étape sans écriture est retirée
+pub(crate) async fn end_undo_group(tx: &mut Transaction<'_, Sqlite>) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM undo_steps
+        WHERE id = (SELECT value FROM undo_state WHERE key = 'group')
+        AND NOT EXISTS (SELECT 1 FROM undo_entries WHERE step_id = undo_steps.id)",
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query("UPDATE undo_state SET value = NULL WHERE key = 'group'")
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+impl Database {
+    // annule la dernière étape ; None s'il n'y a rien à annuler
+    pub async fn undo(&self) -> Result<Option<UndoStep>> {
+        self.replay(true).await
+    }
+
+    // rétablit la dernière étape annulée ; None s'il n'y a rien à rétablir
+    pub async fn redo(&self) -> Result<Option<UndoStep>> {
+        self.replay(false).await
+    }
+
+    async fn replay(&self, undo: bool) -> Result<Option<UndoStep>> {
+        let mut tx = self.pool.begin().await?;
+
+        // les étapes annulées forment la pile de redo : la plus ancienne est
+        // la dernière annulée
+        let step = sqlx::query_as::<_, UndoStep>(if undo {
+            "SELECT * FROM undo_steps WHERE undone = 0 ORDER BY id DESC LIMIT 1"
+        } else {
+            "SELECT * FROM undo_steps WHERE undone = 1 ORDER BY id LIMIT 1"
+        })
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(mut step) = step else {
+            return Ok(None);
+        };
+
+        let entries = sqlx::query(if undo {
+            "SELECT * FROM undo_entries WHERE step_id = ? ORDER BY id DESC"
+        } else {
+            "SELECT * FROM undo_entries WHERE step_id = ? ORDER BY id"
+        })
+        .bind(step.id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        set_replaying(&mut tx, true).await?;
+        for entry in &entries {
+            let entity: String = entry.get("entity");
+            let entity_id: String = entry.get("entity_id");
+            let before: Option<String> = entry.get("before_row");
+            let after: Option<String> = entry.get("after_row");
+            let (remove, restore) = if undo { (after, before) } else { (before, after) };
+
+            if remove.is_some() {
+                delete_row(&mut tx, &entity, &entity_id).await?;
+            }
+            if let Some(row) = &restore {
+                insert_row(&mut tx, &entity, row).await?;
+            }
+            // les triggers ne connaissent pas les hashtags : réindexés ici
+            if entity == "bloc" {
+                if let Some(after) = restore.as_deref().and_then(row_content) {
+                    index_hashtags(&mut tx, &entity_id, remove.as_deref().and_then(row_content).as_deref(), &after)
+                        .await?;
+                }
+            }
+        }
+        set_replaying(&mut tx, false).await?;
+
+        step.undone = undo;
+        sqlx::query("UPDATE undo_steps SET undone = ? WHERE id = ?")
+            .bind(step.undone)
+            .bind(step.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(Some(step))
+    }
+
+    pub async fn undo_status(&self) -> Result<UndoStatus> {
+        let undo = sqlx::query("SELECT label FROM undo_steps WHERE undone = 0 ORDER BY id DESC LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get(0));
+        let redo = sqlx::query("SELECT label FROM undo_steps WHERE undone = 1 ORDER BY id LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get(0));
+        Ok(UndoStatus { undo, redo })
+    }
+
+    // étapes les plus récentes d'abord
+    pub async fn undo_history(&self, limit: i64) -> Result<Vec<UndoStep>> {
+        let steps = sqlx::query_as::<_, UndoStep>("SELECT * FROM undo_steps ORDER BY id DESC LIMIT ?")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(steps)
+    }
+
+    pub async fn clear_undo_history(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM undo_entries").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM undo_steps").execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
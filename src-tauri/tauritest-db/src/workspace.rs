@@ -0,0 +1,232 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{bloc_content, checksum, now_millis, BlocJson, Database, PageJson, PropsJson};
+use crate::fractional_index;
+use crate::lexical;
+use crate::tags::index_hashtags;
+use crate::undo::{begin_undo_group, end_undo_group};
+
+// Sauvegarde complète d'un espace de travail, au format JSON
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceExport {
+    pub format_version: i64,
+    pub schema_version: i64,
+    pub exported_at: i64,
+    pub pages: Vec<PageJson>,
+    pub blocs: Vec<BlocJson>,
+    pub props: Vec<PropsJson>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub pages: u64,
+    pub blocs: u64,
+    pub props: u64,
+}
+
+impl Database {
+    pub const EXPORT_FORMAT_VERSION: i64 = 1;
+
+    pub async fn export_workspace(&self) -> Result<WorkspaceExport> {
+        let pages = sqlx::query_as::<_, PageJson>(
+            "SELECT id, path, title, COALESCE(cache, '') as cache, created_at, updated_at
+            FROM pages
+            ORDER BY path, title",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let blocs = sqlx::query_as::<_, BlocJson>(
+            "SELECT id, position, content, page_id, bloc_type, created_at, updated_at
+            FROM blocs
+            ORDER BY page_id, position",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let props = sqlx::query_as::<_, PropsJson>(
+            "SELECT id, key, value, bloc_id
+            FROM props
+            ORDER BY bloc_id, key",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(WorkspaceExport {
+            format_version: Self::EXPORT_FORMAT_VERSION,
+            schema_version: self.schema_version().await?,
+            exported_at: now_millis(),
+            pages,
+            blocs,
+            props,
+        })
+    }
+
+    // importe une sauvegarde ; une ligne de même id est remplacée
+    pub async fn import_workspace(&self, export: &WorkspaceExport) -> Result<ImportReport> {
+        if export.format_version > Self::EXPORT_FORMAT_VERSION {
+            bail!(
+                "export format {} is newer than supported format {}",
+                export.format_version,
+                Self::EXPORT_FORMAT_VERSION
+            );
+        }
+
+        let mut report = ImportReport::default();
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "import workspace").await?;
+
+        for page in &export.pages {
+            sqlx::query("DELETE FROM pages WHERE id = ?")
+                .bind(&page.id)
+                .execute(&mut *tx)
+                .await?;
+            report.pages += sqlx::query(
+                "INSERT INTO pages (id, path, title, cache, created_at, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&page.id)
+            .bind(&page.path)
+            .bind(&page.title)
+            .bind(&page.cache)
+            .bind(page.created_at)
+            .bind(page.updated_at)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        for bloc in &export.blocs {
+            let before = bloc_content(&mut tx, bloc.id.as_deref()).await?;
+            sqlx::query("DELETE FROM blocs WHERE id = ?")
+                .bind(&bloc.id)
+                .execute(&mut *tx)
+                .await?;
+            report.blocs += sqlx::query(
+                "INSERT INTO blocs (id, position, content, checksum, page_id, bloc_type, created_at, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&bloc.id)
+            .bind(&bloc.position)
+            .bind(&bloc.content)
+            .bind(checksum(&bloc.content))
+            .bind(&bloc.page_id)
+            .bind(&bloc.bloc_type)
+            .bind(bloc.created_at)
+            .bind(bloc.updated_at)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if let Some(id) = bloc.id.as_deref() {
+                index_hashtags(&mut tx, id, before.as_deref(), &bloc.content).await?;
+            }
+        }
+
+        for prop in &export.props {
+            sqlx::query("DELETE FROM props WHERE id = ?")
+                .bind(&prop.id)
+                .execute(&mut *tx)
+                .await?;
+            report.props += sqlx::query(
+                "INSERT INTO props (id, key, value, bloc_id)
+                VALUES (?, ?, ?, ?)",
+            )
+            .bind(&prop.id)
+            .bind(&prop.key)
+            .bind(&prop.value)
+            .bind(&prop.bloc_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    // titre puis blocs dans l'ordre de la page
+    pub async fn page_to_markdown(&self, page_id: String) -> Result<String> {
+        let page = self.get_page_by_id(page_id.clone()).await?;
+        let blocs = self.get_blocs_by_page_id(page_id.clone()).await?;
+        let references = self.get_page_references(page_id).await?;
+
+        let mut parts = vec![format!("# {}", page.title)];
+        for bloc in blocs {
+            let node: serde_json::Value = serde_json::from_str(&bloc.content)?;
+            // une référence s'exporte avec le contenu de sa source
+            let source = bloc
+                .id
+                .as_ref()
+                .and_then(|id| references.get(id))
+                .and_then(|resolved| resolved.get("content"));
+            let markdown = lexical::to_markdown(source.unwrap_or(&node));
+            if !markdown.is_empty() {
+                parts.push(markdown);
+            }
+        }
+
+        Ok(parts.join("\n\n") + "\n")
+    }
+
+    // crée une page et un bloc par élément Markdown de premier niveau, en une transaction
+    pub async fn new_page_from_markdown(
+        &self,
+        path: String,
+        title: String,
+        markdown: &str,
+    ) -> Result<String> {
+        let now = now_millis();
+        let page_id = uuid::Uuid::new_v4().to_string();
+
+        let mut nodes = lexical::from_markdown(markdown);
+        if nodes.is_empty() {
+            // l'éditeur attend au moins un bloc par page
+            nodes.push(lexical::paragraph(""));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        begin_undo_group(&mut tx, "new page from markdown").await?;
+
+        sqlx::query(
+            "INSERT INTO pages (id, path, title, cache, created_at, updated_at)
+            VALUES (?, ?, ?, '', ?, ?)",
+        )
+        .bind(&page_id)
+        .bind(&path)
+        .bind(&title)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        let positions = fractional_index::generate_n_keys_between(None, None, nodes.len())?;
+        for (mut node, position) in nodes.into_iter().zip(positions) {
+            let bloc_id = uuid::Uuid::new_v4().to_string();
+            lexical::set_bloc_state(&mut node, &bloc_id, &position);
+
+            let bloc_type = lexical::node_type(&node).to_string();
+            let content = serde_json::to_string(&node)?;
+            sqlx::query(
+                "INSERT INTO blocs (id, position, content, checksum, page_id, bloc_type, created_at, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&bloc_id)
+            .bind(&position)
+            .bind(&content)
+            .bind(checksum(&content))
+            .bind(&page_id)
+            .bind(&bloc_type)
+            .bind(now)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            index_hashtags(&mut tx, &bloc_id, None, &content).await?;
+        }
+
+        end_undo_group(&mut tx).await?;
+        tx.commit().await?;
+        Ok(page_id)
+    }
+}
@@ -0,0 +1,172 @@
+use tauritest_db::comments::DELETED_COMMENT;
+use tauritest_db::{BlocJson, CommentJson, Database, PageCommentCount, PageJson, ThreadJson};
+
+async fn db() -> Database {
+    let db = Database::new_in_memory().await.unwrap();
+    for page_id in ["p1", "p2"] {
+        db.new_page(&PageJson {
+            id: Some(page_id.to_string()),
+            path: "/".to_string(),
+            title: page_id.to_string(),
+            cache: String::new(),
+            created_at: 0,
+            updated_at: 0,
+        })
+        .await
+        .unwrap();
+    }
+    for (id, page_id, position) in [("b1", "p1", "a0"), ("b2", "p1", "a1"), ("b3", "p2", "a0")] {
+        db.new_bloc(&BlocJson {
+            id: Some(id.to_string()),
+            position: position.to_string(),
+            content: "{}".to_string(),
+            page_id: page_id.to_string(),
+            bloc_type: "paragraph".to_string(),
+            created_at: 0,
+            updated_at: 0,
+        })
+        .await
+        .unwrap();
+    }
+    db
+}
+
+fn comment(id: &str, thread_id: &str, content: &str, at: i64) -> CommentJson {
+    CommentJson {
+        id: Some(id.to_string()),
+        thread_id: thread_id.to_string(),
+        author: "me".to_string(),
+        content: content.to_string(),
+        deleted: false,
+        created_at: at,
+        updated_at: at,
+    }
+}
+
+async fn thread(db: &Database, id: &str, bloc_id: &str, first: &str) -> String {
+    db.new_thread(&ThreadJson {
+        id: Some(id.to_string()),
+        bloc_id: bloc_id.to_string(),
+        page_id: None,
+        quote: "quoted".to_string(),
+        resolved: false,
+        created_at: 1,
+        updated_at: 1,
+        comments: vec![comment(&format!("{}-c0", id), "", first, 1)],
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn thread_with_replies_round_trip() {
+    let db = db().await;
+    let id = thread(&db, "t1", "b1", "first").await;
+    db.new_comment(&comment("c1", "t1", "reply", 2)).await.unwrap();
+
+    let thread = db.get_thread_by_id(id).await.unwrap();
+    assert_eq!(thread.page_id.as_deref(), Some("p1"));
+    let contents: Vec<&str> = thread.comments.iter().map(|c| c.content.as_str()).collect();
+    assert_eq!(contents, vec!["first", "reply"]);
+    assert_eq!(thread.comments[0].thread_id, "t1");
+
+    // un fil inconnu est refusé
+    assert!(db.new_comment(&comment("c2", "nope", "x", 3)).await.is_err());
+}
+
+#[tokio::test]
+async fn generates_ids_when_missing() {
+    let db = db().await;
+    let id = db
+        .new_thread(&ThreadJson {
+            id: None,
+            bloc_id: "b1".to_string(),
+            page_id: None,
+            quote: String::new(),
+            resolved: false,
+            created_at: 0,
+            updated_at: 0,
+            comments: vec![CommentJson { id: None, ..comment("", "", "x", 0) }],
+        })
+        .await
+        .unwrap();
+    let thread = db.get_thread_by_id(id).await.unwrap();
+    assert!(!thread.comments[0].id.as_deref().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn soft_delete_keeps_the_comment_in_place() {
+    let db = db().await;
+    thread(&db, "t1", "b1", "first").await;
+    db.new_comment(&comment("c1", "t1", "reply", 2)).await.unwrap();
+
+    assert!(db.delete_comment("t1-c0".to_string(), 5).await.unwrap());
+    assert!(!db.delete_comment("t1-c0".to_string(), 6).await.unwrap());
+    // un commentaire supprimé ne se modifie plus
+    assert!(!db.update_comment_content("t1-c0".to_string(), "back".to_string(), 7).await.unwrap());
+    assert!(db.update_comment_content("c1".to_string(), "edited".to_string(), 7).await.unwrap());
+
+    let thread = db.get_thread_by_id("t1".to_string()).await.unwrap();
+    assert_eq!(thread.comments.len(), 2);
+    assert!(thread.comments[0].deleted);
+    assert_eq!(thread.comments[0].content, DELETED_COMMENT);
+    assert_eq!(thread.comments[1].content, "edited");
+}
+
+#[tokio::test]
+async fn resolve_reopen_and_page_listing() {
+    let db = db().await;
+    thread(&db, "t1", "b2", "on b2").await;
+    thread(&db, "t2", "b1", "on b1").await;
+    thread(&db, "t3", "b3", "other page").await;
+
+    let ids = |threads: Vec<ThreadJson>| -> Vec<String> {
+        threads.into_iter().map(|t| t.id.unwrap()).collect()
+    };
+    // ordre des blocs de la page
+    assert_eq!(ids(db.get_threads_by_page_id("p1".to_string(), false).await.unwrap()), vec!["t2", "t1"]);
+
+    assert!(db.set_thread_resolved("t2".to_string(), true, 3).await.unwrap());
+    assert!(!db.set_thread_resolved("t2".to_string(), true, 4).await.unwrap());
+    assert_eq!(ids(db.get_threads_by_page_id("p1".to_string(), false).await.unwrap()), vec!["t1"]);
+    assert_eq!(ids(db.get_threads_by_page_id("p1".to_string(), true).await.unwrap()), vec!["t2", "t1"]);
+
+    // répondre rouvre le fil
+    db.new_comment(&comment("c1", "t2", "again", 5)).await.unwrap();
+    assert!(!db.get_thread_by_id("t2".to_string()).await.unwrap().resolved);
+
+    assert!(db.set_thread_resolved("t2".to_string(), true, 6).await.unwrap());
+    assert!(db.set_thread_resolved("t2".to_string(), false, 7).await.unwrap());
+    assert_eq!(db.get_threads_by_bloc_id("b1".to_string()).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn counts_unresolved_comments_per_page() {
+    let db = db().await;
+    thread(&db, "t1", "b1", "a").await;
+    db.new_comment(&comment("c1", "t1", "b", 2)).await.unwrap();
+    db.new_comment(&comment("c2", "t1", "c", 3)).await.unwrap();
+    db.delete_comment("c2".to_string(), 4).await.unwrap();
+    thread(&db, "t2", "b2", "d").await;
+    thread(&db, "t3", "b3", "e").await;
+    db.set_thread_resolved("t3".to_string(), true, 5).await.unwrap();
+
+    assert_eq!(
+        db.get_unresolved_comment_counts().await.unwrap(),
+        vec![PageCommentCount {
+            page_id: "p1".to_string(),
+            threads: 2,
+            comments: 3,
+        }]
+    );
+
+    // le fil suit son bloc quand celui-ci change de page
+    db.update_bloc_page_id("b2".to_string(), "p2".to_string()).await.unwrap();
+    let counts = db.get_unresolved_comment_counts().await.unwrap();
+    assert_eq!(counts.len(), 2);
+    assert_eq!((counts[1].page_id.as_str(), counts[1].threads), ("p2", 1));
+
+    assert!(db.delete_thread("t1".to_string()).await.unwrap());
+    assert!(db.get_thread_by_id("t1".to_string()).await.is_err());
+    assert_eq!(db.get_unresolved_comment_counts().await.unwrap()[0].page_id, "p2");
+}
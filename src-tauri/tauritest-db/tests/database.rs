@@ -0,0 +1,454 @@
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson};
+
+async fn db() -> Database {
+    Database::new_in_memory().await.unwrap()
+}
+
+fn page(id: &str, path: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: path.to_string(),
+        title: format!("title {}", id),
+        cache: format!("cache {}", id),
+        created_at: 10,
+        updated_at: 10,
+    }
+}
+
+fn bloc(id: &str, page_id: &str, position: &str) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: format!("{{\"text\":\"{}\"}}", id),
+        page_id: page_id.to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 10,
+        updated_at: 10,
+    }
+}
+
+fn prop(id: &str, bloc_id: &str, key: &str, value: &str) -> PropsJson {
+    PropsJson {
+        id: Some(id.to_string()),
+        key: key.to_string(),
+        value: value.to_string(),
+        bloc_id: bloc_id.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn in_memory_databases_are_isolated() {
+    let a = db().await;
+    let b = db().await;
+    a.new_page(&page("p1", "/")).await.unwrap();
+
+    assert_eq!(a.get_pages_by_path("/".to_string()).await.unwrap().len(), 1);
+    assert!(b.get_pages_by_path("/".to_string()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn file_database_persists_between_connections() {
+    let dir = std::env::temp_dir().join(format!("tauritest-db-test-{}", std::process::id()));
+    let path = dir.join("nested").join("notes.db");
+    let path = path.to_str().unwrap();
+
+    {
+        let db = Database::new(path).await.unwrap();
+        db.new_page(&page("p1", "/")).await.unwrap();
+    }
+
+    let db = Database::new(path).await.unwrap();
+    let pages = db.get_pages_by_path("/".to_string()).await.unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].id.as_deref(), Some("p1"));
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn new_bloc_and_get_bloc_by_id() {
+    let db = db().await;
+    let id = db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+    assert_eq!(id, "b1");
+
+    let stored = db.get_bloc_by_id("b1".to_string()).await.unwrap();
+    assert_eq!(stored.position, "a0");
+    assert_eq!(stored.content, "{\"text\":\"b1\"}");
+    assert_eq!(stored.page_id, "p1");
+    assert_eq!(stored.bloc_type, "paragraph");
+    assert_eq!(stored.created_at, 10);
+    assert_eq!(stored.updated_at, 10);
+}
+
+#[tokio::test]
+async fn get_bloc_by_id_fails_for_unknown_id() {
+    let db = db().await;
+    assert!(db.get_bloc_by_id("missing".to_string()).await.is_err());
+}
+
+#[tokio::test]
+async fn update_bloc_rewrites_fields_and_checksum() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+    let before = db.get_checksum("b1".to_string()).await.unwrap();
+
+    let mut edited = bloc("b1", "p1", "a5");
+    edited.content = "{\"text\":\"edited\"}".to_string();
+    edited.bloc_type = "heading".to_string();
+    edited.updated_at = 20;
+    assert!(db.update_bloc(&edited).await.unwrap());
+
+    let stored = db.get_bloc_by_id("b1".to_string()).await.unwrap();
+    assert_eq!(stored.position, "a5");
+    assert_eq!(stored.content, "{\"text\":\"edited\"}");
+    assert_eq!(stored.bloc_type, "heading");
+    assert_eq!(stored.updated_at, 20);
+    assert_ne!(db.get_checksum("b1".to_string()).await.unwrap(), before);
+}
+
+#[tokio::test]
+async fn update_bloc_returns_false_for_unknown_id() {
+    let db = db().await;
+    assert!(!db.update_bloc(&bloc("missing", "p1", "a0")).await.unwrap());
+}
+
+#[tokio::test]
+async fn update_bloc_content_success() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+    let before = db.get_checksum("b1".to_string()).await.unwrap();
+
+    let status = db
+        .update_bloc_content("b1".to_string(), "{\"text\":\"new\"}".to_string(), 30)
+        .await
+        .unwrap();
+    assert_eq!(status, Database::SUCCESS);
+
+    let stored = db.get_bloc_by_id("b1".to_string()).await.unwrap();
+    assert_eq!(stored.content, "{\"text\":\"new\"}");
+    assert_eq!(stored.updated_at, 30);
+    assert_ne!(db.get_checksum("b1".to_string()).await.unwrap(), before);
+}
+
+#[tokio::test]
+async fn update_bloc_content_no_change_when_checksum_matches() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+
+    let status = db
+        .update_bloc_content("b1".to_string(), "{\"text\":\"b1\"}".to_string(), 30)
+        .await
+        .unwrap();
+    assert_eq!(status, Database::NO_CHANGE);
+
+    // updated_at n'est pas touché quand le contenu est identique
+    let stored = db.get_bloc_by_id("b1".to_string()).await.unwrap();
+    assert_eq!(stored.updated_at, 10);
+}
+
+#[tokio::test]
+async fn update_bloc_content_fails_for_unknown_id() {
+    let db = db().await;
+    let result = db
+        .update_bloc_content("missing".to_string(), "x".to_string(), 30)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn update_bloc_position_success() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+
+    let status = db
+        .update_bloc_position("b1".to_string(), "a1".to_string(), 40)
+        .await
+        .unwrap();
+    assert_eq!(status, Database::SUCCESS);
+    assert_eq!(db.get_position("b1".to_string()).await.unwrap(), "a1");
+    assert_eq!(db.get_bloc_by_id("b1".to_string()).await.unwrap().updated_at, 40);
+}
+
+#[tokio::test]
+async fn update_bloc_position_no_change_when_position_matches() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+
+    let status = db
+        .update_bloc_position("b1".to_string(), "a0".to_string(), 40)
+        .await
+        .unwrap();
+    assert_eq!(status, Database::NO_CHANGE);
+    assert_eq!(db.get_bloc_by_id("b1".to_string()).await.unwrap().updated_at, 10);
+}
+
+#[tokio::test]
+async fn update_bloc_position_fails_for_unknown_id() {
+    let db = db().await;
+    let result = db
+        .update_bloc_position("missing".to_string(), "a1".to_string(), 40)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn update_bloc_page_id_moves_bloc() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+
+    assert!(db.update_bloc_page_id("b1".to_string(), "p2".to_string()).await.unwrap());
+    assert!(db.get_blocs_by_page_id("p1".to_string()).await.unwrap().is_empty());
+    assert_eq!(db.get_blocs_by_page_id("p2".to_string()).await.unwrap().len(), 1);
+    assert!(!db.update_bloc_page_id("missing".to_string(), "p2".to_string()).await.unwrap());
+}
+
+#[tokio::test]
+async fn delete_bloc_removes_only_that_bloc() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+    db.new_bloc(&bloc("b2", "p1", "a1")).await.unwrap();
+
+    assert!(db.delete_bloc("b1".to_string()).await.unwrap());
+    assert!(!db.delete_bloc("b1".to_string()).await.unwrap());
+
+    let remaining = db.get_blocs_by_page_id("p1".to_string()).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id.as_deref(), Some("b2"));
+}
+
+#[tokio::test]
+async fn delete_bloc_by_page_id_removes_page_blocs() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+    db.new_bloc(&bloc("b2", "p1", "a1")).await.unwrap();
+    db.new_bloc(&bloc("b3", "p2", "a0")).await.unwrap();
+
+    assert!(db.delete_bloc_by_page_id("p1".to_string()).await.unwrap());
+    assert!(!db.delete_bloc_by_page_id("p1".to_string()).await.unwrap());
+    assert!(db.get_blocs_by_page_id("p1".to_string()).await.unwrap().is_empty());
+    assert_eq!(db.get_blocs_by_page_id("p2".to_string()).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn get_position_and_get_checksum() {
+    let db = db().await;
+    db.new_bloc(&bloc("b1", "p1", "Zz")).await.unwrap();
+
+    assert_eq!(db.get_position("b1".to_string()).await.unwrap(), "Zz");
+    let checksum = db.get_checksum("b1".to_string()).await.unwrap();
+    assert!(!checksum.is_empty());
+
+    // deux blocs de même contenu ont la même empreinte
+    let mut twin = bloc("b2", "p1", "a0");
+    twin.content = "{\"text\":\"b1\"}".to_string();
+    db.new_bloc(&twin).await.unwrap();
+    assert_eq!(db.get_checksum("b2".to_string()).await.unwrap(), checksum);
+
+    assert!(db.get_position("missing".to_string()).await.is_err());
+    assert!(db.get_checksum("missing".to_string()).await.is_err());
+}
+
+#[tokio::test]
+async fn get_blocs_by_page_id_orders_by_position() {
+    let db = db().await;
+    db.new_bloc(&bloc("b3", "p1", "a2")).await.unwrap();
+    db.new_bloc(&bloc("b1", "p1", "a0")).await.unwrap();
+    db.new_bloc(&bloc("b2", "p1", "a1")).await.unwrap();
+    db.new_bloc(&bloc("other", "p2", "a0")).await.unwrap();
+
+    let ids: Vec<String> = db
+        .get_blocs_by_page_id("p1".to_string())
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|b| b.id)
+        .collect();
+    assert_eq!(ids, vec!["b1", "b2", "b3"]);
+    assert!(db.get_blocs_by_page_id("empty".to_string()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn new_page_and_get_pages_by_path() {
+    let db = db().await;
+    assert_eq!(db.new_page(&page("p1", "/notes")).await.unwrap(), "p1");
+    db.new_page(&page("p2", "/notes")).await.unwrap();
+    db.new_page(&page("p3", "/other")).await.unwrap();
+
+    let pages = db.get_pages_by_path("/notes".to_string()).await.unwrap();
+    assert_eq!(pages.len(), 2);
+    assert!(pages.iter().all(|p| p.path == "/notes"));
+    // la liste ne transporte pas le cache
+    assert!(pages.iter().all(|p| p.cache.is_empty()));
+    assert!(db.get_pages_by_path("/nothing".to_string()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn update_page_rewrites_fields() {
+    let db = db().await;
+    db.new_page(&page("p1", "/")).await.unwrap();
+
+    let mut edited = page("p1", "/moved");
+    edited.title = "renamed".to_string();
+    edited.cache = "new cache".to_string();
+    edited.updated_at = 50;
+    assert!(db.update_page(&edited).await.unwrap());
+
+    let pages = db.get_pages_by_path("/moved".to_string()).await.unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].title, "renamed");
+    assert_eq!(pages[0].updated_at, 50);
+    assert_eq!(db.get_page_cache("p1".to_string()).await.unwrap(), "new cache");
+
+    assert!(!db.update_page(&page("missing", "/")).await.unwrap());
+}
+
+#[tokio::test]
+async fn update_page_path_and_title() {
+    let db = db().await;
+    db.new_page(&page("p1", "/")).await.unwrap();
+
+    assert!(db.update_page_path("p1".to_string(), "/a/b".to_string()).await.unwrap());
+    assert!(db.update_page_title("p1".to_string(), "hello".to_string()).await.unwrap());
+
+    let pages = db.get_pages_by_path("/a/b".to_string()).await.unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].title, "hello");
+
+    assert!(!db.update_page_path("missing".to_string(), "/".to_string()).await.unwrap());
+    assert!(!db.update_page_title("missing".to_string(), "x".to_string()).await.unwrap());
+}
+
+#[tokio::test]
+async fn update_and_get_page_cache() {
+    let db = db().await;
+    db.new_page(&page("p1", "/")).await.unwrap();
+    assert_eq!(db.get_page_cache("p1".to_string()).await.unwrap(), "cache p1");
+
+    assert!(db.update_page_cache("p1".to_string(), "fresh".to_string()).await.unwrap());
+    assert_eq!(db.get_page_cache("p1".to_string()).await.unwrap(), "fresh");
+
+    assert!(!db.update_page_cache("missing".to_string(), "x".to_string()).await.unwrap());
+    assert!(db.get_page_cache("missing".to_string()).await.is_err());
+}
+
+#[tokio::test]
+async fn update_page_updated_at() {
+    let db = db().await;
+    db.new_page(&page("p1", "/")).await.unwrap();
+
+    assert!(db.update_page_updated_at("p1".to_string(), 99).await.unwrap());
+    let pages = db.get_pages_by_path("/".to_string()).await.unwrap();
+    assert_eq!(pages[0].updated_at, 99);
+    assert_eq!(pages[0].created_at, 10);
+
+    assert!(!db.update_page_updated_at("missing".to_string(), 99).await.unwrap());
+}
+
+#[tokio::test]
+async fn delete_page_removes_page() {
+    let db = db().await;
+    db.new_page(&page("p1", "/")).await.unwrap();
+    db.new_page(&page("p2", "/")).await.unwrap();
+
+    assert!(db.delete_page("p1".to_string()).await.unwrap());
+    assert!(!db.delete_page("p1".to_string()).await.unwrap());
+
+    let pages = db.get_pages_by_path("/".to_string()).await.unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].id.as_deref(), Some("p2"));
+}
+
+#[tokio::test]
+async fn new_prop_and_get_props_by_bloc_id() {
+    let db = db().await;
+    assert_eq!(db.new_prop(&prop("r1", "b1", "status", "todo")).await.unwrap(), "r1");
+    db.new_prop(&prop("r2", "b1", "priority", "2")).await.unwrap();
+    db.new_prop(&prop("r3", "b2", "status", "done")).await.unwrap();
+
+    let props = db.get_props_by_bloc_id("b1".to_string()).await.unwrap();
+    assert_eq!(props.len(), 2);
+    assert!(props.iter().all(|p| p.bloc_id == "b1"));
+    assert!(db.get_props_by_bloc_id("missing".to_string()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn get_props_by_key_spans_blocs() {
+    let db = db().await;
+    db.new_prop(&prop("r1", "b1", "status", "todo")).await.unwrap();
+    db.new_prop(&prop("r2", "b2", "status", "done")).await.unwrap();
+    db.new_prop(&prop("r3", "b2", "priority", "1")).await.unwrap();
+
+    let mut values: Vec<String> = db
+        .get_props_by_key("status".to_string())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.value)
+        .collect();
+    values.sort();
+    assert_eq!(values, vec!["done", "todo"]);
+}
+
+#[tokio::test]
+async fn update_prop_value_targets_bloc_and_key() {
+    let db = db().await;
+    db.new_prop(&prop("r1", "b1", "status", "todo")).await.unwrap();
+    db.new_prop(&prop("r2", "b2", "status", "todo")).await.unwrap();
+
+    assert!(db
+        .update_prop_value("b1".to_string(), "status".to_string(), "done".to_string())
+        .await
+        .unwrap());
+    assert_eq!(db.get_props_by_bloc_id("b1".to_string()).await.unwrap()[0].value, "done");
+    assert_eq!(db.get_props_by_bloc_id("b2".to_string()).await.unwrap()[0].value, "todo");
+
+    assert!(!db
+        .update_prop_value("b1".to_string(), "missing".to_string(), "x".to_string())
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn delete_prop_and_delete_prop_by_bloc_id() {
+    let db = db().await;
+    db.new_prop(&prop("r1", "b1", "status", "todo")).await.unwrap();
+    db.new_prop(&prop("r2", "b1", "priority", "1")).await.unwrap();
+    db.new_prop(&prop("r3", "b2", "status", "todo")).await.unwrap();
+
+    assert!(db.delete_prop("b1".to_string(), "status".to_string()).await.unwrap());
+    assert!(!db.delete_prop("b1".to_string(), "status".to_string()).await.unwrap());
+    assert_eq!(db.get_props_by_bloc_id("b1".to_string()).await.unwrap().len(), 1);
+
+    assert!(db.delete_prop_by_bloc_id("b1".to_string()).await.unwrap());
+    assert!(!db.delete_prop_by_bloc_id("b1".to_string()).await.unwrap());
+    assert!(db.get_props_by_bloc_id("b1".to_string()).await.unwrap().is_empty());
+    assert_eq!(db.get_props_by_bloc_id("b2".to_string()).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn change_prop_key_name_renames_everywhere() {
+    let db = db().await;
+    db.new_prop(&prop("r1", "b1", "state", "todo")).await.unwrap();
+    db.new_prop(&prop("r2", "b2", "state", "done")).await.unwrap();
+
+    assert!(db
+        .change_prop_key_name("state".to_string(), "status".to_string())
+        .await
+        .unwrap());
+    assert!(db.get_props_by_key("state".to_string()).await.unwrap().is_empty());
+    assert_eq!(db.get_props_by_key("status".to_string()).await.unwrap().len(), 2);
+
+    assert!(!db
+        .change_prop_key_name("state".to_string(), "x".to_string())
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn cloned_handles_share_the_pool() {
+    let db = db().await;
+    let clone = db.clone();
+    clone.new_page(&page("p1", "/")).await.unwrap();
+    assert_eq!(db.get_pages_by_path("/".to_string()).await.unwrap().len(), 1);
+}
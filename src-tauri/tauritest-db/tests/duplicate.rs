@@ -0,0 +1,146 @@
+use serde_json::{json, Value};
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson, TagEntity};
+
+fn page(id: &str, path: &str, title: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: path.to_string(),
+        title: title.to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn bloc(id: &str, page_id: &str, position: &str, url: &str) -> BlocJson {
+    let content = json!({
+        "type": "paragraph",
+        "children": [
+            { "type": "text", "text": "voir " },
+            { "type": "link", "url": url, "children": [{ "type": "text", "text": "ici" }] },
+            { "type": "image", "src": "asset://photo.png" },
+        ],
+        "$": { "id": id, "position": position },
+    });
+    BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: content.to_string(),
+        page_id: page_id.to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+async fn setup() -> Database {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "/projets/alpha", "Alpha")).await.unwrap();
+    db.new_page(&page("p2", "/projets/alpha/notes", "Notes")).await.unwrap();
+    db.new_page(&page("p3", "/projets/alphabet", "Ailleurs")).await.unwrap();
+    db.new_bloc(&bloc("b1", "p1", "a0", "page://p2")).await.unwrap();
+    db.new_bloc(&bloc("b2", "p1", "a1", "https://example.com")).await.unwrap();
+    db.new_bloc(&bloc("b3", "p2", "a0", "page://p1#b2")).await.unwrap();
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "status".to_string(),
+        value: "todo".to_string(),
+        bloc_id: "b1".to_string(),
+    })
+    .await
+    .unwrap();
+    db.tag_entity(TagEntity::Page, "p1".to_string(), "projet".to_string()).await.unwrap();
+    db.tag_entity(TagEntity::Bloc, "b1".to_string(), "important".to_string()).await.unwrap();
+    db
+}
+
+fn link(bloc: &BlocJson) -> String {
+    let node: Value = serde_json::from_str(&bloc.content).unwrap();
+    node["children"][1]["url"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn duplicates_a_page_with_blocs_props_and_tags() {
+    let db = setup().await;
+    let copy_id = db.duplicate_page("p1".to_string(), "/archive".to_string(), true).await.unwrap();
+
+    let copy = db.get_page_by_id(copy_id.clone()).await.unwrap();
+    assert_eq!((copy.path.as_str(), copy.title.as_str()), ("/archive", "Alpha"));
+
+    let blocs = db.get_blocs_by_page_id(copy_id.clone()).await.unwrap();
+    assert_eq!(blocs.iter().map(|b| b.position.as_str()).collect::<Vec<_>>(), ["a0", "a1"]);
+    let first = &blocs[0];
+    let first_id = first.id.clone().unwrap();
+    assert_ne!(first_id, "b1");
+    let node: Value = serde_json::from_str(&first.content).unwrap();
+    assert_eq!(node["$"]["id"], json!(first_id));
+    assert_eq!(node["children"][2]["src"], json!("asset://photo.png"));
+    // p2 n'est pas copiée : le lien reste sur l'original
+    assert_eq!(link(first), "page://p2");
+
+    let props = db.get_props_by_bloc_id(first_id.clone()).await.unwrap();
+    assert_eq!((props[0].key.as_str(), props[0].value.as_str()), ("status", "todo"));
+    let names = |tags: Vec<tauritest_db::TagJson>| tags.into_iter().map(|t| t.name).collect::<Vec<_>>();
+    assert_eq!(names(db.get_entity_tags(TagEntity::Page, copy_id).await.unwrap()), ["projet"]);
+    assert_eq!(names(db.get_entity_tags(TagEntity::Bloc, first_id).await.unwrap()), ["important"]);
+
+    // l'original est intact
+    assert_eq!(db.get_blocs_by_page_id("p1".to_string()).await.unwrap()[0].id.as_deref(), Some("b1"));
+    assert!(db.duplicate_page("absente".to_string(), "/".to_string(), false).await.is_err());
+}
+
+#[tokio::test]
+async fn copies_are_previewed_and_their_headings_indexed() {
+    let db = setup().await;
+    db.new_bloc(&BlocJson {
+        id: Some("h1".to_string()),
+        position: "a2".to_string(),
+        content: json!({ "type": "heading", "tag": "h2", "children": [{ "type": "text", "text": "Calendrier" }] })
+            .to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "heading".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    let copy_id = db.duplicate_page("p1".to_string(), "/archive".to_string(), false).await.unwrap();
+
+    // indexés dès la copie, sans attendre un enregistrement de la page
+    let hits = db.search_headings("calendrier".to_string(), 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!((hits[0].page_id.as_str(), hits[0].anchor.as_str()), (copy_id.as_str(), "calendrier"));
+    let preview = db.refresh_page_preview(copy_id).await.unwrap();
+    assert_eq!(preview.outline.len(), 1);
+}
+
+#[tokio::test]
+async fn duplicates_a_subtree_and_rewrites_internal_links() {
+    let db = setup().await;
+    let report = db.duplicate_subtree("/projets/alpha/".to_string(), "/modeles".to_string(), true).await.unwrap();
+
+    // "/projets/alphabet" n'est pas dans le dossier
+    assert_eq!(report.pages.len(), 2);
+    assert_eq!((report.blocs, report.props, report.taggings, report.links_rewritten), (3, 1, 2, 2));
+    let p1 = report.pages["p1"].clone();
+    let p2 = report.pages["p2"].clone();
+    assert_eq!(db.get_page_by_id(p1.clone()).await.unwrap().path, "/modeles/alpha");
+    assert_eq!(db.get_page_by_id(p2.clone()).await.unwrap().path, "/modeles/alpha/notes");
+
+    let p1_blocs = db.get_blocs_by_page_id(p1.clone()).await.unwrap();
+    let p2_blocs = db.get_blocs_by_page_id(p2.clone()).await.unwrap();
+    assert_eq!(link(&p1_blocs[0]), format!("page://{}", p2));
+    assert_eq!(link(&p1_blocs[1]), "https://example.com");
+    assert_eq!(link(&p2_blocs[0]), format!("page://{}#{}", p1, p1_blocs[1].id.clone().unwrap()));
+
+    // sans réécriture, les liens visent toujours les originaux
+    let report = db.duplicate_subtree("/projets/alpha".to_string(), "/copie".to_string(), false).await.unwrap();
+    let blocs = db.get_blocs_by_page_id(report.pages["p1"].clone()).await.unwrap();
+    assert_eq!(link(&blocs[0]), "page://p2");
+    assert_eq!(report.links_rewritten, 0);
+
+    // une seule étape d'annulation par copie
+    db.undo().await.unwrap();
+    assert!(db.get_page_by_id(report.pages["p1"].clone()).await.is_err());
+    assert!(db.get_page_by_id(p1).await.is_ok());
+}
@@ -0,0 +1,145 @@
+use chrono::NaiveDate;
+use serde_json::json;
+use tauritest_db::flashcards::{sm2, split_card, MAX_INTERVAL_DAYS};
+use tauritest_db::{BlocJson, CardSchedule, Database, PageJson, PropsJson};
+
+fn day(text: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+}
+
+async fn add(db: &Database, id: &str, position: &str, text: &str) {
+    let content = json!({ "type": "paragraph", "children": [{ "type": "text", "text": text }] });
+    db.new_bloc(&BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: content.to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+}
+
+#[test]
+fn sm2_schedules_reviews() {
+    let first = sm2(CardSchedule::default(), 4);
+    assert_eq!((first.interval_days, first.repetitions), (1, 1));
+    assert!((first.ease - 2.5).abs() < 1e-9);
+    let second = sm2(first, 5);
+    assert_eq!(second.interval_days, 6);
+    assert!((second.ease - 2.6).abs() < 1e-9);
+    let third = sm2(second, 3);
+    // intervalle précédent multiplié par la facilité d'avant la révision
+    assert_eq!(third.interval_days, 16);
+    assert!(third.ease < second.ease);
+
+    let lapse = sm2(third, 1);
+    assert_eq!((lapse.interval_days, lapse.repetitions, lapse.lapses), (1, 0, 1));
+    // la facilité ne descend pas sous 1,3
+    let mut schedule = CardSchedule::default();
+    for _ in 0..20 {
+        schedule = sm2(schedule, 0);
+    }
+    assert!((schedule.ease - 1.3).abs() < 1e-9);
+
+    // une longue suite de bonnes notes reste sous le plafond
+    let mut schedule = CardSchedule::default();
+    for _ in 0..40 {
+        schedule = sm2(schedule, 5);
+    }
+    assert_eq!(schedule.interval_days, MAX_INTERVAL_DAYS);
+}
+
+#[test]
+fn splits_question_and_answer() {
+    assert_eq!(split_card("Capitale de l'Italie :: Rome", None), ("Capitale de l'Italie".to_string(), "Rome".to_string()));
+    assert_eq!(split_card("a :: b", Some("c")), ("a :: b".to_string(), "c".to_string()));
+    assert_eq!(split_card("sans réponse", None), ("sans réponse".to_string(), String::new()));
+}
+
+#[tokio::test]
+async fn reviews_due_cards_and_keeps_history() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&PageJson {
+        id: Some("p1".to_string()),
+        path: "/".to_string(),
+        title: "Géographie".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    add(&db, "b1", "a0", "Capitale de l'Italie :: Rome").await;
+    add(&db, "b2", "a1", "Plus long fleuve de France").await;
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "answer".to_string(),
+        value: "La Loire".to_string(),
+        bloc_id: "b2".to_string(),
+    })
+    .await
+    .unwrap();
+
+    let monday = day("2026-03-02");
+    assert!(db.mark_flashcard("b1".to_string(), monday).await.unwrap());
+    assert!(!db.mark_flashcard("b1".to_string(), monday).await.unwrap());
+    assert!(db.mark_flashcard("b2".to_string(), day("2026-03-03")).await.unwrap());
+    assert!(db.mark_flashcard("absent".to_string(), monday).await.is_err());
+
+    let due = db.get_due_cards(monday, 10).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!((due[0].question.as_str(), due[0].answer.as_str()), ("Capitale de l'Italie", "Rome"));
+    assert_eq!(due[0].page_title, "Géographie");
+
+    let review = db.record_review("b1".to_string(), 4, monday).await.unwrap();
+    assert_eq!((review.interval_days, review.due.as_str()), (1, "2026-03-03"));
+    assert!(db.record_review("b1".to_string(), 6, monday).await.is_err());
+    assert!(db.record_review("b3".to_string(), 4, monday).await.is_err());
+    assert!(db.get_due_cards(monday, 10).await.unwrap().is_empty());
+
+    let tuesday = day("2026-03-03");
+    let due = db.get_due_cards(tuesday, 10).await.unwrap();
+    assert_eq!(due.iter().map(|c| c.answer.as_str()).collect::<Vec<_>>(), ["Rome", "La Loire"]);
+    let review = db.record_review("b1".to_string(), 5, tuesday).await.unwrap();
+    assert_eq!(review.due, "2026-03-09");
+    db.record_review("b2".to_string(), 1, tuesday).await.unwrap();
+
+    let history = db.get_card_reviews("b1".to_string()).await.unwrap();
+    assert_eq!(history.iter().map(|r| r.grade).collect::<Vec<_>>(), [4, 5]);
+    let due = db.get_due_cards(day("2026-03-04"), 10).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].schedule.lapses, 1);
+
+    assert!(db.unmark_flashcard("b1".to_string()).await.unwrap());
+    assert!(db.get_card_reviews("b1".to_string()).await.unwrap().is_empty());
+    assert!(db.get_due_cards(day("2026-12-31"), 10).await.unwrap().iter().all(|c| c.bloc_id == "b2"));
+}
+
+#[tokio::test]
+async fn long_streaks_stay_schedulable() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&PageJson {
+        id: Some("p1".to_string()),
+        path: "/".to_string(),
+        title: "Géographie".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    add(&db, "b1", "a0", "Capitale de l'Italie :: Rome").await;
+    let today = day("2026-03-02");
+    db.mark_flashcard("b1".to_string(), today).await.unwrap();
+
+    let mut review = None;
+    for _ in 0..30 {
+        review = Some(db.record_review("b1".to_string(), 5, today).await.unwrap());
+    }
+    let review = review.unwrap();
+    assert_eq!(review.interval_days, MAX_INTERVAL_DAYS);
+    assert_eq!(review.due, "2126-02-06");
+}
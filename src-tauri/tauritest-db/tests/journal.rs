@@ -0,0 +1,170 @@
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use tauritest_db::{BlocJson, Database, JournalOptions, PageJson, TemplateJson};
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+}
+
+fn checklist(id: &str, page_id: &str, items: &[(&str, bool)]) -> BlocJson {
+    let items: Vec<Value> = items
+        .iter()
+        .enumerate()
+        .map(|(i, (text, checked))| {
+            json!({
+                "type": "listitem",
+                "value": i + 1,
+                "checked": checked,
+                "children": [{ "type": "text", "text": text }],
+            })
+        })
+        .collect();
+    let content = json!({ "type": "list", "listType": "check", "tag": "ul", "children": items });
+    BlocJson {
+        id: Some(id.to_string()),
+        // "b1" -> "a1" : une position distincte par bloc de la page
+        position: format!("a{}", &id[1..]),
+        content: content.to_string(),
+        page_id: page_id.to_string(),
+        bloc_type: "list".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+#[tokio::test]
+async fn daily_page_is_created_once_per_day() {
+    let db = Database::new_in_memory().await.unwrap();
+    let options = JournalOptions::default();
+
+    let page = db.get_or_create_daily_page(date(3), &options).await.unwrap();
+    assert_eq!((page.path.as_str(), page.title.as_str()), ("/journal", "2024-05-03"));
+    let again = db.get_or_create_daily_page(date(3), &options).await.unwrap();
+    assert_eq!(again.id, page.id);
+    assert_eq!(db.get_daily_page_date(page.id.clone().unwrap()).await.unwrap(), Some(date(3)));
+
+    // page supprimée : recréée
+    db.delete_page(page.id.clone().unwrap()).await.unwrap();
+    let recreated = db.get_or_create_daily_page(date(3), &options).await.unwrap();
+    assert_ne!(recreated.id, page.id);
+}
+
+#[tokio::test]
+async fn navigates_between_existing_days() {
+    let db = Database::new_in_memory().await.unwrap();
+    let options = JournalOptions { path: "/daily".to_string(), ..JournalOptions::default() };
+    for day in [2, 5, 9] {
+        db.get_or_create_daily_page(date(day), &options).await.unwrap();
+    }
+
+    let previous = db.get_previous_daily_page(date(5)).await.unwrap().unwrap();
+    assert_eq!(previous.day, "2024-05-02");
+    assert_eq!(db.get_next_daily_page(date(5)).await.unwrap().unwrap().day, "2024-05-09");
+    assert_eq!(db.get_next_daily_page(date(6)).await.unwrap().unwrap().day, "2024-05-09");
+    assert!(db.get_previous_daily_page(date(2)).await.unwrap().is_none());
+    assert!(db.get_next_daily_page(date(9)).await.unwrap().is_none());
+
+    let calendar = db.get_journal_calendar(date(1), date(6)).await.unwrap();
+    let days: Vec<&str> = calendar.iter().map(|day| day.day.as_str()).collect();
+    assert_eq!(days, vec!["2024-05-02", "2024-05-05"]);
+    assert_eq!(calendar[0].bloc_count, 0);
+}
+
+#[tokio::test]
+async fn rolls_unchecked_items_over_from_the_previous_day() {
+    let db = Database::new_in_memory().await.unwrap();
+    let options = JournalOptions { rollover: true, ..JournalOptions::default() };
+
+    let monday = db.get_or_create_daily_page(date(6), &options).await.unwrap();
+    let monday_id = monday.id.unwrap();
+    db.new_bloc(&checklist("b1", &monday_id, &[("done", true), ("todo 1", false), ("todo 2", false)]))
+        .await
+        .unwrap();
+    db.new_bloc(&checklist("b2", &monday_id, &[("all done", true)])).await.unwrap();
+
+    // mardi n'a pas été ouvert : mercredi reprend lundi
+    let wednesday = db.get_or_create_daily_page(date(8), &options).await.unwrap();
+    let blocs = db.get_blocs_by_page_id(wednesday.id.unwrap()).await.unwrap();
+    assert_eq!(blocs.len(), 1);
+    let list: Value = serde_json::from_str(&blocs[0].content).unwrap();
+    let texts: Vec<&str> = list["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["children"][0]["text"].as_str().unwrap())
+        .collect();
+    assert_eq!(texts, vec!["todo 1", "todo 2"]);
+    assert_eq!(list["children"][0]["value"], 1);
+    assert_eq!(list["$"]["id"], blocs[0].id.clone().unwrap().as_str());
+
+    // la page de lundi n'est pas modifiée
+    assert_eq!(db.get_blocs_by_page_id(monday_id).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn new_daily_pages_use_the_template() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&PageJson {
+        id: Some("t1".to_string()),
+        path: "/templates".to_string(),
+        title: "journal".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    db.new_bloc(&checklist("b1", "t1", &[("Revue du {{date}}", false)])).await.unwrap();
+    db.set_template(&TemplateJson {
+        page_id: "t1".to_string(),
+        name: "journal".to_string(),
+        recurrence: None,
+        target_path: "/".to_string(),
+        title_pattern: "{{title}}".to_string(),
+        last_run: None,
+    })
+    .await
+    .unwrap();
+
+    let options = JournalOptions {
+        title_format: "%d/%m/%Y".to_string(),
+        template_id: Some("t1".to_string()),
+        ..JournalOptions::default()
+    };
+    let page = db.get_or_create_daily_page(date(4), &options).await.unwrap();
+    assert_eq!((page.path.as_str(), page.title.as_str()), ("/journal", "04/05/2024"));
+    let blocs = db.get_blocs_by_page_id(page.id.unwrap()).await.unwrap();
+    assert!(blocs[0].content.contains("Revue du 2024-05-04"));
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_title_format() {
+    let db = Database::new_in_memory().await.unwrap();
+    let options = JournalOptions { title_format: "%Q".to_string(), ..JournalOptions::default() };
+    assert!(db.get_or_create_daily_page(date(1), &options).await.is_err());
+    assert!(db.get_journal_calendar(date(1), date(31)).await.unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_calls_share_one_daily_page() {
+    let dir = std::env::temp_dir().join(format!("tauritest-db-journal-{}", std::process::id()));
+    let db = Database::new(dir.join("notes.db").to_str().unwrap()).await.unwrap();
+    let options = JournalOptions::default();
+
+    let calls: Vec<_> = (0..8)
+        .map(|_| {
+            let (db, options) = (db.clone(), options.clone());
+            tokio::spawn(async move { db.get_or_create_daily_page(date(3), &options).await.unwrap() })
+        })
+        .collect();
+    let mut pages = Vec::new();
+    for call in calls {
+        pages.push(call.await.unwrap());
+    }
+    let first = pages[0].id.clone();
+    assert!(pages.iter().all(|page| page.id == first));
+    assert_eq!(db.get_pages_by_path("/journal".to_string()).await.unwrap().len(), 1);
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(dir);
+}
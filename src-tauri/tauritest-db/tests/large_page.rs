@@ -0,0 +1,74 @@
+// Seul test du binaire : l'allocateur compte les octets de tout le processus.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauritest_db::{fractional_index, BlocJson, Database};
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const BLOCS: usize = 100_000;
+const CHUNK: i64 = 500;
+// le contenu seul pèse ~40 Mo : une lecture d'un bloc dépasserait largement
+const MEMORY_BOUND: usize = 8 * 1024 * 1024;
+
+#[tokio::test]
+async fn streams_a_100k_bloc_page_within_a_memory_bound() {
+    let db = Database::new_in_memory().await.unwrap();
+    {
+        let text = "x".repeat(400);
+        let positions = fractional_index::generate_n_keys_between(None, None, BLOCS).unwrap();
+        let blocs: Vec<BlocJson> = positions
+            .into_iter()
+            .enumerate()
+            .map(|(i, position)| BlocJson {
+                id: Some(format!("b{:06}", i)),
+                position,
+                content: format!("{{\"type\":\"paragraph\",\"text\":\"{}\"}}", text),
+                page_id: "big".to_string(),
+                bloc_type: "paragraph".to_string(),
+                created_at: 0,
+                updated_at: 0,
+            })
+            .collect();
+        db.replace_page_blocs("big".to_string(), &blocs).await.unwrap();
+    }
+
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    let mut last = String::new();
+    let count = db
+        .stream_blocs_by_page_id("big".to_string(), CHUNK, |blocs, _| {
+            assert!(blocs.len() as i64 <= CHUNK);
+            assert!(blocs[0].position > last);
+            last = blocs.last().unwrap().position.clone();
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    let used = PEAK.load(Ordering::Relaxed).saturating_sub(baseline);
+    assert_eq!(count, BLOCS);
+    assert!(used < MEMORY_BOUND, "peak {} bytes above baseline", used);
+}
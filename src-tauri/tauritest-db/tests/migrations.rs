@@ -0,0 +1,44 @@
+use tauritest_db::migrations::MIGRATIONS;
+use tauritest_db::Database;
+
+#[tokio::test]
+async fn new_database_is_at_latest_version() {
+    let db = Database::new_in_memory().await.unwrap();
+    assert_eq!(
+        db.schema_version().await.unwrap(),
+        Database::latest_schema_version()
+    );
+}
+
+#[tokio::test]
+async fn migrate_is_idempotent() {
+    let db = Database::new_in_memory().await.unwrap();
+    let report = db.migrate().await.unwrap();
+    assert!(report.applied.is_empty());
+    assert_eq!(report.from_version, report.to_version);
+}
+
+#[tokio::test]
+async fn connect_does_not_migrate() {
+    let dir = std::env::temp_dir().join(format!("tauritest-db-migrate-{}", std::process::id()));
+    let path = dir.join("notes.db");
+    let path = path.to_str().unwrap();
+
+    let db = Database::connect(path).await.unwrap();
+    assert_eq!(db.schema_version().await.unwrap(), 0);
+
+    let report = db.migrate().await.unwrap();
+    assert_eq!(report.from_version, 0);
+    assert_eq!(report.applied.len(), MIGRATIONS.len());
+    assert_eq!(report.to_version, Database::latest_schema_version());
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn versions_are_strictly_increasing() {
+    for pair in MIGRATIONS.windows(2) {
+        assert!(pair[0].version < pair[1].version);
+    }
+}
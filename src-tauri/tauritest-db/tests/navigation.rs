@@ -0,0 +1,81 @@
+use tauritest_db::{Database, PageJson, TabJson, TabSession};
+
+async fn setup() -> Database {
+    let db = Database::new_in_memory().await.unwrap();
+    for id in ["p1", "p2", "p3"] {
+        db.new_page(&PageJson {
+            id: Some(id.to_string()),
+            path: "/notes".to_string(),
+            title: format!("page {}", id),
+            cache: String::new(),
+            created_at: 0,
+            updated_at: 0,
+        })
+        .await
+        .unwrap();
+    }
+    db
+}
+
+fn ids(pages: &[tauritest_db::PageVisits]) -> Vec<&str> {
+    pages.iter().map(|page| page.page_id.as_str()).collect()
+}
+
+#[tokio::test]
+async fn records_visits_with_recent_and_frequent_queries() {
+    let db = setup().await;
+    for page_id in ["p1", "p2", "p1", "p3", "p1", "p2"] {
+        db.record_visit(page_id.to_string(), None).await.unwrap();
+    }
+    // même page que la dernière visite : mise à jour, pas de nouvelle visite
+    let last = db.record_visit("p2".to_string(), Some("{\"scroll\":120}".to_string())).await.unwrap();
+    assert!(db.update_visit_anchor(last, Some("{\"scroll\":300}".to_string())).await.unwrap());
+
+    let visits = db.get_visits(10, None).await.unwrap();
+    assert_eq!(visits.len(), 6);
+    assert_eq!((visits[0].title.as_str(), visits[0].anchor.as_deref()), ("page p2", Some("{\"scroll\":300}")));
+    let older = db.get_visits(2, Some(visits[1].id)).await.unwrap();
+    assert_eq!(older.iter().map(|visit| visit.id).collect::<Vec<_>>(), vec![visits[2].id, visits[3].id]);
+
+    assert_eq!(ids(&db.get_recent_pages(10).await.unwrap()), vec!["p2", "p1", "p3"]);
+    let frequent = db.get_frequent_pages(2, None).await.unwrap();
+    assert_eq!(ids(&frequent), vec!["p1", "p2"]);
+    assert_eq!(frequent[0].visit_count, 3);
+    assert_eq!(frequent[1].anchor.as_deref(), Some("{\"scroll\":300}"));
+
+    // page supprimée : absente des listes
+    db.delete_page("p1".to_string()).await.unwrap();
+    assert_eq!(ids(&db.get_recent_pages(10).await.unwrap()), vec!["p2", "p3"]);
+    assert_eq!(db.delete_page_visits("p2".to_string()).await.unwrap(), 2);
+    db.clear_visits().await.unwrap();
+    assert!(db.get_visits(10, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn tab_sessions_are_kept_per_window() {
+    let db = setup().await;
+    let tab = |page_id: &str, pinned: bool| TabJson {
+        page_id: page_id.to_string(),
+        anchor: None,
+        pinned,
+    };
+    let session = TabSession {
+        tabs: vec![tab("p1", true), tab("p2", false), tab("p3", false)],
+        active: Some(1),
+    };
+    db.save_tab_session("main".to_string(), &session).await.unwrap();
+    db.save_tab_session("other".to_string(), &TabSession { tabs: vec![tab("p3", false)], active: None })
+        .await
+        .unwrap();
+    assert_eq!(db.get_tab_session("main".to_string()).await.unwrap(), session);
+
+    // l'onglet actif suit quand une page disparaît
+    db.delete_page("p1".to_string()).await.unwrap();
+    let restored = db.get_tab_session("main".to_string()).await.unwrap();
+    assert_eq!(restored.tabs.len(), 2);
+    assert_eq!(restored.tabs[restored.active.unwrap()].page_id, "p2");
+
+    db.save_tab_session("main".to_string(), &TabSession::default()).await.unwrap();
+    assert!(db.get_tab_session("main".to_string()).await.unwrap().tabs.is_empty());
+    assert_eq!(db.get_tab_session("other".to_string()).await.unwrap().tabs.len(), 1);
+}
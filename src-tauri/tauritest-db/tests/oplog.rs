@@ -0,0 +1,151 @@
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson};
+
+fn bloc(id: &str, content: &str) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        // "b1" -> "a1" : une position distincte par bloc de la page
+        position: format!("a{}", &id[1..]),
+        content: content.to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn summary(db_ops: &[tauritest_db::Operation]) -> Vec<(String, String, String)> {
+    db_ops
+        .iter()
+        .map(|o| (o.op.clone(), o.entity.clone(), o.entity_id.clone().unwrap_or_default()))
+        .collect()
+}
+
+fn op(op: &str, entity: &str, id: &str) -> (String, String, String) {
+    (op.to_string(), entity.to_string(), id.to_string())
+}
+
+#[tokio::test]
+async fn mutations_are_logged_in_order() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&PageJson {
+        id: Some("p1".to_string()),
+        path: "/".to_string(),
+        title: "page".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    db.new_bloc(&bloc("b1", "one")).await.unwrap();
+    db.update_bloc_content("b1".to_string(), "two".to_string(), 1).await.unwrap();
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "k".to_string(),
+        value: "v".to_string(),
+        bloc_id: "b1".to_string(),
+    })
+    .await
+    .unwrap();
+    db.delete_bloc("b1".to_string()).await.unwrap();
+
+    let feed = db.changes_since(0, 100).await.unwrap();
+    assert_eq!(
+        summary(&feed.operations),
+        vec![
+            op("insert", "page", "p1"),
+            op("insert", "bloc", "b1"),
+            op("update", "bloc", "b1"),
+            op("insert", "prop", "r1"),
+            // la prop date son bloc pour la synchronisation
+            op("update", "bloc", "b1"),
+            op("delete", "bloc", "b1"),
+        ]
+    );
+    assert!(!feed.has_more);
+    assert_eq!(feed.cursor, db.latest_cursor().await.unwrap());
+
+    let update = &feed.operations[2];
+    assert_eq!(update.before_checksum, feed.operations[1].after_checksum);
+    assert_ne!(update.before_checksum, update.after_checksum);
+    assert_eq!(update.page_id.as_deref(), Some("p1"));
+    assert_eq!(feed.operations[3].page_id.as_deref(), Some("p1"));
+    // une suppression garde une trace de ce qui a disparu
+    assert_eq!(feed.operations[5].before_checksum, update.after_checksum);
+    assert!(feed.operations[5].after_checksum.is_none());
+}
+
+#[tokio::test]
+async fn cursor_pages_through_the_feed() {
+    let db = Database::new_in_memory().await.unwrap();
+    for i in 0..5 {
+        db.new_bloc(&bloc(&format!("b{}", i), "x")).await.unwrap();
+    }
+
+    let first = db.changes_since(0, 2).await.unwrap();
+    assert_eq!(first.operations.len(), 2);
+    assert!(first.has_more);
+    let second = db.changes_since(first.cursor, 10).await.unwrap();
+    assert_eq!(second.operations.len(), 3);
+    assert!(!second.has_more);
+    assert_eq!(second.operations[0].entity_id.as_deref(), Some("b2"));
+
+    // rien de nouveau : le curseur ne bouge pas
+    let empty = db.changes_since(second.cursor, 10).await.unwrap();
+    assert!(empty.operations.is_empty());
+    assert_eq!(empty.cursor, second.cursor);
+
+    // une limite < 1 ne ferait jamais avancer le curseur
+    assert!(db.changes_since(0, 0).await.is_err());
+    assert!(db.changes_since(0, -1).await.is_err());
+}
+
+#[tokio::test]
+async fn no_op_updates_and_bulk_deletes() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_bloc(&bloc("b1", "x")).await.unwrap();
+    db.new_bloc(&bloc("b2", "y")).await.unwrap();
+    let cursor = db.latest_cursor().await.unwrap();
+
+    // même valeur : pas d'opération
+    db.update_bloc_page_id("b1".to_string(), "p1".to_string()).await.unwrap();
+    assert!(db.changes_since(cursor, 10).await.unwrap().operations.is_empty());
+
+    // une suppression en masse journalise chaque ligne
+    db.delete_bloc_by_page_id("p1".to_string()).await.unwrap();
+    let feed = db.changes_since(cursor, 10).await.unwrap();
+    assert_eq!(feed.operations.len(), 2);
+    assert!(feed.operations.iter().all(|o| o.op == "delete"));
+
+}
+
+#[tokio::test]
+async fn rolled_back_writes_leave_no_operation() {
+    let db = Database::new_in_memory().await.unwrap();
+    let before = db.latest_cursor().await.unwrap();
+
+    // deux commentaires avec le même id : l'insertion du fil est annulée
+    let comment = tauritest_db::CommentJson {
+        id: Some("c1".to_string()),
+        thread_id: String::new(),
+        author: "me".to_string(),
+        content: "x".to_string(),
+        deleted: false,
+        created_at: 0,
+        updated_at: 0,
+    };
+    let result = db
+        .new_thread(&tauritest_db::ThreadJson {
+            id: Some("t1".to_string()),
+            bloc_id: "b1".to_string(),
+            page_id: None,
+            quote: String::new(),
+            resolved: false,
+            created_at: 0,
+            updated_at: 0,
+            comments: vec![comment.clone(), comment],
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(db.latest_cursor().await.unwrap(), before);
+}
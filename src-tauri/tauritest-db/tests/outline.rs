@@ -0,0 +1,102 @@
+use serde_json::json;
+use tauritest_db::outline::{anchors, slugify};
+use tauritest_db::{BlocJson, Database, OutlineEntry, PageJson};
+
+fn page(id: &str, title: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: "/notes".to_string(),
+        title: title.to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn heading(id: &str, page_id: &str, position: &str, tag: &str, text: &str) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: json!({ "type": "heading", "tag": tag, "children": [{ "type": "text", "text": text }] })
+            .to_string(),
+        page_id: page_id.to_string(),
+        bloc_type: "heading".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+#[test]
+fn slugs_are_lowercase_and_dashed() {
+    assert_eq!(slugify("Étape 2 : déploiement !"), "étape-2-déploiement");
+    assert_eq!(slugify("  ?? "), "heading");
+}
+
+#[test]
+fn suffixed_anchors_do_not_collide_with_other_headings() {
+    let entries = |texts: &[&str]| -> Vec<OutlineEntry> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| OutlineEntry { level: 1, text: text.to_string(), bloc_id: format!("b{}", i) })
+            .collect()
+    };
+    assert_eq!(anchors(&entries(&["Notes", "Notes", "Notes 1"])), vec!["notes", "notes-1", "notes-1-1"]);
+    assert_eq!(anchors(&entries(&["Notes 1", "Notes", "Notes"])), vec!["notes-1", "notes", "notes-2"]);
+}
+
+#[tokio::test]
+async fn nests_headings_with_unique_anchors() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "Guide")).await.unwrap();
+    for (i, (tag, text)) in [("h1", "Intro"), ("h2", "Notes"), ("h3", "Détail"), ("h2", "Notes"), ("h1", "Fin"), ("h3", "Annexe")]
+        .iter()
+        .enumerate()
+    {
+        db.new_bloc(&heading(&format!("b{}", i), "p1", &format!("a{}", i), tag, text)).await.unwrap();
+    }
+
+    let outline = db.get_page_outline("p1".to_string()).await.unwrap();
+    assert_eq!(outline.len(), 2);
+    let intro = &outline[0];
+    assert_eq!((intro.text.as_str(), intro.bloc_id.as_str(), intro.anchor.as_str()), ("Intro", "b0", "intro"));
+    let anchors: Vec<&str> = intro.children.iter().map(|node| node.anchor.as_str()).collect();
+    assert_eq!(anchors, vec!["notes", "notes-1"]);
+    assert_eq!(intro.children[0].children[0].text, "Détail");
+    // niveau sauté : h3 directement sous h1
+    assert_eq!(outline[1].children[0].level, 3);
+}
+
+#[tokio::test]
+async fn indexes_headings_across_the_workspace() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "Projet")).await.unwrap();
+    db.new_page(&page("p2", "Réunions")).await.unwrap();
+    db.new_bloc(&heading("b1", "p1", "a0", "h2", "Planning 100%")).await.unwrap();
+    db.new_bloc(&heading("b2", "p2", "a0", "h1", "Ordre du jour et planning")).await.unwrap();
+
+    assert_eq!(db.rebuild_heading_index().await.unwrap(), 2);
+    let hits = db.search_headings("planning".to_string(), 10).await.unwrap();
+    let pages: Vec<&str> = hits.iter().map(|hit| hit.page_title.as_str()).collect();
+    // commence par la recherche : en premier
+    assert_eq!(pages, vec!["Projet", "Réunions"]);
+    assert_eq!((hits[0].bloc_id.as_str(), hits[0].anchor.as_str()), ("b1", "planning-100"));
+    assert_eq!(db.search_headings("100%".to_string(), 10).await.unwrap().len(), 1);
+    assert!(db.search_headings("_".to_string(), 10).await.unwrap().is_empty());
+
+    // l'index suit les modifications à l'enregistrement de la page
+    db.update_bloc_content(
+        "b1".to_string(),
+        json!({ "type": "heading", "tag": "h2", "children": [{ "type": "text", "text": "Budget" }] }).to_string(),
+        1,
+    )
+    .await
+    .unwrap();
+    db.refresh_page_preview("p1".to_string()).await.unwrap();
+    assert_eq!(db.search_headings("planning".to_string(), 10).await.unwrap().len(), 1);
+    assert_eq!(db.search_headings("budget".to_string(), 10).await.unwrap()[0].page_id, "p1");
+
+    // page supprimée : plus de résultats
+    db.delete_page("p2".to_string()).await.unwrap();
+    assert!(db.search_headings("planning".to_string(), 10).await.unwrap().is_empty());
+}
@@ -0,0 +1,177 @@
+use tauritest_db::{BlocJson, Database, PageMerge, PropsJson};
+
+fn bloc(id: &str, position: &str) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: format!("{{\"type\":\"paragraph\",\"id\":\"{}\"}}", id),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+#[tokio::test]
+async fn updates_are_kept_in_arrival_order_per_page() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.append_page_update("p1".to_string(), &[1, 2]).await.unwrap();
+    db.append_page_update("p2".to_string(), &[9]).await.unwrap();
+    db.append_page_update("p1".to_string(), &[3]).await.unwrap();
+
+    let log = db.get_page_updates("p1".to_string()).await.unwrap();
+    assert_eq!(log.updates, vec![vec![1, 2], vec![3]]);
+    assert!(log.last_id > 0);
+
+    let empty = db.get_page_updates("nope".to_string()).await.unwrap();
+    assert!(empty.updates.is_empty());
+    assert_eq!(empty.last_id, 0);
+}
+
+#[tokio::test]
+async fn compaction_keeps_updates_that_arrived_later() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.append_page_update("p1".to_string(), &[1]).await.unwrap();
+    db.append_page_update("p1".to_string(), &[2]).await.unwrap();
+    let log = db.get_page_updates("p1".to_string()).await.unwrap();
+
+    // arrive pendant la fusion
+    db.append_page_update("p1".to_string(), &[3]).await.unwrap();
+    assert!(db.compact_page_updates("p1".to_string(), log.last_id, &[1, 2]).await.unwrap());
+
+    let log = db.get_page_updates("p1".to_string()).await.unwrap();
+    assert_eq!(log.updates, vec![vec![1, 2], vec![3]]);
+
+    // rien à compacter
+    assert!(!db.compact_page_updates("p2".to_string(), 100, &[0]).await.unwrap());
+
+    db.compact_page_updates("p1".to_string(), log.last_id, &[1, 2, 3]).await.unwrap();
+    db.append_page_update("p1".to_string(), &[4]).await.unwrap();
+    let log = db.get_page_updates("p1".to_string()).await.unwrap();
+    assert_eq!(log.updates, vec![vec![1, 2, 3], vec![4]]);
+}
+
+#[tokio::test]
+async fn compaction_does_not_collide_with_other_pages() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.append_page_update("p1".to_string(), &[1]).await.unwrap();
+    db.append_page_update("p1".to_string(), &[2]).await.unwrap();
+    let log = db.get_page_updates("p1".to_string()).await.unwrap();
+    db.append_page_update("p2".to_string(), &[9]).await.unwrap();
+    db.append_page_update("p1".to_string(), &[3]).await.unwrap();
+
+    assert!(db.compact_page_updates("p1".to_string(), log.last_id, &[1, 2]).await.unwrap());
+    assert_eq!(db.get_page_updates("p1".to_string()).await.unwrap().updates, vec![vec![1, 2], vec![3]]);
+    assert_eq!(db.get_page_updates("p2".to_string()).await.unwrap().updates, vec![vec![9]]);
+}
+
+#[tokio::test]
+async fn replace_page_blocs_swaps_the_whole_page() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_bloc(&bloc("old", "a0")).await.unwrap();
+    db.new_bloc(&BlocJson { page_id: "p2".to_string(), ..bloc("other", "a0") }).await.unwrap();
+
+    let count = db
+        .replace_page_blocs("p1".to_string(), &[bloc("b1", "a0"), bloc("b2", "a1")])
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+
+    let ids: Vec<String> = db
+        .get_blocs_by_page_id("p1".to_string())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|b| b.id.unwrap())
+        .collect();
+    assert_eq!(ids, vec!["b1", "b2"]);
+    assert_eq!(db.get_blocs_by_page_id("p2".to_string()).await.unwrap().len(), 1);
+    assert!(db.check_integrity().await.unwrap().checksum_mismatches.is_empty());
+}
+
+#[tokio::test]
+async fn replace_page_blocs_only_writes_what_changed() {
+    let db = Database::new_in_memory().await.unwrap();
+    for (id, position) in [("b1", "a0"), ("b2", "a1"), ("b3", "a2")] {
+        db.new_bloc(&bloc(id, position)).await.unwrap();
+    }
+    db.new_prop(&PropsJson {
+        id: Some("r3".to_string()),
+        key: "k".to_string(),
+        value: "v".to_string(),
+        bloc_id: "b3".to_string(),
+    })
+    .await
+    .unwrap();
+    let cursor = db.latest_cursor().await.unwrap();
+
+    // b1 inchangé, b2 modifié, b3 retiré, b4 ajouté
+    let merged = [bloc("b1", "a0"), BlocJson { content: "{}".to_string(), ..bloc("b2", "a1") }, bloc("b4", "a3")];
+    assert_eq!(db.replace_page_blocs("p1".to_string(), &merged).await.unwrap(), 2);
+
+    let mut changes: Vec<(String, String, String)> = db
+        .changes_since(cursor, 100)
+        .await
+        .unwrap()
+        .operations
+        .into_iter()
+        .map(|o| (o.op, o.entity, o.entity_id.unwrap_or_default()))
+        .collect();
+    changes.sort();
+    let op = |op: &str, entity: &str, id: &str| (op.to_string(), entity.to_string(), id.to_string());
+    assert_eq!(
+        changes,
+        vec![op("delete", "bloc", "b3"), op("delete", "prop", "r3"), op("insert", "bloc", "b4"), op("update", "bloc", "b2")]
+    );
+    let report = db.check_integrity().await.unwrap();
+    assert!(report.orphan_props.is_empty());
+    assert!(report.checksum_mismatches.is_empty());
+}
+
+#[tokio::test]
+async fn merge_page_reads_and_writes_in_one_step() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_bloc(&bloc("b1", "a0")).await.unwrap();
+    db.append_page_update("p1".to_string(), &[1]).await.unwrap();
+
+    let seen = db
+        .merge_page("p1".to_string(), |log, blocs| {
+            let seen = (log.updates.clone(), blocs.len());
+            let merge = PageMerge {
+                updates: vec![vec![2], vec![3]],
+                blocs: Some(vec![bloc("b2", "a1")]),
+                compacted: None,
+            };
+            Ok((merge, seen))
+        })
+        .await
+        .unwrap();
+    assert_eq!(seen, (vec![vec![1]], 1));
+    assert_eq!(db.get_page_updates("p1".to_string()).await.unwrap().updates, vec![vec![1], vec![2], vec![3]]);
+    let ids: Vec<String> = db.get_blocs_by_page_id("p1".to_string()).await.unwrap().into_iter().map(|b| b.id.unwrap()).collect();
+    assert_eq!(ids, vec!["b2"]);
+
+    // la compaction couvre aussi les mises à jour ajoutées par la fusion
+    db.merge_page("p1".to_string(), |_, _| {
+        Ok((PageMerge { updates: vec![vec![4]], blocs: None, compacted: Some(vec![1, 2, 3, 4]) }, ()))
+    })
+    .await
+    .unwrap();
+    assert_eq!(db.get_page_updates("p1".to_string()).await.unwrap().updates, vec![vec![1, 2, 3, 4]]);
+    assert_eq!(db.get_blocs_by_page_id("p1".to_string()).await.unwrap().len(), 1);
+
+    // une erreur de la fusion n'écrit rien
+    let failed = db
+        .merge_page("p1".to_string(), |_, _| -> anyhow::Result<(PageMerge, ())> { anyhow::bail!("boom") })
+        .await;
+    assert!(failed.is_err());
+    assert_eq!(db.get_page_updates("p1".to_string()).await.unwrap().updates.len(), 1);
+}
+
+#[tokio::test]
+async fn crdt_client_id_is_stable_per_database() {
+    let db = Database::new_in_memory().await.unwrap();
+    let id = db.crdt_client_id().await.unwrap();
+    assert!(id <= u32::MAX as u64);
+    assert_eq!(db.crdt_client_id().await.unwrap(), id);
+}
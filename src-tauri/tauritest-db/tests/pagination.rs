@@ -0,0 +1,99 @@
+use tauritest_db::{fractional_index, BlocJson, Database, PageJson};
+
+fn bloc(id: &str, position: &str) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: format!("{{\"text\":\"{}\"}}", id),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn page(id: &str, updated_at: i64) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: "/notes".to_string(),
+        title: id.to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at,
+    }
+}
+
+#[tokio::test]
+async fn bloc_chunks_follow_positions_across_cursors() {
+    let db = Database::new_in_memory().await.unwrap();
+    let positions = fractional_index::generate_n_keys_between(None, None, 25).unwrap();
+    for (i, position) in positions.iter().enumerate() {
+        db.new_bloc(&bloc(&format!("b{:02}", i), position)).await.unwrap();
+    }
+    // même position : départagés par l'id. new_bloc refuse une clé prise,
+    // le doublon arrive par un déplacement depuis une autre page
+    db.new_bloc(&BlocJson { page_id: "p2".to_string(), ..bloc("b99", &positions[9]) }).await.unwrap();
+    db.update_bloc_page_id("b99".to_string(), "p1".to_string()).await.unwrap();
+
+    let mut ids = Vec::new();
+    let mut after = None;
+    loop {
+        let chunk = db.get_blocs_chunk("p1".to_string(), after, 10).await.unwrap();
+        assert!(chunk.blocs.len() <= 10);
+        ids.extend(chunk.blocs.into_iter().map(|bloc| bloc.id.unwrap()));
+        match chunk.next {
+            Some(next) => after = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(ids.len(), 26);
+    assert_eq!(&ids[9..11], ["b09", "b99"]);
+    assert_eq!(ids.last().map(String::as_str), Some("b24"));
+
+    // exactement une tranche : pas de curseur suivant
+    let all = db.get_blocs_chunk("p1".to_string(), None, 26).await.unwrap();
+    assert!(all.next.is_none());
+    assert!(db.get_blocs_chunk("p1".to_string(), None, 0).await.is_err());
+}
+
+#[tokio::test]
+async fn streams_blocs_chunk_by_chunk() {
+    let db = Database::new_in_memory().await.unwrap();
+    let positions = fractional_index::generate_n_keys_between(None, None, 7).unwrap();
+    for (i, position) in positions.iter().enumerate() {
+        db.new_bloc(&bloc(&format!("b{}", i), position)).await.unwrap();
+    }
+
+    let mut sizes = Vec::new();
+    let count = db
+        .stream_blocs_by_page_id("p1".to_string(), 3, |blocs, _| {
+            sizes.push(blocs.len());
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert_eq!(count, 7);
+    assert_eq!(sizes, vec![3, 3, 1]);
+
+    // une erreur du consommateur arrête la lecture
+    let stopped = db
+        .stream_blocs_by_page_id("p1".to_string(), 3, |_, _| anyhow::bail!("window closed"))
+        .await;
+    assert!(stopped.is_err());
+}
+
+#[tokio::test]
+async fn page_chunks_start_with_the_latest_changes() {
+    let db = Database::new_in_memory().await.unwrap();
+    for (id, updated_at) in [("a", 10), ("b", 30), ("c", 20), ("d", 30)] {
+        db.new_page(&page(id, updated_at)).await.unwrap();
+    }
+
+    let first = db.get_pages_chunk("/notes".to_string(), None, 3).await.unwrap();
+    let titles: Vec<&str> = first.pages.iter().map(|page| page.title.as_str()).collect();
+    assert_eq!(titles, vec!["d", "b", "c"]);
+    let rest = db.get_pages_chunk("/notes".to_string(), first.next, 3).await.unwrap();
+    assert_eq!(rest.pages.len(), 1);
+    assert_eq!(rest.pages[0].title, "a");
+    assert!(rest.next.is_none());
+}
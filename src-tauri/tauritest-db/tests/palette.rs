@@ -0,0 +1,126 @@
+use serde_json::json;
+use tauritest_db::palette::fuzzy_score;
+use tauritest_db::{BlocJson, Database, PageJson, PaletteCommand, PaletteKind, TagEntity};
+
+fn chars(text: &str) -> Vec<char> {
+    text.chars().collect()
+}
+
+fn page(id: &str, path: &str, title: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: path.to_string(),
+        title: title.to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+#[test]
+fn fuzzy_scores_prefer_word_starts_and_runs() {
+    let score = |query: &str, target: &str| fuzzy_score(&chars(query), &chars(target));
+    assert!(score("pa", "project alpha").is_some());
+    assert!(score("hp", "alpha").is_none());
+    assert!(score("xyz", "project alpha").is_none());
+    assert!(score("alp", "alpha") > score("alp", "salpa"));
+    assert!(score("pa", "project alpha") > score("pa", "spare"));
+}
+
+#[tokio::test]
+async fn ranks_pages_headings_tags_and_commands() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "/work", "Roadmap")).await.unwrap();
+    db.new_page(&page("p2", "/archive", "Road trip")).await.unwrap();
+    db.new_page(&page("p3", "/road/maps", "Carte")).await.unwrap();
+    db.new_bloc(&BlocJson {
+        id: Some("b1".to_string()),
+        position: "a0".to_string(),
+        content: json!({ "type": "heading", "tag": "h2", "children": [{ "type": "text", "text": "Road safety" }] })
+            .to_string(),
+        page_id: "p3".to_string(),
+        bloc_type: "heading".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    db.rebuild_heading_index().await.unwrap();
+    db.tag_entity(TagEntity::Page, "p1".to_string(), "roadwork".to_string()).await.unwrap();
+    let commands = vec![PaletteCommand {
+        id: "toggle-theme".to_string(),
+        label: "Toggle theme".to_string(),
+        keywords: vec!["dark".to_string(), "light".to_string()],
+    }];
+
+    let index = db.build_palette_index(&commands, 0).await.unwrap();
+    assert_eq!(index.len(), 6);
+    let hits = index.search("road", 10, 0);
+    let kinds: Vec<PaletteKind> = hits.iter().map(|hit| hit.entry.kind).collect();
+    assert!(kinds.contains(&PaletteKind::Heading));
+    assert!(kinds.contains(&PaletteKind::Tag));
+    // le chemin compte aussi, moins que le titre
+    let carte = hits.iter().position(|hit| hit.entry.id == "p3").unwrap();
+    assert!(carte > hits.iter().position(|hit| hit.entry.id == "p1").unwrap());
+    let heading = hits.iter().find(|hit| hit.entry.kind == PaletteKind::Heading).unwrap();
+    assert_eq!((heading.entry.page_id.as_deref(), heading.entry.anchor.as_deref()), (Some("p3"), Some("road-safety")));
+
+    // mots-clés des commandes
+    assert_eq!(index.search("dark", 5, 0)[0].entry.id, "toggle-theme");
+
+    // les visites font passer "Road trip" devant
+    for _ in 0..5 {
+        db.record_visit("p2".to_string(), None).await.unwrap();
+        db.record_visit("p3".to_string(), None).await.unwrap();
+    }
+    let now = tauritest_db::database::now_millis();
+    let index = db.build_palette_index(&commands, now).await.unwrap();
+    let hits = index.search("road", 10, now);
+    assert_eq!(hits[0].entry.id, "p2");
+    // sans recherche : les pages visitées et les commandes
+    let ids: Vec<String> = index.search("", 10, now).into_iter().map(|hit| hit.entry.id).collect();
+    assert_eq!(ids.len(), 3);
+    assert!(ids.contains(&"toggle-theme".to_string()));
+}
+
+#[tokio::test]
+async fn updates_follow_the_operation_log() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "/work", "Roadmap")).await.unwrap();
+    db.new_page(&page("p2", "/work", "Budget")).await.unwrap();
+    let mut index = db.build_palette_index(&[], 0).await.unwrap();
+    assert_eq!(index.len(), 2);
+
+    db.update_page_title("p1".to_string(), "Planning".to_string()).await.unwrap();
+    db.new_page(&page("p3", "/work", "Retro")).await.unwrap();
+    db.delete_page("p2".to_string()).await.unwrap();
+    db.new_bloc(&BlocJson {
+        id: Some("b1".to_string()),
+        position: "a0".to_string(),
+        content: json!({ "type": "heading", "tag": "h1", "children": [{ "type": "text", "text": "Objectifs" }] })
+            .to_string(),
+        page_id: "p3".to_string(),
+        bloc_type: "heading".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    db.rebuild_page_preview("p3".to_string()).await.unwrap();
+    db.tag_entity(TagEntity::Page, "p3".to_string(), "sprint".to_string()).await.unwrap();
+
+    // sans `now`, étiquettes et visites ne sont pas relues
+    db.update_palette_index(&mut index, None).await.unwrap();
+    assert_eq!(index.cursor, db.latest_cursor().await.unwrap());
+    let ids = |query: &str| -> Vec<String> { index.search(query, 10, 0).into_iter().map(|hit| hit.entry.id).collect() };
+    assert_eq!(ids("planning"), vec!["p1"]);
+    assert!(ids("roadmap").is_empty());
+    assert!(ids("budget").is_empty());
+    assert_eq!(ids("objectifs"), vec!["b1"]);
+    assert!(ids("sprint").is_empty());
+
+    db.update_palette_index(&mut index, Some(1)).await.unwrap();
+    assert_eq!(index.built_at, 1);
+    assert_eq!(index.search("sprint", 10, 1)[0].entry.kind, PaletteKind::Tag);
+    assert_eq!(index.len(), db.build_palette_index(&[], 1).await.unwrap().len());
+}
@@ -0,0 +1,310 @@
+use serde_json::json;
+use tauritest_db::fractional_index::{
+    generate_jittered_n_keys_between, generate_key_between, generate_n_keys_between,
+    validate_order_key,
+};
+use tauritest_db::{BlocJson, Database, MoveAnchor, PageJson, PropsJson};
+
+async fn db() -> Database {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&PageJson {
+        id: Some("p1".to_string()),
+        path: "/".to_string(),
+        title: "page".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    db
+}
+
+async fn add(db: &Database, id: &str, position: &str) {
+    add_on(db, "p1", id, position).await;
+}
+
+async fn add_on(db: &Database, page_id: &str, id: &str, position: &str) {
+    let content = json!({
+        "type": "paragraph",
+        "children": [],
+        "$": { "id": id, "position": position },
+    });
+    db.new_bloc(&BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: content.to_string(),
+        page_id: page_id.to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+}
+
+// mêmes valeurs que les tests de fractional_indexing.ts
+#[test]
+fn keys_match_the_editor_implementation() {
+    let key = |a: Option<&str>, b: Option<&str>| generate_key_between(a, b).unwrap();
+    assert_eq!(key(None, None), "a0");
+    assert_eq!(key(None, Some("a0")), "Zz");
+    assert_eq!(key(Some("a0"), None), "a1");
+    assert_eq!(key(Some("a0"), Some("a1")), "a0V");
+    assert_eq!(key(Some("a1"), Some("a2")), "a1V");
+    assert_eq!(key(Some("az"), None), "b00");
+    assert_eq!(key(Some("Zz"), None), "a0");
+    assert_eq!(key(Some("a0V"), Some("a1")), "a0l");
+    assert_eq!(key(Some("b125"), Some("b129")), "b127");
+    assert!(generate_key_between(Some("a1"), Some("a0")).is_err());
+
+    let keys = generate_n_keys_between(None, None, 3).unwrap();
+    assert_eq!(keys, vec!["a0", "a1", "a2"]);
+    let keys = generate_n_keys_between(Some("a0"), Some("a1"), 5).unwrap();
+    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    assert!(keys.iter().all(|k| "a0" < k.as_str() && k.as_str() < "a1"));
+}
+
+#[test]
+fn validates_key_format() {
+    for key in ["a0", "a0V", "Zz", "b00", "b125"] {
+        assert!(validate_order_key(key).is_ok(), "{}", key);
+    }
+    for key in ["", "a", "a00", "b0", "0", "a0 ", "é"] {
+        assert!(validate_order_key(key).is_err(), "{:?}", key);
+    }
+}
+
+#[test]
+fn jittered_keys_stay_sorted_and_in_range() {
+    let keys = generate_jittered_n_keys_between(Some("a0"), Some("a1"), 10).unwrap();
+    assert_eq!(keys.len(), 10);
+    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    assert!(keys.iter().all(|k| "a0" < k.as_str() && k.as_str() < "a1"));
+    assert!(keys.iter().all(|k| validate_order_key(k).is_ok()));
+}
+
+#[tokio::test]
+async fn rejects_invalid_and_duplicate_positions() {
+    let db = db().await;
+    add(&db, "b1", "a0").await;
+    add(&db, "b2", "a1").await;
+
+    assert_eq!(
+        db.update_bloc_position("b2".to_string(), "not a key".to_string(), 1).await.unwrap(),
+        Database::ERROR
+    );
+    assert_eq!(
+        db.update_bloc_position("b2".to_string(), "a0".to_string(), 1).await.unwrap(),
+        Database::ERROR
+    );
+    assert_eq!(
+        db.update_bloc_position("b2".to_string(), "a0V".to_string(), 1).await.unwrap(),
+        Database::SUCCESS
+    );
+    assert_eq!(db.get_bloc_by_id("b2".to_string()).await.unwrap().position, "a0V");
+}
+
+#[tokio::test]
+async fn saves_check_positions_like_moves() {
+    let db = db().await;
+    add(&db, "b1", "a0").await;
+    add(&db, "b2", "a1").await;
+    let bloc = |id: &str, position: &str| BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: "{}".to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 1,
+    };
+
+    assert!(db.new_bloc(&bloc("b3", "a0")).await.is_err());
+    assert!(db.new_bloc(&bloc("b3", "not a key")).await.is_err());
+    assert!(db.get_bloc_by_id("b3".to_string()).await.is_err());
+
+    // rien n'est écrit quand la clé est refusée, pas même le contenu
+    assert!(db.update_bloc(&bloc("b2", "a0")).await.is_err());
+    assert!(db.update_bloc(&bloc("b2", "a00")).await.is_err());
+    let b2 = db.get_bloc_by_id("b2".to_string()).await.unwrap();
+    assert_eq!(b2.position, "a1");
+    assert_ne!(b2.content, "{}");
+
+    assert!(db.update_bloc(&bloc("b2", "a1")).await.unwrap());
+    assert!(db.update_bloc(&bloc("b2", "a2")).await.unwrap());
+    assert_eq!(db.get_bloc_by_id("b2".to_string()).await.unwrap().position, "a2");
+    assert!(!db.update_bloc(&bloc("missing", "a3")).await.unwrap());
+}
+
+#[tokio::test]
+async fn checks_position_against_neighbors() {
+    let db = db().await;
+    add(&db, "b1", "a0").await;
+    add(&db, "b2", "a1").await;
+    add(&db, "b3", "a2").await;
+
+    // b3 entre b1 et b2 : a3 n'est pas avant b2
+    let status = db
+        .update_bloc_position_between(
+            "b3".to_string(),
+            "a3".to_string(),
+            Some("b1".to_string()),
+            Some("b2".to_string()),
+            1,
+        )
+        .await
+        .unwrap();
+    assert_eq!(status, Database::ERROR);
+
+    // voisin inconnu
+    let status = db
+        .update_bloc_position_between("b3".to_string(), "a0V".to_string(), Some("nope".to_string()), None, 1)
+        .await
+        .unwrap();
+    assert_eq!(status, Database::ERROR);
+
+    let status = db
+        .update_bloc_position_between(
+            "b3".to_string(),
+            "a0V".to_string(),
+            Some("b1".to_string()),
+            Some("b2".to_string()),
+            1,
+        )
+        .await
+        .unwrap();
+    assert_eq!(status, Database::SUCCESS);
+
+    let order: Vec<String> = db
+        .get_page_positions("p1".to_string())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+    assert_eq!(order, vec!["b1", "b3", "b2"]);
+}
+
+#[tokio::test]
+async fn rebalance_keeps_order_and_rewrites_content() {
+    let db = db().await;
+    // clés qui s'allongent à force d'insérer au même endroit
+    let mut low = "a0".to_string();
+    let high = "a1".to_string();
+    add(&db, "first", &low).await;
+    add(&db, "last", &high).await;
+    for i in 0..200 {
+        let key = generate_key_between(Some(&low), Some(&high)).unwrap();
+        add(&db, &format!("b{:03}", i), &key).await;
+        low = key;
+    }
+    assert!(db.needs_rebalance("p1".to_string()).await.unwrap());
+
+    let before: Vec<String> = db
+        .get_page_positions("p1".to_string())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+
+    let positions = db.rebalance_page_positions("p1".to_string()).await.unwrap();
+    let after: Vec<String> = positions.iter().map(|p| p.id.clone()).collect();
+    assert_eq!(after, before);
+    assert_eq!(positions[0].position, "a0");
+    assert!(positions.iter().all(|p| p.position.len() <= 3));
+    assert!(!db.needs_rebalance("p1".to_string()).await.unwrap());
+
+    let bloc = db.get_bloc_by_id("last".to_string()).await.unwrap();
+    let node: serde_json::Value = serde_json::from_str(&bloc.content).unwrap();
+    assert_eq!(node["$"]["position"], bloc.position);
+    assert!(db.check_integrity().await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn moves_a_contiguous_selection_to_another_page() {
+    let db = db().await;
+    db.new_page(&PageJson {
+        id: Some("p2".to_string()),
+        path: "/".to_string(),
+        title: "autre".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    for (id, position) in [("a", "a0"), ("b", "a1"), ("c", "a2"), ("d", "a3")] {
+        add(&db, id, position).await;
+    }
+    // mêmes clés que la sélection dans la page cible
+    add_on(&db, "p2", "x", "a1").await;
+    add_on(&db, "p2", "y", "a2").await;
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "status".to_string(),
+        value: "todo".to_string(),
+        bloc_id: "c".to_string(),
+    })
+    .await
+    .unwrap();
+
+    // sélection non contiguë, ancre absente ou dans la sélection
+    let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+    assert!(db.move_blocs(ids(&["a", "c"]), "p2".to_string(), None).await.is_err());
+    assert!(db.move_blocs(ids(&["b"]), "p2".to_string(), Some(MoveAnchor::After("a".to_string()))).await.is_err());
+    assert!(db.move_blocs(ids(&["b", "c"]), "p1".to_string(), Some(MoveAnchor::Before("c".to_string()))).await.is_err());
+
+    let moved = db
+        .move_blocs(ids(&["c", "b"]), "p2".to_string(), Some(MoveAnchor::Before("y".to_string())))
+        .await
+        .unwrap();
+    assert_eq!(moved.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), ["b", "c"]);
+
+    let order = |positions: Vec<tauritest_db::BlocPosition>| positions.into_iter().map(|p| p.id).collect::<Vec<_>>();
+    assert_eq!(order(db.get_page_positions("p1".to_string()).await.unwrap()), ["a", "d"]);
+    assert_eq!(order(db.get_page_positions("p2".to_string()).await.unwrap()), ["x", "b", "c", "y"]);
+
+    let bloc = db.get_bloc_by_id("c".to_string()).await.unwrap();
+    let node: serde_json::Value = serde_json::from_str(&bloc.content).unwrap();
+    assert_eq!(node["$"]["position"], json!(bloc.position));
+    assert!(bloc.updated_at > 0);
+    assert_eq!(db.get_props_by_bloc_id("c".to_string()).await.unwrap().len(), 1);
+    for page_id in ["p1", "p2"] {
+        assert!(db.get_page_by_id(page_id.to_string()).await.unwrap().updated_at > 0);
+    }
+    let preview = db.refresh_page_preview("p2".to_string()).await.unwrap();
+    assert!(!preview.checksum.is_empty());
+
+    // une seule étape ramène les deux blocs
+    db.undo().await.unwrap();
+    assert_eq!(order(db.get_page_positions("p1".to_string()).await.unwrap()), ["a", "b", "c", "d"]);
+    assert_eq!(db.get_bloc_by_id("b".to_string()).await.unwrap().position, "a1");
+
+    // à la fin de la même page
+    db.move_blocs(ids(&["a"]), "p1".to_string(), None).await.unwrap();
+    assert_eq!(order(db.get_page_positions("p1".to_string()).await.unwrap()), ["b", "c", "d", "a"]);
+}
+
+#[tokio::test]
+async fn concurrent_moves_to_the_same_gap_never_share_a_key() {
+    let db = db().await;
+    add(&db, "b1", "a0").await;
+    add(&db, "b2", "a1").await;
+    add(&db, "b3", "a2").await;
+    add(&db, "b4", "a3").await;
+
+    let between = |id: &str| {
+        db.update_bloc_position_between(id.to_string(), "a0V".to_string(), Some("b1".to_string()), Some("b2".to_string()), 1)
+    };
+    let (moved_b3, moved_b4) = tokio::join!(between("b3"), between("b4"));
+    let mut statuses = vec![moved_b3.unwrap(), moved_b4.unwrap()];
+    statuses.sort();
+    assert_eq!(statuses, vec![Database::ERROR, Database::SUCCESS]);
+
+    let positions = db.get_page_positions("p1".to_string()).await.unwrap();
+    let mut keys: Vec<_> = positions.iter().map(|p| p.position.clone()).collect();
+    keys.dedup();
+    assert_eq!(keys.len(), 4);
+}
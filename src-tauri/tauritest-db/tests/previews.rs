@@ -0,0 +1,109 @@
+use serde_json::json;
+use tauritest_db::{BlocJson, Database, PageJson};
+
+fn page(id: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: "/notes".to_string(),
+        title: format!("titre {}", id),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn bloc(id: &str, page_id: &str, position: &str, content: serde_json::Value) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: content.to_string(),
+        page_id: page_id.to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn text(text: &str) -> serde_json::Value {
+    json!({ "type": "paragraph", "children": [{ "type": "text", "text": text }] })
+}
+
+fn heading(tag: &str, text: &str) -> serde_json::Value {
+    json!({ "type": "heading", "tag": tag, "children": [{ "type": "text", "text": text }] })
+}
+
+#[tokio::test]
+async fn builds_the_preview_from_the_blocs() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1")).await.unwrap();
+    db.new_bloc(&bloc("b1", "p1", "a0", heading("h1", "Introduction"))).await.unwrap();
+    db.new_bloc(&bloc("b2", "p1", "a1", text("Un deux trois quatre"))).await.unwrap();
+    db.new_bloc(&bloc(
+        "b3",
+        "p1",
+        "a2",
+        json!({ "type": "paragraph", "children": [
+            { "type": "image", "src": "assets/a.png", "altText": "schéma" },
+            { "type": "image", "src": "assets/b.png", "altText": "" },
+        ]}),
+    ))
+    .await
+    .unwrap();
+    db.new_bloc(&bloc("b4", "p1", "a3", heading("h2", "Suite"))).await.unwrap();
+
+    let preview = db.refresh_page_preview("p1".to_string()).await.unwrap();
+    assert_eq!(preview.excerpt, "Introduction Un deux trois quatre schéma Suite");
+    assert_eq!(preview.word_count, 7);
+    assert_eq!(preview.reading_minutes, 1);
+    assert_eq!(preview.first_image.as_deref(), Some("assets/a.png"));
+    let outline: Vec<(usize, &str, &str)> = preview
+        .outline
+        .iter()
+        .map(|entry| (entry.level, entry.text.as_str(), entry.bloc_id.as_str()))
+        .collect();
+    assert_eq!(outline, vec![(1, "Introduction", "b1"), (2, "Suite", "b4")]);
+
+    // gardé à part : pages.cache reste à l'éditeur
+    assert_eq!(db.get_page_cache("p1".to_string()).await.unwrap(), "");
+    assert_eq!(db.refresh_page_preview("p1".to_string()).await.unwrap(), preview);
+}
+
+#[tokio::test]
+async fn bloc_changes_invalidate_the_preview() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1")).await.unwrap();
+    db.new_bloc(&bloc("b1", "p1", "a0", text("avant"))).await.unwrap();
+    let before = db.refresh_page_preview("p1".to_string()).await.unwrap();
+
+    // le cache de l'éditeur n'est ni lu ni écrasé par l'aperçu
+    db.update_page_cache("p1".to_string(), "{aaa}".to_string()).await.unwrap();
+    assert_eq!(db.refresh_page_preview("p1".to_string()).await.unwrap(), before);
+    db.rebuild_page_preview("p1".to_string()).await.unwrap();
+    assert_eq!(db.get_page_cache("p1".to_string()).await.unwrap(), "{aaa}");
+
+    db.update_bloc_content("b1".to_string(), text("après modification").to_string(), 1).await.unwrap();
+    let after = db.refresh_page_preview("p1".to_string()).await.unwrap();
+    assert_ne!(after.checksum, before.checksum);
+    assert_eq!(after.excerpt, "après modification");
+
+    db.delete_bloc("b1".to_string()).await.unwrap();
+    let empty = db.refresh_page_preview("p1".to_string()).await.unwrap();
+    assert_eq!((empty.word_count, empty.reading_minutes), (0, 0));
+}
+
+#[tokio::test]
+async fn long_pages_get_a_cut_excerpt_and_reading_time() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1")).await.unwrap();
+    db.new_page(&page("p2")).await.unwrap();
+    db.new_bloc(&bloc("b1", "p1", "a0", text(&"mot ".repeat(450)))).await.unwrap();
+
+    let pages = db.get_page_previews_by_path("/notes".to_string()).await.unwrap();
+    assert_eq!(pages.len(), 2);
+    let long = pages.iter().find(|page| page.id == "p1").unwrap();
+    assert_eq!(long.title, "titre p1");
+    assert_eq!(long.preview.word_count, 450);
+    assert_eq!(long.preview.reading_minutes, 3);
+    assert!(long.preview.excerpt.ends_with("mot…"));
+    assert!(long.preview.excerpt.chars().count() <= 281);
+}
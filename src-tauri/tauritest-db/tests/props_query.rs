@@ -0,0 +1,153 @@
+use chrono::NaiveDate;
+use serde_json::json;
+use tauritest_db::props_query::{parse_props_query, CompareOp, Literal, PropsExpr};
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson};
+
+fn day(text: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+}
+
+async fn add(db: &Database, page_id: &str, id: &str, position: &str, props: &[(&str, &str)]) {
+    let content = json!({ "type": "paragraph", "children": [{ "type": "text", "text": format!("tâche {}", id) }] });
+    db.new_bloc(&BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: content.to_string(),
+        page_id: page_id.to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    for (key, value) in props {
+        db.new_prop(&PropsJson {
+            id: Some(format!("{}-{}", id, key)),
+            key: key.to_string(),
+            value: value.to_string(),
+            bloc_id: id.to_string(),
+        })
+        .await
+        .unwrap();
+    }
+}
+
+async fn setup() -> Database {
+    let db = Database::new_in_memory().await.unwrap();
+    for (id, path, title) in [("p1", "/projets", "Alpha"), ("p2", "/perso", "Maison")] {
+        db.new_page(&PageJson {
+            id: Some(id.to_string()),
+            path: path.to_string(),
+            title: title.to_string(),
+            cache: String::new(),
+            created_at: 0,
+            updated_at: 0,
+        })
+        .await
+        .unwrap();
+    }
+    add(&db, "p1", "b1", "a0", &[("status", "todo"), ("due", "2026-03-01"), ("priority", "2")]).await;
+    add(&db, "p1", "b2", "a1", &[("status", "todo"), ("due", "2026-03-20"), ("priority", "10")]).await;
+    add(&db, "p1", "b3", "a2", &[("status", "done"), ("due", "2026-02-01")]).await;
+    add(&db, "p2", "b4", "a0", &[("status", "todo"), ("priority", "1"), ("owner", "Camille")]).await;
+    add(&db, "p2", "b5", "a1", &[]).await;
+    db
+}
+
+fn ids(found: &[tauritest_db::PropsMatch]) -> Vec<&str> {
+    found.iter().map(|m| m.bloc_id.as_str()).collect()
+}
+
+#[test]
+fn parses_queries() {
+    let query = parse_props_query(r#"status = "todo" AND (due < today() - 7 OR NOT EXISTS due) SORT BY priority DESC, page.title LIMIT 5"#).unwrap();
+    let PropsExpr::And(left, right) = query.filter.unwrap() else { panic!() };
+    assert_eq!(
+        *left,
+        PropsExpr::Compare { key: "status".to_string(), op: CompareOp::Eq, value: Literal::Text("todo".to_string()) }
+    );
+    let PropsExpr::Or(due, _) = *right else { panic!() };
+    assert_eq!(
+        *due,
+        PropsExpr::Compare { key: "due".to_string(), op: CompareOp::Lt, value: Literal::Today(-7) }
+    );
+    assert_eq!(query.sort.len(), 2);
+    assert!(query.sort[0].descending && !query.sort[1].descending);
+    assert_eq!(query.limit, Some(5));
+
+    assert_eq!(parse_props_query("due >= today()+3").unwrap(), parse_props_query("due >= today() + 3").unwrap());
+    assert!(parse_props_query("SORT BY `due date`").unwrap().filter.is_none());
+    for invalid in ["status =", "status \"todo\"", "(a = 1", "a = 1 b = 2", "a = \"x", "SORT priority", "AND = 1"] {
+        assert!(parse_props_query(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[tokio::test]
+async fn filters_and_sorts_blocs_by_props() {
+    let db = setup().await;
+    let today = day("2026-03-10");
+
+    let found = db.query_props(r#"status = "todo" AND due < today()"#, today).await.unwrap();
+    assert_eq!(ids(&found), ["b1"]);
+    assert_eq!((found[0].page_title.as_str(), found[0].page_path.as_str()), ("Alpha", "/projets"));
+    assert_eq!(found[0].props["priority"], "2");
+    assert_eq!(found[0].text, "tâche b1");
+
+    // nombres comparés en nombres ("10" > "2"), absents à la fin
+    let found = db.query_props("SORT BY priority DESC", today).await.unwrap();
+    assert_eq!(ids(&found), ["b2", "b1", "b4", "b3"]);
+    let found = db.query_props(r#"status != "done" SORT BY priority LIMIT 2"#, today).await.unwrap();
+    assert_eq!(ids(&found), ["b4", "b1"]);
+
+    let found = db.query_props("due <= today() + 10 AND due > today() - 30", today).await.unwrap();
+    assert_eq!(ids(&found), ["b1", "b2"]);
+    let found = db.query_props(r#"owner CONTAINS "cam" OR page.path = "/perso""#, today).await.unwrap();
+    assert_eq!(ids(&found), ["b4"]);
+    let found = db.query_props("NOT EXISTS priority", today).await.unwrap();
+    assert_eq!(ids(&found), ["b3"]);
+    // une prop absente ne vérifie aucune comparaison, même !=
+    let found = db.query_props(r#"owner != "Camille""#, today).await.unwrap();
+    assert!(found.is_empty());
+
+    assert!(db.query_props("status ==", today).await.is_err());
+}
+
+#[tokio::test]
+async fn sorts_mixed_values_by_class() {
+    let db = setup().await;
+    let today = day("2026-03-10");
+    let values = ["high", "9", "2026-01-05", "1a", "10", "1", "2025-12-31"];
+    for (i, value) in values.iter().enumerate() {
+        add(&db, "p1", &format!("m{}", i), &format!("a{}", i + 3), &[("rank", value)]).await;
+    }
+
+    // nombres, puis dates, puis texte : "9" < "10" < "1a" sans boucler
+    let found = db.query_props("EXISTS rank SORT BY rank", today).await.unwrap();
+    let sorted: Vec<&str> = found.iter().map(|m| m.props["rank"].as_str()).collect();
+    assert_eq!(sorted, ["1", "9", "10", "2025-12-31", "2026-01-05", "1a", "high"]);
+    let found = db.query_props("EXISTS rank SORT BY rank DESC", today).await.unwrap();
+    let sorted: Vec<&str> = found.iter().map(|m| m.props["rank"].as_str()).collect();
+    assert_eq!(sorted, ["high", "1a", "2026-01-05", "2025-12-31", "10", "9", "1"]);
+}
+
+#[tokio::test]
+async fn out_of_range_dates_and_deep_nesting_are_errors() {
+    let db = setup().await;
+    let today = day("2026-03-10");
+
+    // au-delà des dates représentables : erreur plutôt que panique
+    assert!(db.query_props("due < today() + 100000000", today).await.is_err());
+    assert!(db.query_props("due < today() - 100000000", today).await.is_err());
+    assert!(parse_props_query("due < today() + 99999999999999999999").is_err());
+    assert!(parse_props_query("due < today() -99999999999999999999").is_err());
+
+    let deep_not = format!("{}EXISTS due", "NOT ".repeat(100_000));
+    assert!(parse_props_query(&deep_not).is_err());
+    let deep_parens = format!("{}EXISTS due{}", "(".repeat(100_000), ")".repeat(100_000));
+    assert!(parse_props_query(&deep_parens).is_err());
+    let long_chain = vec!["EXISTS due"; 100_000].join(" OR ");
+    assert!(parse_props_query(&long_chain).is_err());
+    // une imbrication raisonnable passe
+    let nested = format!("{}EXISTS due{}", "(NOT ".repeat(20), ")".repeat(20));
+    assert!(parse_props_query(&nested).is_ok());
+}
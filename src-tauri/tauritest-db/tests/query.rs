@@ -0,0 +1,207 @@
+use serde_json::{json, Value as JsonValue};
+use tauritest_db::{BlocJson, ContentQuery, Database, FilterOp, NodeFilter, PageJson};
+
+async fn db() -> Database {
+    let db = Database::new_in_memory().await.unwrap();
+    for (id, path) in [("p1", "/work"), ("p2", "/home")] {
+        db.new_page(&PageJson {
+            id: Some(id.to_string()),
+            path: path.to_string(),
+            title: format!("page {}", id),
+            cache: String::new(),
+            created_at: 0,
+            updated_at: 0,
+        })
+        .await
+        .unwrap();
+    }
+    db
+}
+
+async fn add(db: &Database, id: &str, page_id: &str, position: &str, node: JsonValue) {
+    db.new_bloc(&BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: node.to_string(),
+        page_id: page_id.to_string(),
+        bloc_type: node["type"].as_str().unwrap().to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+}
+
+fn text(value: &str) -> JsonValue {
+    json!({ "type": "text", "text": value, "format": 0 })
+}
+
+fn checklist(items: &[(&str, bool)]) -> JsonValue {
+    let children: Vec<JsonValue> = items
+        .iter()
+        .map(|(label, checked)| {
+            json!({ "type": "listitem", "checked": checked, "children": [text(label)] })
+        })
+        .collect();
+    json!({ "type": "list", "listType": "check", "children": children })
+}
+
+fn code(language: &str, source: &str) -> JsonValue {
+    json!({ "type": "code", "language": language, "children": [text(source)] })
+}
+
+fn filter(path: &str, value: JsonValue) -> NodeFilter {
+    NodeFilter {
+        path: path.to_string(),
+        op: FilterOp::Eq,
+        value,
+    }
+}
+
+#[tokio::test]
+async fn finds_unchecked_checklist_items_across_pages() {
+    let db = db().await;
+    add(&db, "b1", "p1", "a0", checklist(&[("write tests", false), ("ship", true)])).await;
+    add(&db, "b2", "p2", "a0", checklist(&[("buy milk", false)])).await;
+    add(&db, "b3", "p2", "a1", json!({ "type": "paragraph", "children": [text("x")] })).await;
+
+    let matches = db
+        .query_nodes(&ContentQuery {
+            filters: vec![filter("$.type", json!("listitem")), filter("$.checked", json!(false))],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let found: Vec<(&str, &str)> = matches
+        .iter()
+        .map(|m| (m.bloc_id.as_str(), m.text.as_str()))
+        .collect();
+    assert_eq!(found, vec![("b2", "buy milk"), ("b1", "write tests")]);
+    assert_eq!(matches[1].node_path, "$.children[0]");
+    assert_eq!(matches[1].page_path, "/work");
+}
+
+#[tokio::test]
+async fn finds_code_blocs_by_language() {
+    let db = db().await;
+    add(&db, "b1", "p1", "a0", code("rust", "fn main() {}")).await;
+    add(&db, "b2", "p1", "a1", code("js", "let a")).await;
+    add(&db, "b3", "p2", "a0", code("rust", "struct A;")).await;
+
+    let matches = db
+        .query_nodes(&ContentQuery {
+            filters: vec![filter("$.language", json!("rust"))],
+            bloc_type: Some("code".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let ids: Vec<&str> = matches.iter().map(|m| m.bloc_id.as_str()).collect();
+    assert_eq!(ids, vec!["b3", "b1"]);
+    assert!(matches.iter().all(|m| m.node_path == "$"));
+
+    let matches = db
+        .query_nodes(&ContentQuery {
+            filters: vec![filter("$.language", json!("rust"))],
+            page_path: Some("/work".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].bloc_id, "b1");
+}
+
+#[tokio::test]
+async fn supports_ne_contains_null_and_limit() {
+    let db = db().await;
+    add(&db, "b1", "p1", "a0", code("rust", "x")).await;
+    add(&db, "b2", "p1", "a1", code("python", "y")).await;
+    add(&db, "b3", "p1", "a2", json!({ "type": "code", "children": [] })).await;
+
+    let query = |filters: Vec<NodeFilter>, limit: Option<i64>| ContentQuery {
+        filters,
+        bloc_type: Some("code".to_string()),
+        limit,
+        ..Default::default()
+    };
+
+    let ne = db
+        .query_nodes(&query(
+            vec![NodeFilter { op: FilterOp::Ne, ..filter("$.language", json!("rust")) }],
+            None,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(ne.len(), 1);
+    assert_eq!(ne[0].bloc_id, "b2");
+
+    let contains = db
+        .query_nodes(&query(
+            vec![NodeFilter { op: FilterOp::Contains, ..filter("$.language", json!("PYTH")) }],
+            None,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(contains.len(), 1);
+
+    let missing = db
+        .query_nodes(&query(
+            // sans le filtre sur le type, les noeuds text enfants correspondraient aussi
+            vec![filter("$.type", json!("code")), filter("$.language", JsonValue::Null)],
+            None,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].bloc_id, "b3");
+
+    let limited = db.query_nodes(&query(vec![], Some(2))).await.unwrap();
+    assert_eq!(limited.len(), 2);
+}
+
+#[tokio::test]
+async fn invalid_content_is_skipped() {
+    let db = db().await;
+    add(&db, "b1", "p1", "a0", code("rust", "x")).await;
+    db.new_bloc(&BlocJson {
+        id: Some("broken".to_string()),
+        position: "a1".to_string(),
+        content: "{not json".to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "code".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+
+    let matches = db
+        .query_nodes(&ContentQuery {
+            filters: vec![filter("$.language", json!("rust"))],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(matches.len(), 1);
+
+    let typed: Vec<JsonValue> = db.query("$.language", json!("rust")).await.unwrap();
+    assert_eq!(typed.len(), 1);
+}
+
+#[tokio::test]
+async fn query_deserializes_matching_roots() {
+    #[derive(serde::Deserialize)]
+    struct CodeBloc {
+        language: String,
+    }
+
+    let db = db().await;
+    add(&db, "b1", "p1", "a0", code("rust", "x")).await;
+    add(&db, "b2", "p1", "a1", code("go", "y")).await;
+
+    let blocs: Vec<CodeBloc> = db.query("$.type", json!("code")).await.unwrap();
+    let languages: Vec<String> = blocs.into_iter().map(|b| b.language).collect();
+    assert_eq!(languages, vec!["rust", "go"]);
+}
@@ -0,0 +1,160 @@
+use chrono::{FixedOffset, NaiveTime};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tauritest_db::reminders::parse_reminder_date;
+use tauritest_db::{BlocJson, Database, ManualClock, PageJson, PropsJson, ReminderScheduler};
+
+// 2026-03-01T00:00:00Z
+const MARCH_1: i64 = 1_772_323_200_000;
+const HOUR: i64 = 3_600_000;
+
+#[tokio::test]
+async fn fires_snoozes_and_dismisses_with_a_manual_clock() {
+    let db = Database::new_in_memory().await.unwrap();
+    let clock = ManualClock::new(MARCH_1);
+    let scheduler = ReminderScheduler::new(db.clone(), Arc::new(clock.clone()));
+
+    let call = db.new_reminder(None, None, "Appeler".to_string(), MARCH_1 + HOUR).await.unwrap();
+    let report = db.new_reminder(None, None, "Rapport".to_string(), MARCH_1 + 3 * HOUR).await.unwrap();
+    assert_eq!(db.next_reminder_at().await.unwrap(), Some(MARCH_1 + HOUR));
+    assert!(scheduler.tick().await.unwrap().is_empty());
+
+    clock.set(MARCH_1 + HOUR);
+    let fired = scheduler.tick().await.unwrap();
+    assert_eq!(fired.len(), 1);
+    assert_eq!((fired[0].reminder.id.as_str(), fired[0].late), (call.id.as_str(), false));
+    // déjà déclenché : pas une seconde fois
+    assert!(scheduler.tick().await.unwrap().is_empty());
+
+    assert!(db.snooze_reminder(call.id.clone(), MARCH_1 + 2 * HOUR).await.unwrap());
+    clock.advance(HOUR);
+    let fired = scheduler.tick().await.unwrap();
+    assert_eq!(fired[0].reminder.snooze_count, 1);
+
+    assert!(db.dismiss_reminder(report.id.clone()).await.unwrap());
+    clock.advance(10 * HOUR);
+    assert!(scheduler.tick().await.unwrap().is_empty());
+    assert!(db.get_reminders(false).await.unwrap().is_empty());
+    let all = db.get_reminders(true).await.unwrap();
+    assert_eq!(all.iter().map(|r| r.status.as_str()).collect::<Vec<_>>(), ["fired", "dismissed"]);
+}
+
+#[tokio::test]
+async fn catches_up_on_reminders_missed_while_closed() {
+    let dir = std::env::temp_dir().join(format!("tauritest-reminders-{}", std::process::id()));
+    let path = dir.join("notes.db");
+    let path = path.to_str().unwrap();
+
+    let db = Database::new(path).await.unwrap();
+    db.new_reminder(Some("b1".to_string()), Some("p1".to_string()), "Réunion".to_string(), MARCH_1).await.unwrap();
+    drop(db);
+
+    // rouvert le lendemain
+    let db = Database::new(path).await.unwrap();
+    let scheduler = ReminderScheduler::new(db.clone(), Arc::new(ManualClock::new(MARCH_1 + 24 * HOUR)));
+    let fired = scheduler.tick().await.unwrap();
+    assert_eq!(fired.len(), 1);
+    assert!(fired[0].late);
+    assert_eq!(fired[0].reminder.bloc_id.as_deref(), Some("b1"));
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn the_run_loop_fires_when_woken() {
+    let db = Database::new_in_memory().await.unwrap();
+    let clock = ManualClock::new(MARCH_1);
+    let scheduler = Arc::new(ReminderScheduler::new(db.clone(), Arc::new(clock.clone())));
+    // un rappel manqué est rattrapé au démarrage
+    db.new_reminder(None, None, "Manqué".to_string(), MARCH_1 - HOUR).await.unwrap();
+    db.new_reminder(None, None, "Plus tard".to_string(), MARCH_1 + HOUR).await.unwrap();
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let running = scheduler.clone();
+    let task = tokio::spawn(async move {
+        running.run(|fired| sender.send(fired).unwrap(), |e| panic!("{e}")).await;
+    });
+
+    let first = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+    assert_eq!((first.reminder.title.as_str(), first.late), ("Manqué", true));
+
+    clock.advance(HOUR);
+    scheduler.waker().notify_one();
+    let second = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+    assert_eq!((second.reminder.title.as_str(), second.late), ("Plus tard", false));
+    task.abort();
+}
+
+#[test]
+fn parses_reminder_dates() {
+    let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+    let paris = FixedOffset::east_opt(3600).unwrap();
+    assert_eq!(parse_reminder_date("2026-03-01", nine, paris), Some(MARCH_1 + 8 * HOUR));
+    assert_eq!(parse_reminder_date("2026-03-01 14:30", nine, paris), Some(MARCH_1 + 13 * HOUR + HOUR / 2));
+    assert_eq!(parse_reminder_date("2026-03-01T02:00:00Z", nine, paris), Some(MARCH_1 + 2 * HOUR));
+    assert_eq!(parse_reminder_date("demain", nine, paris), None);
+}
+
+#[tokio::test]
+async fn follows_date_props() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&PageJson {
+        id: Some("p1".to_string()),
+        path: "/".to_string(),
+        title: "Tâches".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    for (id, position) in [("b1", "a0"), ("b2", "a1")] {
+        db.new_bloc(&BlocJson {
+            id: Some(id.to_string()),
+            position: position.to_string(),
+            content: json!({ "type": "paragraph", "children": [{ "type": "text", "text": format!("Rendre {}", id) }] })
+                .to_string(),
+            page_id: "p1".to_string(),
+            bloc_type: "paragraph".to_string(),
+            created_at: 0,
+            updated_at: 0,
+        })
+        .await
+        .unwrap();
+    }
+    let prop = |id: &str, bloc_id: &str, key: &str, value: &str| PropsJson {
+        id: Some(id.to_string()),
+        key: key.to_string(),
+        value: value.to_string(),
+        bloc_id: bloc_id.to_string(),
+    };
+    db.new_prop(&prop("r1", "b1", "due", "2026-03-01")).await.unwrap();
+    db.new_prop(&prop("r2", "b2", "due", "pas une date")).await.unwrap();
+    db.new_prop(&prop("r3", "b2", "status", "2026-03-01")).await.unwrap();
+
+    let keys = vec!["due".to_string(), "remind".to_string()];
+    let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+    let utc = FixedOffset::east_opt(0).unwrap();
+    assert_eq!(db.sync_prop_reminders(&keys, nine, utc).await.unwrap(), 1);
+    let reminders = db.get_reminders(false).await.unwrap();
+    assert_eq!(reminders.len(), 1);
+    assert_eq!((reminders[0].title.as_str(), reminders[0].fire_at), ("Rendre b1", MARCH_1 + 9 * HOUR));
+
+    // inchangé : le rappel déclenché le reste
+    db.fire_due_reminders(MARCH_1 + 10 * HOUR).await.unwrap();
+    assert_eq!(db.sync_prop_reminders(&keys, nine, utc).await.unwrap(), 0);
+    assert!(db.get_reminders(false).await.unwrap().is_empty());
+
+    // nouvelle date : replanifié
+    db.update_prop_value("b1".to_string(), "due".to_string(), "2026-03-02 08:00".to_string()).await.unwrap();
+    assert_eq!(db.sync_prop_reminders(&keys, nine, utc).await.unwrap(), 1);
+    let reminders = db.get_reminders(false).await.unwrap();
+    assert_eq!(reminders[0].fire_at, MARCH_1 + 32 * HOUR);
+
+    // prop retirée : rappel retiré
+    db.delete_prop("b1".to_string(), "due".to_string()).await.unwrap();
+    db.sync_prop_reminders(&keys, nine, utc).await.unwrap();
+    assert!(db.get_reminders(true).await.unwrap().is_empty());
+}
@@ -0,0 +1,93 @@
+use serde_json::{json, Map, Value};
+use tauritest_db::settings::{export_settings, get_settings, settings_version, upgrade_setting_keys, SettingsMigration};
+use tauritest_db::{Database, SettingScope, SettingSource, SettingValue};
+
+fn find<'a>(settings: &'a [SettingValue], key: &str) -> &'a SettingValue {
+    settings.iter().find(|setting| setting.key == key).unwrap()
+}
+
+#[tokio::test]
+async fn workspace_values_override_global_ones_and_defaults() {
+    let global = Database::new_in_memory().await.unwrap();
+    let workspace = Database::new_in_memory().await.unwrap();
+
+    let defaults = get_settings(&global, None).await.unwrap();
+    assert_eq!(find(&defaults, "showTreeView").value, json!(true));
+    assert_eq!(find(&defaults, "theme").source, SettingSource::Default);
+
+    global.set_setting(SettingScope::Global, "showTreeView".to_string(), json!(false)).await.unwrap();
+    global.set_setting(SettingScope::Global, "autosaveDelayMs".to_string(), json!(500)).await.unwrap();
+    workspace.set_setting(SettingScope::Workspace, "autosaveDelayMs".to_string(), json!(2000)).await.unwrap();
+
+    let settings = get_settings(&global, Some(&workspace)).await.unwrap();
+    assert_eq!(find(&settings, "showTreeView").value, json!(false));
+    assert_eq!(find(&settings, "showTreeView").source, SettingSource::Global);
+    let delay = find(&settings, "autosaveDelayMs");
+    assert_eq!((&delay.value, delay.source), (&json!(2000), SettingSource::Workspace));
+
+    // sans espace ouvert, la valeur globale s'applique
+    let settings = get_settings(&global, None).await.unwrap();
+    assert_eq!(find(&settings, "autosaveDelayMs").value, json!(500));
+
+    assert_eq!(workspace.reset_setting(Some("autosaveDelayMs".to_string())).await.unwrap(), vec!["autosaveDelayMs"]);
+    let settings = get_settings(&global, Some(&workspace)).await.unwrap();
+    assert_eq!(find(&settings, "autosaveDelayMs").source, SettingSource::Global);
+    assert_eq!(global.reset_setting(None).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn rejects_invalid_values_and_scopes() {
+    let db = Database::new_in_memory().await.unwrap();
+    let set = |key: &str, value: Value, scope| db.set_setting(scope, key.to_string(), value);
+
+    assert!(set("unknown", json!(true), SettingScope::Global).await.is_err());
+    assert!(set("showTreeView", json!("yes"), SettingScope::Global).await.is_err());
+    assert!(set("autosaveDelayMs", json!(10), SettingScope::Global).await.is_err());
+    assert!(set("theme", json!("sepia"), SettingScope::Global).await.is_err());
+    // le thème ne se règle pas par espace de travail
+    assert!(set("theme", json!("dark"), SettingScope::Workspace).await.is_err());
+    assert_eq!(set("theme", json!("dark"), SettingScope::Global).await.unwrap(), json!("dark"));
+}
+
+#[tokio::test]
+async fn imports_and_exports_stored_values() {
+    let global = Database::new_in_memory().await.unwrap();
+    let workspace = Database::new_in_memory().await.unwrap();
+
+    let mut values = Map::new();
+    values.insert("isRichText".to_string(), json!(false));
+    values.insert("journalPath".to_string(), json!("/daily"));
+    values.insert("theme".to_string(), json!("dark"));
+    values.insert("removed".to_string(), json!(1));
+    let report = workspace.import_settings(SettingScope::Workspace, &values, settings_version()).await.unwrap();
+    assert_eq!(report.applied, vec!["isRichText", "journalPath"]);
+    assert_eq!(report.skipped, vec!["removed", "theme"]);
+
+    global.set_setting(SettingScope::Global, "theme".to_string(), json!("light")).await.unwrap();
+    let export = export_settings(&global, Some(&workspace)).await.unwrap();
+    assert_eq!(export.version, settings_version());
+    assert_eq!(export.global.get("theme"), Some(&json!("light")));
+    assert_eq!(export.workspace.len(), 2);
+    assert_eq!(export.workspace.get("journalPath"), Some(&json!("/daily")));
+}
+
+#[test]
+fn older_exports_have_their_keys_renamed() {
+    const MIGRATIONS: &[SettingsMigration] = &[
+        SettingsMigration { version: 1, description: "départ", renames: &[] },
+        SettingsMigration { version: 2, description: "renommage", renames: &[("showTree", "showTreeView")] },
+        SettingsMigration { version: 3, description: "encore", renames: &[("darkMode", "theme")] },
+    ];
+    let mut values = Map::new();
+    values.insert("showTree".to_string(), json!(false));
+    values.insert("darkMode".to_string(), json!("dark"));
+
+    let upgraded = upgrade_setting_keys(&values, 1, MIGRATIONS);
+    assert_eq!(upgraded.get("showTreeView"), Some(&json!(false)));
+    assert_eq!(upgraded.get("theme"), Some(&json!("dark")));
+    assert!(!upgraded.contains_key("showTree"));
+    // un export déjà à la version 2 n'a que la dernière étape à rejouer
+    let upgraded = upgrade_setting_keys(&values, 2, MIGRATIONS);
+    assert!(upgraded.contains_key("showTree"));
+    assert!(upgraded.contains_key("theme"));
+}
@@ -0,0 +1,254 @@
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson, SyncOptions, SyncReport};
+use tokio::net::TcpListener;
+
+fn page(id: &str, title: &str, updated_at: i64) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: "/".to_string(),
+        title: title.to_string(),
+        cache: String::new(),
+        created_at: 1,
+        updated_at,
+    }
+}
+
+fn bloc(id: &str, content: &str, updated_at: i64) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        position: "a0".to_string(),
+        content: content.to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 1,
+        updated_at,
+    }
+}
+
+// deux instances dans le même processus, reliées par une socket locale
+async fn sync(
+    server: &Database,
+    server_options: &SyncOptions,
+    client: &Database,
+    client_options: &SyncOptions,
+) -> (SyncReport, SyncReport) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (served, connected) = tokio::join!(
+        server.accept_sync(&listener, server_options),
+        client.sync_with_peer(address, client_options),
+    );
+    (served.unwrap(), connected.unwrap())
+}
+
+fn paired() -> SyncOptions {
+    SyncOptions { secret: "correct horse".to_string(), ..Default::default() }
+}
+
+async fn content(db: &Database, id: &str) -> String {
+    db.get_bloc_by_id(id.to_string()).await.unwrap().content
+}
+
+#[tokio::test]
+async fn exchanges_changes_in_both_directions() {
+    let a = Database::new_in_memory().await.unwrap();
+    let b = Database::new_in_memory().await.unwrap();
+    let none = paired();
+
+    a.new_page(&page("p1", "shared", 10)).await.unwrap();
+    a.new_bloc(&bloc("b1", "from a", 10)).await.unwrap();
+    a.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "status".to_string(),
+        value: "todo".to_string(),
+        bloc_id: "b1".to_string(),
+    })
+    .await
+    .unwrap();
+    b.new_bloc(&bloc("b2", "from b", 20)).await.unwrap();
+
+    let (on_a, on_b) = sync(&a, &none, &b, &none).await;
+    assert_eq!(on_a.peer_id, b.instance_id().await.unwrap());
+    assert_eq!(on_b.peer_id, a.instance_id().await.unwrap());
+    assert_eq!((on_a.blocs_sent, on_a.blocs_applied), (1, 1));
+    assert_eq!((on_b.pages_applied, on_b.blocs_applied), (1, 1));
+
+    assert_eq!(content(&b, "b1").await, "from a");
+    assert_eq!(content(&a, "b2").await, "from b");
+    assert_eq!(b.get_props_by_bloc_id("b1".to_string()).await.unwrap()[0].value, "todo");
+    // les changements reçus ne s'annulent pas : seul l'ajout de b2 est dans le journal
+    assert_eq!(b.undo_history(10).await.unwrap().len(), 1);
+
+    // le point de synchronisation est mémorisé : plus rien à envoyer
+    let (on_a, on_b) = sync(&a, &none, &b, &none).await;
+    assert!(on_a.since > 0);
+    assert_eq!((on_a.blocs_sent, on_b.blocs_sent), (0, 0));
+}
+
+#[tokio::test]
+async fn concurrent_bloc_edits_converge_on_the_latest() {
+    let a = Database::new_in_memory().await.unwrap();
+    let b = Database::new_in_memory().await.unwrap();
+    let none = paired();
+
+    a.new_bloc(&bloc("b1", "original", 10)).await.unwrap();
+    sync(&a, &none, &b, &none).await;
+
+    // modifié des deux côtés après la synchronisation
+    let later = tauritest_db::database::now_millis() + 1_000;
+    a.update_bloc(&bloc("b1", "edited on a", later)).await.unwrap();
+    b.update_bloc(&bloc("b1", "edited on b", later + 1)).await.unwrap();
+
+    let (on_a, on_b) = sync(&a, &none, &b, &none).await;
+    assert_eq!(on_a.conflicts, vec!["b1"]);
+    assert_eq!(on_b.conflicts, vec!["b1"]);
+    assert_eq!(content(&a, "b1").await, "edited on b");
+    assert_eq!(content(&b, "b1").await, "edited on b");
+    assert_eq!(
+        a.get_checksum("b1".to_string()).await.unwrap(),
+        b.get_checksum("b1".to_string()).await.unwrap()
+    );
+
+    // même date : le checksum départage, de la même façon des deux côtés
+    a.update_bloc(&bloc("b1", "tie a", later + 5)).await.unwrap();
+    b.update_bloc(&bloc("b1", "tie b", later + 5)).await.unwrap();
+    sync(&a, &none, &b, &none).await;
+    assert_eq!(content(&a, "b1").await, content(&b, "b1").await);
+}
+
+#[tokio::test]
+async fn transfers_missing_attachments() {
+    let root = std::env::temp_dir().join(format!("tauritest-sync-{}", std::process::id()));
+    let (dir_a, dir_b) = (root.join("a"), root.join("b"));
+    std::fs::create_dir_all(&dir_a).unwrap();
+    std::fs::create_dir_all(&dir_b).unwrap();
+    std::fs::write(dir_a.join("image.png"), [1u8, 2, 3]).unwrap();
+    std::fs::write(dir_b.join("notes.pdf"), b"pdf").unwrap();
+    // même nom des deux côtés : jamais écrasé
+    std::fs::write(dir_a.join("same.txt"), b"a").unwrap();
+    std::fs::write(dir_b.join("same.txt"), b"b").unwrap();
+
+    let a = Database::new_in_memory().await.unwrap();
+    let b = Database::new_in_memory().await.unwrap();
+    let options_a = SyncOptions { attachments_dir: Some(dir_a.clone()), ..paired() };
+    let options_b = SyncOptions { attachments_dir: Some(dir_b.clone()), ..paired() };
+
+    let (on_a, on_b) = sync(&a, &options_a, &b, &options_b).await;
+    assert_eq!((on_a.attachments_sent, on_a.attachments_received), (1, 1));
+    assert_eq!((on_b.attachments_sent, on_b.attachments_received), (1, 1));
+    assert_eq!(std::fs::read(dir_b.join("image.png")).unwrap(), vec![1, 2, 3]);
+    assert_eq!(std::fs::read(dir_a.join("notes.pdf")).unwrap(), b"pdf");
+    assert_eq!(std::fs::read(dir_b.join("same.txt")).unwrap(), b"b");
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[tokio::test]
+async fn refuses_to_sync_with_itself() {
+    let db = Database::new_in_memory().await.unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let none = paired();
+    let (served, connected) = tokio::join!(
+        db.accept_sync(&listener, &none),
+        db.sync_with_peer(address, &none),
+    );
+    assert!(served.is_err());
+    assert!(connected.is_err());
+}
+
+#[tokio::test]
+async fn refuses_a_peer_without_the_pairing_secret() {
+    let a = Database::new_in_memory().await.unwrap();
+    let b = Database::new_in_memory().await.unwrap();
+    a.new_bloc(&bloc("b1", "secret note", 10)).await.unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (options, intruder) = (paired(), SyncOptions { secret: "guess".to_string(), ..Default::default() });
+    let (served, connected) = tokio::join!(
+        a.accept_sync(&listener, &options),
+        b.sync_with_peer(address, &intruder),
+    );
+    assert!(served.is_err());
+    assert!(connected.is_err());
+    assert!(b.get_bloc_by_id("b1".to_string()).await.is_err());
+
+    // sans secret, pas de synchronisation du tout
+    assert!(b.sync_with_peer(address, &SyncOptions::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn prop_edits_and_moves_reach_the_peer() {
+    let a = Database::new_in_memory().await.unwrap();
+    let b = Database::new_in_memory().await.unwrap();
+    let options = paired();
+    a.new_page(&page("p1", "one", 10)).await.unwrap();
+    a.new_page(&page("p2", "two", 10)).await.unwrap();
+    a.new_bloc(&bloc("b1", "note", 10)).await.unwrap();
+    a.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "status".to_string(),
+        value: "todo".to_string(),
+        bloc_id: "b1".to_string(),
+    })
+    .await
+    .unwrap();
+    sync(&a, &options, &b, &options).await;
+
+    // après la première synchronisation, seules la prop et la page changent
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    a.update_prop_value("b1".to_string(), "status".to_string(), "done".to_string()).await.unwrap();
+    a.update_bloc_page_id("b1".to_string(), "p2".to_string()).await.unwrap();
+    sync(&a, &options, &b, &options).await;
+
+    let moved = b.get_bloc_by_id("b1".to_string()).await.unwrap();
+    assert_eq!(moved.page_id, "p2");
+    assert_eq!(b.get_props_by_bloc_id("b1".to_string()).await.unwrap()[0].value, "done");
+}
+
+#[tokio::test]
+async fn refuses_large_frames_before_the_proof() {
+    use tokio::io::AsyncWriteExt;
+
+    let db = Database::new_in_memory().await.unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let options = paired();
+    // un inconnu annonce un Hello de 200 Mio
+    let intruder = async {
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream.write_u32(200 * 1024 * 1024).await.unwrap();
+        stream
+    };
+    let (served, _stream) = tokio::join!(db.accept_sync(&listener, &options), intruder);
+    assert!(served.unwrap_err().to_string().contains("frame too large"));
+}
+
+#[tokio::test]
+async fn deletions_propagate_unless_edited_afterwards() {
+    let a = Database::new_in_memory().await.unwrap();
+    let b = Database::new_in_memory().await.unwrap();
+    let options = paired();
+
+    a.new_page(&page("p1", "shared", 10)).await.unwrap();
+    a.new_bloc(&bloc("b1", "to delete", 10)).await.unwrap();
+    a.new_bloc(&BlocJson { position: "a1".to_string(), ..bloc("b2", "kept", 10) }).await.unwrap();
+    sync(&a, &options, &b, &options).await;
+
+    a.delete_bloc("b1".to_string()).await.unwrap();
+    a.delete_bloc("b2".to_string()).await.unwrap();
+    // modifié sur b après la suppression sur a : la modification l'emporte
+    let later = tauritest_db::database::now_millis() + 60_000;
+    b.update_bloc(&BlocJson { position: "a1".to_string(), ..bloc("b2", "edited", later) }).await.unwrap();
+
+    let (on_a, on_b) = sync(&a, &options, &b, &options).await;
+    assert_eq!(on_a.deletions_sent, 2);
+    assert_eq!(on_b.deletions_applied, 1);
+    assert!(b.get_bloc_by_id("b1".to_string()).await.is_err());
+    assert_eq!(content(&a, "b2").await, "edited");
+    assert_eq!(content(&b, "b2").await, "edited");
+
+    // le bloc supprimé ne revient pas à la synchronisation suivante
+    sync(&a, &options, &b, &options).await;
+    assert!(a.get_bloc_by_id("b1".to_string()).await.is_err());
+}
@@ -0,0 +1,144 @@
+use serde_json::json;
+use tauritest_db::{BlocJson, Database, PageJson, TagEntity};
+
+fn page(id: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: "/".to_string(),
+        title: "page".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn bloc_with_hashtag(id: &str, hashtag: &str) -> BlocJson {
+    let content = json!({
+        "type": "paragraph",
+        "children": [
+            { "type": "text", "text": "voir " },
+            { "type": "hashtag", "text": hashtag },
+        ],
+    });
+    BlocJson {
+        id: Some(id.to_string()),
+        position: "a0".to_string(),
+        content: content.to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn names(tags: &[tauritest_db::TagJson]) -> Vec<&str> {
+    tags.iter().map(|tag| tag.name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn tags_form_a_hierarchy_shared_by_all_entities() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1")).await.unwrap();
+
+    let alpha = db.new_tag(" #project / alpha/".to_string(), Some("#FF5252".to_string())).await.unwrap();
+    assert_eq!(alpha.name, "project/alpha");
+    assert_eq!(alpha.color.as_deref(), Some("#FF5252"));
+    // même nom, casse différente : la même étiquette
+    assert_eq!(db.new_tag("Project/Alpha".to_string(), None).await.unwrap().id, alpha.id);
+
+    db.tag_entity(TagEntity::Page, "p1".to_string(), "project/alpha".to_string()).await.unwrap();
+    db.tag_entity(TagEntity::Event, "evt-1".to_string(), "project".to_string()).await.unwrap();
+    db.set_entity_tags(TagEntity::Card, "card-1".to_string(), &["project/beta".to_string(), "urgent".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(names(&db.get_tags().await.unwrap()), vec!["project", "project/alpha", "project/beta", "urgent"]);
+
+    let direct = db.get_tagged("project".to_string(), false).await.unwrap();
+    assert_eq!(direct.len(), 1);
+    assert_eq!((direct[0].entity, direct[0].entity_id.as_str()), (TagEntity::Event, "evt-1"));
+    let all = db.get_tagged("project".to_string(), true).await.unwrap();
+    assert_eq!(all.len(), 3);
+
+    // remplacer les étiquettes d'une carte retire les anciennes
+    let card = db.set_entity_tags(TagEntity::Card, "card-1".to_string(), &["urgent".to_string()]).await.unwrap();
+    assert_eq!(names(&card), vec!["urgent"]);
+    assert!(db.untag_entity(TagEntity::Card, "card-1".to_string(), "urgent".to_string()).await.unwrap());
+    assert!(db.get_entity_tags(TagEntity::Card, "card-1".to_string()).await.unwrap().is_empty());
+
+    // page supprimée : n'est plus listée
+    db.delete_page("p1".to_string()).await.unwrap();
+    assert!(db.get_tagged("project/alpha".to_string(), false).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn renaming_moves_descendants_and_rewrites_hashtags() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_bloc(&bloc_with_hashtag("b1", "#alpha")).await.unwrap();
+    db.tag_entity(TagEntity::Card, "c1".to_string(), "alpha/docs".to_string()).await.unwrap();
+    db.new_tag("beta".to_string(), None).await.unwrap();
+
+    let alpha = db.get_tags().await.unwrap().into_iter().find(|tag| tag.name == "alpha").unwrap();
+    assert!(db.rename_tag(alpha.id.clone(), "beta".to_string()).await.is_err());
+    assert!(db.rename_tag(alpha.id.clone(), "alpha/sub".to_string()).await.is_err());
+
+    let renamed = db.rename_tag(alpha.id.clone(), "projects/alpha".to_string()).await.unwrap();
+    assert_eq!((renamed.name.as_str(), renamed.usage), ("projects/alpha", 1));
+    assert_eq!(
+        names(&db.get_tags().await.unwrap()),
+        vec!["beta", "projects", "projects/alpha", "projects/alpha/docs"]
+    );
+    assert_eq!(db.get_tagged("projects/alpha/docs".to_string(), false).await.unwrap()[0].entity_id, "c1");
+
+    let content = db.get_bloc_by_id("b1".to_string()).await.unwrap().content;
+    assert!(content.contains("#projects/alpha"));
+    // le registre n'est pas journalisé : le renommage n'est pas une étape
+    // d'annulation, annuler ne peut pas désaccorder blocs et étiquettes
+    assert_eq!(db.undo_status().await.unwrap().undo.as_deref(), Some("insert bloc"));
+    db.undo().await.unwrap();
+    assert_eq!(names(&db.get_tags().await.unwrap()), vec!["beta", "projects", "projects/alpha", "projects/alpha/docs"]);
+}
+
+#[tokio::test]
+async fn hashtags_are_indexed_when_bloc_content_is_written() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1")).await.unwrap();
+    db.new_bloc(&bloc_with_hashtag("b1", "#project/alpha")).await.unwrap();
+    let tagged = db.get_tagged("project".to_string(), true).await.unwrap();
+    assert_eq!(tagged.len(), 1);
+    assert_eq!((tagged[0].entity, tagged[0].entity_id.as_str()), (TagEntity::Bloc, "b1"));
+
+    // une étiquette posée à la main reste quand le contenu change
+    db.tag_entity(TagEntity::Bloc, "b1".to_string(), "manual".to_string()).await.unwrap();
+    let mut bloc = bloc_with_hashtag("b1", "#beta");
+    db.update_bloc(&bloc).await.unwrap();
+    assert_eq!(names(&db.get_entity_tags(TagEntity::Bloc, "b1".to_string()).await.unwrap()), vec!["beta", "manual"]);
+
+    bloc.content = bloc_with_hashtag("b1", "#gamma").content;
+    db.update_bloc_content("b1".to_string(), bloc.content.clone(), 1).await.unwrap();
+    assert_eq!(names(&db.get_entity_tags(TagEntity::Bloc, "b1".to_string()).await.unwrap()), vec!["gamma", "manual"]);
+
+    // annuler la saisie rend au bloc ses étiquettes d'avant
+    db.undo().await.unwrap();
+    assert_eq!(names(&db.get_entity_tags(TagEntity::Bloc, "b1".to_string()).await.unwrap()), vec!["beta", "manual"]);
+}
+
+#[tokio::test]
+async fn merging_combines_items_and_subtrees() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.tag_entity(TagEntity::Card, "c1".to_string(), "todo".to_string()).await.unwrap();
+    db.tag_entity(TagEntity::Card, "c2".to_string(), "todo/later".to_string()).await.unwrap();
+    db.tag_entity(TagEntity::Card, "c1".to_string(), "tasks".to_string()).await.unwrap();
+    db.tag_entity(TagEntity::Event, "e1".to_string(), "tasks/later".to_string()).await.unwrap();
+
+    let tags = db.get_tags().await.unwrap();
+    let id = |name: &str| tags.iter().find(|tag| tag.name == name).unwrap().id.clone();
+    let merged = db.merge_tags(id("todo"), id("tasks")).await.unwrap();
+    // c1 portait déjà les deux : compté une fois
+    assert_eq!(merged.usage, 1);
+    assert_eq!(names(&db.get_tags().await.unwrap()), vec!["tasks", "tasks/later"]);
+    assert_eq!(db.get_tagged("tasks/later".to_string(), false).await.unwrap().len(), 2);
+
+    assert_eq!(db.delete_tag(id("tasks")).await.unwrap(), 2);
+    assert!(db.get_tags().await.unwrap().is_empty());
+    assert!(db.get_entity_tags(TagEntity::Card, "c1".to_string()).await.unwrap().is_empty());
+}
@@ -0,0 +1,178 @@
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson, Recurrence, TemplateJson};
+
+fn page(id: &str, title: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: "/templates".to_string(),
+        title: title.to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn bloc(id: &str, position: &str, text: &str) -> BlocJson {
+    let content = json!({
+        "type": "paragraph",
+        "children": [{ "type": "text", "text": text }],
+        "$": { "id": id, "position": position },
+    });
+    BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: content.to_string(),
+        page_id: "t1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn template(recurrence: Option<Recurrence>) -> TemplateJson {
+    TemplateJson {
+        page_id: "t1".to_string(),
+        name: "Réunion".to_string(),
+        recurrence,
+        target_path: "/journal".to_string(),
+        title_pattern: "Journal {{date}}".to_string(),
+        last_run: None,
+    }
+}
+
+async fn setup() -> Database {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("t1", "modèle")).await.unwrap();
+    db.new_bloc(&bloc("b1", "a0", "{{title}} du {{date}} ({{weekday}})")).await.unwrap();
+    db.new_bloc(&bloc("b2", "a1", "Animateur : {{ host }}, {{inconnu}}")).await.unwrap();
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "owner".to_string(),
+        value: "{{host}}".to_string(),
+        bloc_id: "b2".to_string(),
+    })
+    .await
+    .unwrap();
+    db
+}
+
+fn text(bloc: &BlocJson) -> String {
+    let content: Value = serde_json::from_str(&bloc.content).unwrap();
+    content["children"][0]["text"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn instantiates_a_template_with_fresh_ids_and_variables() {
+    let db = setup().await;
+    assert!(db.set_template(&template(None)).await.unwrap());
+    assert_eq!(
+        db.template_variables("t1".to_string()).await.unwrap(),
+        vec!["host", "inconnu"]
+    );
+
+    let variables = HashMap::from([("host".to_string(), "Alice".to_string())]);
+    let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+    let page_id = db
+        .instantiate_template_on(
+            "t1".to_string(),
+            "/meetings".to_string(),
+            "Point {{host}}".to_string(),
+            &variables,
+            date,
+        )
+        .await
+        .unwrap();
+
+    let new_page = db.get_page_by_id(page_id.clone()).await.unwrap();
+    assert_eq!((new_page.path.as_str(), new_page.title.as_str()), ("/meetings", "Point Alice"));
+
+    let blocs = db.get_blocs_by_page_id(page_id).await.unwrap();
+    assert_eq!(blocs.len(), 2);
+    assert_eq!(text(&blocs[0]), "Point Alice du 2024-03-15 (Friday)");
+    // variable sans valeur laissée telle quelle
+    assert_eq!(text(&blocs[1]), "Animateur : Alice, {{inconnu}}");
+
+    let id = blocs[1].id.clone().unwrap();
+    assert_ne!(id, "b2");
+    let content: Value = serde_json::from_str(&blocs[1].content).unwrap();
+    assert_eq!(content["$"]["id"], id.as_str());
+    let props = db.get_props_by_bloc_id(id).await.unwrap();
+    assert_eq!((props[0].key.as_str(), props[0].value.as_str()), ("owner", "Alice"));
+    assert_ne!(props[0].id.as_deref(), Some("r1"));
+
+    // le modèle n'est pas modifié, et l'instanciation s'annule d'un coup
+    assert_eq!(text(&db.get_bloc_by_id("b1".to_string()).await.unwrap()), "{{title}} du {{date}} ({{weekday}})");
+    assert_eq!(db.undo().await.unwrap().unwrap().label, "instantiate template");
+    assert!(db.get_page_by_id(new_page.id.unwrap()).await.is_err());
+}
+
+#[tokio::test]
+async fn only_templates_can_be_instantiated() {
+    let db = setup().await;
+    let none = HashMap::new();
+    assert!(db
+        .instantiate_template("t1".to_string(), "/".to_string(), "x".to_string(), &none)
+        .await
+        .is_err());
+
+    db.set_template(&template(None)).await.unwrap();
+    assert_eq!(db.get_templates().await.unwrap().len(), 1);
+    assert!(db.unset_template("t1".to_string()).await.unwrap());
+    assert!(db.get_templates().await.unwrap().is_empty());
+    // page inconnue
+    let mut missing = template(None);
+    missing.page_id = "nope".to_string();
+    assert!(!db.set_template(&missing).await.unwrap());
+}
+
+#[tokio::test]
+async fn recurring_templates_need_a_real_title_pattern() {
+    let db = setup().await;
+    let self_titled = TemplateJson { title_pattern: "{{title}}".to_string(), ..template(Some(Recurrence::Daily)) };
+    assert!(db.set_template(&self_titled).await.is_err());
+
+    // motif par défaut : la date du jour
+    let defaulted: TemplateJson = serde_json::from_value(json!({
+        "page_id": "t1",
+        "name": "Journal",
+        "recurrence": "daily",
+    }))
+    .unwrap();
+    db.set_template(&defaulted).await.unwrap();
+    let created = db.run_recurring_templates(NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()).await.unwrap();
+    assert_eq!(db.get_page_by_id(created[0].clone()).await.unwrap().title, "2024-03-15");
+}
+
+#[tokio::test]
+async fn recurring_templates_create_one_daily_note_per_day() {
+    let db = setup().await;
+    db.set_template(&template(Some(Recurrence::Daily))).await.unwrap();
+    let day = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+
+    let created = db.run_recurring_templates(day).await.unwrap();
+    assert_eq!(created.len(), 1);
+    let note = db.get_page_by_id(created[0].clone()).await.unwrap();
+    assert_eq!((note.path.as_str(), note.title.as_str()), ("/journal", "Journal 2024-03-15"));
+    assert_eq!(db.get_templates().await.unwrap()[0].last_run.as_deref(), Some("2024-03-15"));
+
+    assert!(db.run_recurring_templates(day).await.unwrap().is_empty());
+    let next = day.succ_opt().unwrap();
+    assert_eq!(db.run_recurring_templates(next).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn daily_notes_stay_out_of_the_undo_history() {
+    let db = setup().await;
+    db.set_template(&template(Some(Recurrence::Daily))).await.unwrap();
+    db.update_page_title("t1".to_string(), "renommé".to_string()).await.unwrap();
+    db.undo().await.unwrap();
+    let before = db.undo_status().await.unwrap();
+    assert!(before.redo.is_some());
+
+    let created = db.run_recurring_templates(NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()).await.unwrap();
+    assert_eq!(created.len(), 1);
+    let after = db.undo_status().await.unwrap();
+    assert_eq!((after.undo, after.redo), (before.undo, before.redo));
+}
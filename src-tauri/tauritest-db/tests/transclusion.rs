@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson};
+
+fn page(id: &str, title: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: "/".to_string(),
+        title: title.to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+async fn setup() -> Database {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "Source")).await.unwrap();
+    db.new_page(&page("p2", "Cible")).await.unwrap();
+    let content = json!({
+        "type": "paragraph",
+        "children": [{ "type": "text", "text": "paragraphe partagé" }],
+        "$": { "id": "s1", "position": "a0" },
+    });
+    db.new_bloc(&BlocJson {
+        id: Some("s1".to_string()),
+        position: "a0".to_string(),
+        content: content.to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    })
+    .await
+    .unwrap();
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "status".to_string(),
+        value: "done".to_string(),
+        bloc_id: "s1".to_string(),
+    })
+    .await
+    .unwrap();
+    db
+}
+
+fn node(bloc: &BlocJson) -> Value {
+    serde_json::from_str(&bloc.content).unwrap()
+}
+
+#[tokio::test]
+async fn resolves_references_when_loading_a_page() {
+    let db = setup().await;
+    let r1 = db.new_bloc_reference("p2".to_string(), "s1".to_string(), "a0".to_string()).await.unwrap();
+    // une référence vers une référence affiche la source finale
+    let r2 = db.new_bloc_reference("p2".to_string(), r1.clone(), "a1".to_string()).await.unwrap();
+    assert!(db.new_bloc_reference("p2".to_string(), "absent".to_string(), "a2".to_string()).await.is_err());
+
+    let references = db.get_page_references("p2".to_string()).await.unwrap();
+    assert_eq!(references.len(), 2);
+    for id in [&r1, &r2] {
+        let resolved = &references[id];
+        assert_eq!(resolved["bloc_id"], json!("s1"));
+        assert_eq!(resolved["page_id"], json!("p1"));
+        assert_eq!(resolved["content"]["children"][0]["text"], json!("paragraphe partagé"));
+    }
+    // le chargement d'une page renvoie les sources avec les blocs
+    let page = db.get_page_blocs("p2".to_string()).await.unwrap();
+    assert_eq!(page.references, references);
+    assert_eq!(page.blocs.len(), 2);
+    let chunk = db.get_blocs_chunk("p2".to_string(), None, 10).await.unwrap();
+    assert_eq!(chunk.references, references);
+    let mut streamed = HashMap::new();
+    db.stream_blocs_by_page_id("p2".to_string(), 1, |_, chunk| {
+        streamed.extend(chunk);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(streamed, references);
+    // l'export Markdown reprend le texte de la source
+    assert_eq!(
+        db.page_to_markdown("p2".to_string()).await.unwrap(),
+        "# Cible\n\nparagraphe partagé\n\nparagraphe partagé\n"
+    );
+
+    // "où ce bloc est-il utilisé ?"
+    let used = db.get_bloc_references("s1".to_string()).await.unwrap();
+    assert_eq!(used.len(), 1);
+    assert_eq!((used[0].bloc_id.as_str(), used[0].page_title.as_str()), (r1.as_str(), "Cible"));
+    assert_eq!(db.get_bloc_references(r1.clone()).await.unwrap()[0].bloc_id, r2);
+
+    // source supprimée
+    db.delete_bloc("s1".to_string()).await.unwrap();
+    let references = db.get_page_references("p2".to_string()).await.unwrap();
+    assert_eq!(references[&r1], json!({ "error": "missing", "bloc_id": "s1" }));
+}
+
+#[tokio::test]
+async fn detects_cycles() {
+    let db = setup().await;
+    let r1 = db.new_bloc_reference("p2".to_string(), "s1".to_string(), "a0".to_string()).await.unwrap();
+    let r2 = db.new_bloc_reference("p2".to_string(), r1.clone(), "a1".to_string()).await.unwrap();
+
+    assert!(db.set_reference_source(r1.clone(), r2.clone()).await.is_err());
+    assert!(db.set_reference_source(r1.clone(), r1.clone()).await.is_err());
+    assert!(db.set_reference_source("s1".to_string(), r1.clone()).await.is_err());
+
+    // une boucle écrite par l'éditeur ne bloque pas la lecture
+    let mut bloc = db.get_bloc_by_id(r1.clone()).await.unwrap();
+    let mut content = node(&bloc);
+    content["source"] = json!(r2);
+    bloc.content = content.to_string();
+    db.update_bloc(&bloc).await.unwrap();
+    let references = db.get_page_references("p2".to_string()).await.unwrap();
+    assert_eq!(references.len(), 2);
+    assert!(references.values().all(|resolved| *resolved == json!({ "error": "cycle" })));
+}
+
+#[tokio::test]
+async fn detaches_a_reference_into_a_copy() {
+    let db = setup().await;
+    let r1 = db.new_bloc_reference("p2".to_string(), "s1".to_string(), "a3".to_string()).await.unwrap();
+    assert!(db.detach_bloc_reference("s1".to_string()).await.is_err());
+
+    let detached = db.detach_bloc_reference(r1.clone()).await.unwrap();
+    assert_eq!(detached.bloc_type, "paragraph");
+    assert_eq!(detached.position, "a3");
+    let content = node(&detached);
+    assert_eq!(content["$"], json!({ "id": r1, "position": "a3" }));
+    assert_eq!(content["children"][0]["text"], json!("paragraphe partagé"));
+    assert_eq!(db.get_props_by_bloc_id(r1.clone()).await.unwrap()[0].value, "done");
+    assert!(db.get_bloc_references("s1".to_string()).await.unwrap().is_empty());
+
+    // la copie est indépendante de la source
+    db.delete_bloc("s1".to_string()).await.unwrap();
+    assert!(db.get_page_references("p2".to_string()).await.unwrap().is_empty());
+
+    db.undo().await.unwrap();
+    db.undo().await.unwrap();
+    assert_eq!(db.get_bloc_by_id(r1).await.unwrap().bloc_type, "bloc-reference");
+}
@@ -0,0 +1,164 @@
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson};
+
+fn page(id: &str, path: &str) -> PageJson {
+    PageJson {
+        id: Some(id.to_string()),
+        path: path.to_string(),
+        title: "page".to_string(),
+        cache: String::new(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn bloc(id: &str, content: &str) -> BlocJson {
+    BlocJson {
+        id: Some(id.to_string()),
+        // "b1" -> "a1" : une position distincte par bloc de la page
+        position: format!("a{}", &id[1..]),
+        content: content.to_string(),
+        page_id: "p1".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+#[tokio::test]
+async fn undo_and_redo_a_page_move_and_deletion() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "/a")).await.unwrap();
+    db.update_page_path("p1".to_string(), "/b".to_string()).await.unwrap();
+    db.delete_page("p1".to_string()).await.unwrap();
+    assert_eq!(db.undo_status().await.unwrap().undo.as_deref(), Some("delete page"));
+
+    let step = db.undo().await.unwrap().unwrap();
+    assert_eq!(step.label, "delete page");
+    assert_eq!(db.get_page_by_id("p1".to_string()).await.unwrap().path, "/b");
+
+    db.undo().await.unwrap();
+    assert_eq!(db.get_page_by_id("p1".to_string()).await.unwrap().path, "/a");
+
+    db.redo().await.unwrap();
+    assert_eq!(db.get_page_by_id("p1".to_string()).await.unwrap().path, "/b");
+    db.redo().await.unwrap();
+    assert!(db.get_page_by_id("p1".to_string()).await.is_err());
+    assert!(db.redo().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn deleting_a_page_with_blocs_is_one_step() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "/a")).await.unwrap();
+    db.new_bloc(&bloc("b1", "kept")).await.unwrap();
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "status".to_string(),
+        value: "todo".to_string(),
+        bloc_id: "b1".to_string(),
+    })
+    .await
+    .unwrap();
+
+    assert!(db.delete_page("p1".to_string()).await.unwrap());
+    assert!(db.get_bloc_by_id("b1".to_string()).await.is_err());
+
+    assert_eq!(db.undo().await.unwrap().unwrap().label, "delete page");
+    assert_eq!(db.get_page_by_id("p1".to_string()).await.unwrap().path, "/a");
+    assert_eq!(db.get_bloc_by_id("b1".to_string()).await.unwrap().content, "kept");
+    assert_eq!(db.get_props_by_bloc_id("b1".to_string()).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn merged_blocs_are_not_undo_steps() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_bloc(&bloc("b1", "typed")).await.unwrap();
+    db.replace_page_blocs("p1".to_string(), &[bloc("b1", "merged")]).await.unwrap();
+
+    // l'annulation reprend la dernière action de l'utilisateur
+    assert_eq!(db.undo_history(10).await.unwrap().len(), 1);
+    assert!(db.undo().await.unwrap().is_some());
+    assert!(db.get_bloc_by_id("b1".to_string()).await.is_err());
+}
+
+#[tokio::test]
+async fn bloc_content_and_props_are_restored() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_bloc(&bloc("b1", "first")).await.unwrap();
+    let checksum = db.get_checksum("b1".to_string()).await.unwrap();
+    db.update_bloc_content("b1".to_string(), "second".to_string(), 5).await.unwrap();
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "status".to_string(),
+        value: "todo".to_string(),
+        bloc_id: "b1".to_string(),
+    })
+    .await
+    .unwrap();
+    db.update_prop_value("b1".to_string(), "status".to_string(), "done".to_string()).await.unwrap();
+
+    db.undo().await.unwrap();
+    assert_eq!(db.get_props_by_bloc_id("b1".to_string()).await.unwrap()[0].value, "todo");
+    db.undo().await.unwrap();
+    assert!(db.get_props_by_bloc_id("b1".to_string()).await.unwrap().is_empty());
+    db.undo().await.unwrap();
+    let restored = db.get_bloc_by_id("b1".to_string()).await.unwrap();
+    assert_eq!(restored.content, "first");
+    assert_eq!(restored.updated_at, 0);
+    assert_eq!(db.get_checksum("b1".to_string()).await.unwrap(), checksum);
+}
+
+#[tokio::test]
+async fn bulk_writes_undo_as_one_step() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_bloc(&bloc("b1", "x")).await.unwrap();
+    db.new_bloc(&bloc("b2", "y")).await.unwrap();
+    db.delete_bloc_by_page_id("p1".to_string()).await.unwrap();
+    assert!(db.get_blocs_by_page_id("p1".to_string()).await.unwrap().is_empty());
+
+    let step = db.undo().await.unwrap().unwrap();
+    assert_eq!(step.label, "delete page blocs");
+    assert_eq!(db.get_blocs_by_page_id("p1".to_string()).await.unwrap().len(), 2);
+
+    // un groupe vide ne laisse pas d'étape
+    let before = db.undo_history(100).await.unwrap().len();
+    db.delete_bloc_by_page_id("nothing".to_string()).await.unwrap();
+    assert_eq!(db.undo_history(100).await.unwrap().len(), before);
+}
+
+#[tokio::test]
+async fn a_new_write_clears_the_redo_stack() {
+    let db = Database::new_in_memory().await.unwrap();
+    db.new_page(&page("p1", "/a")).await.unwrap();
+    db.update_page_path("p1".to_string(), "/b".to_string()).await.unwrap();
+    db.undo().await.unwrap();
+    assert!(db.undo_status().await.unwrap().redo.is_some());
+
+    db.update_page_title("p1".to_string(), "renamed".to_string()).await.unwrap();
+    let status = db.undo_status().await.unwrap();
+    assert!(status.redo.is_none());
+    assert_eq!(status.undo.as_deref(), Some("update page"));
+
+    // undo / redo ne s'enregistrent pas eux-mêmes
+    let history = db.undo_history(10).await.unwrap();
+    assert_eq!(history.len(), 2);
+}
+
+#[tokio::test]
+async fn journal_survives_reopening_the_database() {
+    let dir = std::env::temp_dir().join(format!("tauritest-undo-{}", std::process::id()));
+    let path = dir.join("notes.db");
+    let path = path.to_str().unwrap();
+
+    let db = Database::new(path).await.unwrap();
+    db.new_page(&page("p1", "/a")).await.unwrap();
+    db.update_page_path("p1".to_string(), "/moved".to_string()).await.unwrap();
+    drop(db);
+
+    let db = Database::new(path).await.unwrap();
+    db.undo().await.unwrap();
+    assert_eq!(db.get_page_by_id("p1".to_string()).await.unwrap().path, "/a");
+
+    drop(db);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,210 @@
+use tauritest_db::{BlocJson, Database, PageJson, PropsJson};
+
+async fn db() -> Database {
+    Database::new_in_memory().await.unwrap()
+}
+
+const MARKDOWN: &str = "# Intro
+
+Some text
+on two lines
+
+- [ ] buy milk
+- [x] call back
+
+```rust
+fn main() {}
+```
+
+> quoted
+
+1. one
+2. two
+";
+
+#[tokio::test]
+async fn markdown_round_trip() {
+    let db = db().await;
+    let page_id = db
+        .new_page_from_markdown("/notes".to_string(), "Title".to_string(), MARKDOWN)
+        .await
+        .unwrap();
+
+    let blocs = db.get_blocs_by_page_id(page_id.clone()).await.unwrap();
+    let types: Vec<&str> = blocs.iter().map(|b| b.bloc_type.as_str()).collect();
+    assert_eq!(types, vec!["heading", "paragraph", "list", "code", "quote", "list"]);
+
+    let markdown = db.page_to_markdown(page_id).await.unwrap();
+    assert_eq!(markdown, format!("# Title\n\n{}", MARKDOWN));
+}
+
+#[tokio::test]
+async fn markdown_blocs_carry_editor_state() {
+    let db = db().await;
+    let page_id = db
+        .new_page_from_markdown("/".to_string(), "t".to_string(), "a\n\nb")
+        .await
+        .unwrap();
+
+    let blocs = db.get_blocs_by_page_id(page_id).await.unwrap();
+    assert_eq!(blocs.len(), 2);
+    for bloc in &blocs {
+        let node: serde_json::Value = serde_json::from_str(&bloc.content).unwrap();
+        assert_eq!(node["$"]["id"], bloc.id.clone().unwrap());
+        assert_eq!(node["$"]["position"], bloc.position);
+    }
+    assert!(blocs[0].position < blocs[1].position);
+}
+
+#[tokio::test]
+async fn empty_markdown_still_creates_a_bloc() {
+    let db = db().await;
+    let page_id = db
+        .new_page_from_markdown("/".to_string(), "empty".to_string(), "")
+        .await
+        .unwrap();
+    let blocs = db.get_blocs_by_page_id(page_id).await.unwrap();
+    assert_eq!(blocs.len(), 1);
+    assert_eq!(blocs[0].bloc_type, "paragraph");
+}
+
+#[tokio::test]
+async fn export_then_import_into_empty_database() {
+    let source = db().await;
+    source
+        .new_page(&PageJson {
+            id: Some("p1".to_string()),
+            path: "/".to_string(),
+            title: "page".to_string(),
+            cache: "cached".to_string(),
+            created_at: 1,
+            updated_at: 2,
+        })
+        .await
+        .unwrap();
+    source
+        .new_bloc(&BlocJson {
+            id: Some("b1".to_string()),
+            position: "a0".to_string(),
+            content: "{\"type\":\"paragraph\",\"children\":[]}".to_string(),
+            page_id: "p1".to_string(),
+            bloc_type: "paragraph".to_string(),
+            created_at: 1,
+            updated_at: 2,
+        })
+        .await
+        .unwrap();
+    source
+        .new_prop(&PropsJson {
+            id: Some("r1".to_string()),
+            key: "status".to_string(),
+            value: "todo".to_string(),
+            bloc_id: "b1".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let export = source.export_workspace().await.unwrap();
+    let json = serde_json::to_string(&export).unwrap();
+
+    let target = db().await;
+    let report = target
+        .import_workspace(&serde_json::from_str(&json).unwrap())
+        .await
+        .unwrap();
+    assert_eq!((report.pages, report.blocs, report.props), (1, 1, 1));
+
+    assert_eq!(target.get_page_cache("p1".to_string()).await.unwrap(), "cached");
+    assert_eq!(
+        target.get_checksum("b1".to_string()).await.unwrap(),
+        source.get_checksum("b1".to_string()).await.unwrap()
+    );
+    assert_eq!(target.get_props_by_bloc_id("b1".to_string()).await.unwrap()[0].value, "todo");
+
+    // réimporter remplace au lieu de dupliquer
+    target.import_workspace(&export).await.unwrap();
+    assert_eq!(target.get_blocs_by_page_id("p1".to_string()).await.unwrap().len(), 1);
+    assert!(target.check_integrity().await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn import_rejects_newer_format() {
+    let db = db().await;
+    let mut export = db.export_workspace().await.unwrap();
+    export.format_version = Database::EXPORT_FORMAT_VERSION + 1;
+    assert!(db.import_workspace(&export).await.is_err());
+}
+
+#[tokio::test]
+async fn search_matches_titles_and_bloc_text() {
+    let db = db().await;
+    let page_id = db
+        .new_page_from_markdown("/".to_string(), "Groceries".to_string(), "- [ ] buy Milk\n\nnothing here")
+        .await
+        .unwrap();
+
+    let hits = db.search("milk".to_string(), 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].page_id, page_id);
+    assert!(hits[0].bloc_id.is_some());
+    assert_eq!(hits[0].excerpt, "buy Milk");
+
+    let hits = db.search("grocer".to_string(), 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert!(hits[0].bloc_id.is_none());
+
+    // les clés JSON ne doivent pas correspondre
+    assert!(db.search("children".to_string(), 10).await.unwrap().is_empty());
+    assert!(db.search("  ".to_string(), 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn search_excerpt_survives_lowercase_that_changes_length() {
+    let db = db().await;
+    // "İ" devient deux caractères en minuscules
+    let text = format!("{}x", "İ".repeat(100));
+    db.new_page_from_markdown("/".to_string(), "Turc".to_string(), &text)
+        .await
+        .unwrap();
+
+    let hits = db.search("x".to_string(), 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].excerpt, format!("…{}x", "İ".repeat(40)));
+}
+
+#[tokio::test]
+async fn integrity_reports_orphans_and_duplicates() {
+    let db = db().await;
+    assert!(db.check_integrity().await.unwrap().is_ok());
+
+    let bloc = |id: &str, position: &str| BlocJson {
+        id: Some(id.to_string()),
+        position: position.to_string(),
+        content: "not json".to_string(),
+        page_id: "ghost".to_string(),
+        bloc_type: "paragraph".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    };
+    db.new_bloc(&bloc("b1", "a0")).await.unwrap();
+    // new_bloc refuse une clé prise : le doublon arrive par un déplacement
+    db.new_bloc(&BlocJson { page_id: "elsewhere".to_string(), ..bloc("b2", "a0") }).await.unwrap();
+    db.update_bloc_page_id("b2".to_string(), "ghost".to_string()).await.unwrap();
+    db.new_prop(&PropsJson {
+        id: Some("r1".to_string()),
+        key: "k".to_string(),
+        value: "v".to_string(),
+        bloc_id: "gone".to_string(),
+    })
+    .await
+    .unwrap();
+
+    let report = db.check_integrity().await.unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.sqlite, vec!["ok"]);
+    assert_eq!(report.orphan_blocs.len(), 2);
+    assert_eq!(report.orphan_props, vec!["r1"]);
+    assert_eq!(report.invalid_contents.len(), 2);
+    assert_eq!(report.duplicate_positions, vec!["ghost a0"]);
+    assert!(report.checksum_mismatches.is_empty());
+}
@@ -110,10 +110,10 @@ describe('useBlocDatabase', () => {
 
   describe('getBlocsByPageId', () => {
     it('should get blocs by page id', async () => {
-      (invoke as any).mockResolvedValueOnce([mockBloc]);
+      (invoke as any).mockResolvedValueOnce({ blocs: [mockBloc], references: {} });
       const result = await getBlocsByPageId(mockBloc.page_id);
       expect(invoke).toHaveBeenCalledWith('get_blocs_by_page_id', { page_id: mockBloc.page_id });
-      expect(result).toEqual([mockBloc]);
+      expect(result).toEqual({ blocs: [mockBloc], references: {} });
     });
   });
-});
\ No newline at end of file
+});
\ No newline at end of file
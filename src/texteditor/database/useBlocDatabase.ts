@@ -10,6 +10,12 @@ export interface BlocJson {
     updated_at: number,
 }
 
+// blocs d'une page avec les sources de leurs blocs références
+export interface PageBlocs {
+    blocs: BlocJson[],
+    references: Record<string, unknown>,
+}
+
 export const SUCCESS: number = 1;
 export const ERROR: number = -1;
 export const NO_CHANGE: number = 0;
@@ -112,10 +118,10 @@ export const getBlocById = async (id: string): Promise<BlocJson> => {
   }
 }
 
-export const getBlocsByPageId = async (pageId: string): Promise<BlocJson[]> => {
+export const getBlocsByPageId = async (pageId: string): Promise<PageBlocs> => {
   try {
-    let blocs = await invoke('get_blocs_by_page_id', { pageId: pageId }) as BlocJson[];
-    return blocs;
+    let page = await invoke('get_blocs_by_page_id', { pageId: pageId }) as PageBlocs;
+    return page;
   } catch (error) {
     console.error('Failed to initialize database:', error);
     throw error;
@@ -11,7 +11,7 @@ import {
   updatePage,
 } from "../database/usePageDatabase";
 import {
-  BlocJson,
+  PageBlocs,
   getBlocsByPageId,
   newBloc,
 } from "../database/useBlocDatabase";
@@ -103,7 +103,7 @@ export function useFile() {
   };
 
   async function reconstruction(page: PageJson) {
-    const blocs: BlocJson[] = await getBlocsByPageId(page.id);
+    const { blocs }: PageBlocs = await getBlocsByPageId(page.id);
     const contents = blocs.map((bloc) => {
       let content = JSON.parse(bloc.content);
       content.$.id = bloc.id;